@@ -1,12 +1,43 @@
-use std::{env, path::PathBuf};
+use std::{
+    collections::HashMap,
+    env,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use crate::models::file_info::MimeSettings;
 
 #[derive(Clone, Debug)]
 pub struct Config {
     pub root_dir: String,
+    pub bind_addr: IpAddr,
     pub port: u16,
     pub log_level: String,
     pub cache_dir: PathBuf,
     pub log_dir: PathBuf,
+    pub log_skip_paths: Vec<String>,
+    pub log_body_limit: usize,
+    pub request_timeout_secs: u64,
+    pub listing_cache_ttl_secs: u64,
+    pub listing_cache_size: usize,
+    pub thumb_concurrency: usize,
+    pub thumb_cache_max_age_secs: u64,
+    pub thumb_fallback: bool,
+    pub thumb_seek_percent: u32,
+    pub image_cache_max_age_secs: u64,
+    pub max_text_file_size: u64,
+    pub max_search_results: usize,
+    pub search_timeout_secs: u64,
+    pub mime: MimeSettings,
+    pub video_extensions: Vec<String>,
+    pub ffmpeg_path: String,
+    pub ffprobe_path: String,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    pub admin_token: Option<String>,
+    pub web_root: PathBuf,
+    pub spa_index: PathBuf,
+    pub spa_watch: bool,
 }
 
 impl Config {
@@ -14,7 +45,13 @@ impl Config {
         let _ = dotenvy::dotenv;
 
         let root_dir = env::var("FILE_PI_ROOT_DIR").unwrap_or_else(|_| ".".to_string());
-        let cache_dir = PathBuf::from(&root_dir).join(".cache");
+        // Thumbnails, previews, tags and favorites. Left out of listings
+        // while it lies inside the root, see `system_dirs`.
+        let cache_dir = env::var("FILE_PI_CACHE_DIR")
+            .ok()
+            .filter(|dir| !dir.trim().is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(&root_dir).join(syncfusion_fm_backend::CACHE_DIR));
 
         let port = env::var("FILE_PI_PORT")
             .unwrap_or_else(|_| "8080".to_string())
@@ -22,15 +59,303 @@ impl Config {
             .map_err(|_| "Invalid PORT value")
             .unwrap();
 
+        let bind_addr = env::var("FILE_PI_BIND_ADDR")
+            .unwrap_or_else(|_| "0.0.0.0".to_string())
+            .parse::<IpAddr>()
+            .map_err(|_| "Invalid FILE_PI_BIND_ADDR value, expected an IP address".to_string())?;
+
         let log_level = env::var("FILE_PI_LOGLEVEL").unwrap_or_else(|_| "info".to_string());
         let log_dir = env::var("FILE_PI_LOG_DIR").unwrap_or_else(|_| "./logs".to_string());
 
-        Ok(Config {
+        // Comma separated request paths that are only logged at trace level
+        let log_skip_paths = env::var("FILE_PI_LOG_SKIP_PATHS")
+            .unwrap_or_else(|_| "/health,/api/v1/metrics".to_string())
+            .split(',')
+            .map(|path| path.trim().to_string())
+            .filter(|path| !path.is_empty())
+            .collect();
+
+        // Request bodies are logged at debug level up to this many bytes,
+        // 0 disables body logging
+        let log_body_limit = env::var("FILE_PI_LOG_BODY_LIMIT")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse::<usize>()
+            .map_err(|_| "Invalid FILE_PI_LOG_BODY_LIMIT value".to_string())?;
+
+        // 0 disables the timeout
+        let request_timeout_secs = env::var("FILE_PI_REQUEST_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse::<u64>()
+            .map_err(|_| "Invalid FILE_PI_REQUEST_TIMEOUT_SECS value".to_string())?;
+
+        // Either one set to 0 disables the directory listing cache
+        let listing_cache_ttl_secs = env::var("FILE_PI_LISTING_CACHE_TTL_SECS")
+            .unwrap_or_else(|_| "2".to_string())
+            .parse::<u64>()
+            .map_err(|_| "Invalid FILE_PI_LISTING_CACHE_TTL_SECS value".to_string())?;
+
+        let listing_cache_size = env::var("FILE_PI_LISTING_CACHE_SIZE")
+            .unwrap_or_else(|_| "256".to_string())
+            .parse::<usize>()
+            .map_err(|_| "Invalid FILE_PI_LISTING_CACHE_SIZE value".to_string())?;
+
+        // Defaults to one thumbnail job per CPU
+        let thumb_concurrency = match env::var("FILE_PI_THUMB_CONCURRENCY") {
+            Ok(value) => value
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| "Invalid FILE_PI_THUMB_CONCURRENCY value".to_string())?,
+            Err(_) => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        };
+
+        // How long browsers may reuse thumbnails and resized images before
+        // revalidating them with their ETag
+        let thumb_cache_max_age_secs = env::var("FILE_PI_THUMB_CACHE_MAX_AGE")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<u64>()
+            .map_err(|_| "Invalid FILE_PI_THUMB_CACHE_MAX_AGE value".to_string())?;
+
+        // Answer thumbnails that can't be generated with a placeholder icon
+        // instead of an error, requests can still ask for the error
+        let thumb_fallback = env::var("FILE_PI_THUMB_FALLBACK")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .map_err(|_| "Invalid FILE_PI_THUMB_FALLBACK value".to_string())?;
+
+        // Where in a video its thumbnail frame is taken, as a percentage of
+        // its duration. Early frames tend to be intros or black screens.
+        let thumb_seek_percent = env::var("FILE_PI_THUMB_SEEK_PERCENT")
+            .unwrap_or_else(|_| "25".to_string())
+            .parse::<u32>()
+            .ok()
+            .filter(|percent| *percent <= 100)
+            .ok_or_else(|| "Invalid FILE_PI_THUMB_SEEK_PERCENT value".to_string())?;
+
+        // How long browsers may reuse Syncfusion image previews
+        let image_cache_max_age_secs = env::var("FILE_PI_IMAGE_CACHE_MAX_AGE")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<u64>()
+            .map_err(|_| "Invalid FILE_PI_IMAGE_CACHE_MAX_AGE value".to_string())?;
+
+        // Largest file the text content endpoints read or write, 10 MiB
+        let max_text_file_size = env::var("FILE_PI_MAX_TEXT_FILE_SIZE")
+            .unwrap_or_else(|_| "10485760".to_string())
+            .parse::<u64>()
+            .map_err(|_| "Invalid FILE_PI_MAX_TEXT_FILE_SIZE value".to_string())?;
+
+        // Searches stop after this many matches, 0 disables the limit
+        let max_search_results = env::var("FILE_PI_MAX_SEARCH_RESULTS")
+            .unwrap_or_else(|_| "10000".to_string())
+            .parse::<usize>()
+            .map_err(|_| "Invalid FILE_PI_MAX_SEARCH_RESULTS value".to_string())?;
+
+        // Searches return what they found so far after this long, 0 disables
+        // it. Kept below the request timeout so partial results beat a 504.
+        let search_timeout_secs = env::var("FILE_PI_SEARCH_TIMEOUT")
+            .unwrap_or_else(|_| "20".to_string())
+            .parse::<u64>()
+            .map_err(|_| "Invalid FILE_PI_SEARCH_TIMEOUT value".to_string())?;
+
+        // Detect file types from their contents instead of only the extension
+        let mime_sniff = env::var("FILE_PI_MIME_SNIFF")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .map_err(|_| "Invalid FILE_PI_MIME_SNIFF value".to_string())?;
+
+        // Extensions listed as videos whatever their guessed MIME type
+        let video_extensions = env::var("FILE_PI_VIDEO_EXTENSIONS")
+            .unwrap_or_else(|_| "mkv,ts,mts,m2ts".to_string())
+            .split(',')
+            .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+
+        // Comma separated `extension=mime/type` pairs replacing the guessed type
+        let mime_overrides = env::var("FILE_PI_MIME_OVERRIDES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                pair.split_once('=')
+                    .map(|(ext, mime)| {
+                        (
+                            ext.trim().trim_start_matches('.').to_lowercase(),
+                            mime.trim().to_string(),
+                        )
+                    })
+                    .filter(|(ext, mime)| !ext.is_empty() && mime.contains('/'))
+                    .ok_or_else(|| format!("Invalid FILE_PI_MIME_OVERRIDES entry '{}'", pair))
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        // Bare names are looked up on PATH
+        let ffmpeg_path = env::var("FILE_PI_FFMPEG_PATH").unwrap_or_else(|_| "ffmpeg".to_string());
+        let ffprobe_path =
+            env::var("FILE_PI_FFPROBE_PATH").unwrap_or_else(|_| "ffprobe".to_string());
+
+        // TLS is only enabled when both the certificate and key are set
+        let tls_cert = env::var("FILE_PI_TLS_CERT").ok().map(PathBuf::from);
+        let tls_key = env::var("FILE_PI_TLS_KEY").ok().map(PathBuf::from);
+        if tls_cert.is_some() != tls_key.is_some() {
+            return Err("FILE_PI_TLS_CERT and FILE_PI_TLS_KEY must be set together".to_string());
+        }
+
+        // The admin API is disabled unless a token is set
+        let admin_token = env::var("FILE_PI_ADMIN_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty());
+
+        // The frontend is served from here when the directory exists, paths
+        // that match no file get the SPA index, relative to the web root
+        let web_root =
+            PathBuf::from(env::var("FILE_PI_WEBROOT").unwrap_or_else(|_| "webdeploy".to_string()));
+        let spa_index = PathBuf::from(
+            env::var("FILE_PI_SPA_INDEX").unwrap_or_else(|_| "index.html".to_string()),
+        );
+        if spa_index.is_absolute()
+            || spa_index
+                .components()
+                .any(|component| component == std::path::Component::ParentDir)
+        {
+            return Err("FILE_PI_SPA_INDEX must be a path inside FILE_PI_WEBROOT".to_string());
+        }
+
+        // The SPA index is kept in memory, poll it for changes so a redeployed
+        // frontend is picked up without a restart
+        let spa_watch = env::var("FILE_PI_SPA_WATCH")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .map_err(|_| "Invalid FILE_PI_SPA_WATCH value".to_string())?;
+
+        let config = Config {
             root_dir,
+            bind_addr,
             port,
             log_level,
             cache_dir,
             log_dir: PathBuf::from(log_dir),
-        })
+            log_skip_paths,
+            log_body_limit,
+            request_timeout_secs,
+            listing_cache_ttl_secs,
+            listing_cache_size,
+            thumb_concurrency,
+            thumb_cache_max_age_secs,
+            thumb_fallback,
+            thumb_seek_percent,
+            image_cache_max_age_secs,
+            max_text_file_size,
+            max_search_results,
+            search_timeout_secs,
+            mime: MimeSettings {
+                sniff: mime_sniff,
+                overrides: mime_overrides,
+            },
+            video_extensions,
+            ffmpeg_path,
+            ffprobe_path,
+            tls_cert,
+            tls_key,
+            admin_token,
+            web_root,
+            spa_index,
+            spa_watch,
+        };
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// The folders the server keeps its own data in, left out of every
+    /// listing, see `syncfusion_fm_backend::is_listed`. They are joined to
+    /// `root`, the root directory as the listed paths are built from it.
+    /// The cache directory is only one of them while it lies inside the root,
+    /// otherwise a `.cache` in the root is an ordinary folder.
+    pub fn system_dirs(&self, root: &Path) -> Vec<PathBuf> {
+        let mut dirs = vec![root.join(syncfusion_fm_backend::TRASH_DIR)];
+        if let Some(cache_dir) = relative_to_root(Path::new(&self.root_dir), &self.cache_dir) {
+            dirs.push(root.join(cache_dir));
+        }
+        dirs
+    }
+
+    /// Checks that the root directory is usable and the cache directory can
+    /// be written, so a misconfigured server fails at startup instead of on
+    /// every request.
+    pub fn validate(&self) -> Result<(), String> {
+        let root = PathBuf::from(&self.root_dir).canonicalize().map_err(|e| {
+            format!(
+                "FILE_PI_ROOT_DIR '{}' is not accessible: {}",
+                self.root_dir, e
+            )
+        })?;
+        if !root.is_dir() {
+            return Err(format!(
+                "FILE_PI_ROOT_DIR '{}' is not a directory",
+                self.root_dir
+            ));
+        }
+
+        std::fs::create_dir_all(&self.cache_dir).map_err(|e| {
+            format!(
+                "Cache directory '{}' can't be created: {}",
+                self.cache_dir.display(),
+                e
+            )
+        })?;
+
+        let probe = self.cache_dir.join(".write_test");
+        std::fs::write(&probe, b"")
+            .and_then(|_| std::fs::remove_file(&probe))
+            .map_err(|e| {
+                format!(
+                    "Cache directory '{}' is not writable: {}",
+                    self.cache_dir.display(),
+                    e
+                )
+            })
+    }
+}
+
+// `dir` relative to `root_dir`, `None` when it lies outside or is the root
+// itself. Both are resolved first, so links and relative paths compare
+// like the directories they point to.
+fn relative_to_root(root_dir: &Path, dir: &Path) -> Option<PathBuf> {
+    let root_dir = root_dir.canonicalize().ok()?;
+    let dir = dir.canonicalize().ok()?;
+    dir.strip_prefix(&root_dir)
+        .ok()
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn finds_cache_dir_inside_root() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("root");
+        std::fs::create_dir_all(root.join("thumbs")).unwrap();
+
+        assert_eq!(
+            relative_to_root(&root, &root.join("thumbs")),
+            Some(PathBuf::from("thumbs"))
+        );
+    }
+
+    #[test]
+    fn ignores_cache_dir_outside_root() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("root");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::create_dir_all(temp.path().join("cache")).unwrap();
+
+        assert_eq!(relative_to_root(&root, &temp.path().join("cache")), None);
+        assert_eq!(relative_to_root(&root, &root), None);
     }
 }
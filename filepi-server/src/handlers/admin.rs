@@ -0,0 +1,26 @@
+use axum::{Json, extract::State, http::StatusCode};
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+#[derive(Serialize)]
+pub struct ShutdownResponse {
+    message: String,
+}
+
+// Handler for POST /api/v1/admin/shutdown
+// Starts the same graceful shutdown as SIGTERM. In-flight requests, this one
+// included, are completed before the process exits.
+pub async fn shutdown(
+    State(shutdown): State<CancellationToken>,
+) -> (StatusCode, Json<ShutdownResponse>) {
+    info!("Shutdown requested through the admin API");
+    shutdown.cancel();
+
+    (
+        StatusCode::ACCEPTED,
+        Json(ShutdownResponse {
+            message: "Server is shutting down".to_string(),
+        }),
+    )
+}
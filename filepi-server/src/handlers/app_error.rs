@@ -1,22 +1,142 @@
-use crate::models::ErrorResponse;
+use crate::models::{ErrorBody, ErrorResponse};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
+use syncfusion_fm_backend::PathError;
+use tracing::error;
+
+/// Longest part of a client supplied path echoed back in an error message
+const MAX_ECHOED_PATH_CHARS: usize = 200;
 
 pub enum AppError {
     NotFound(String),
     InternalError(String),
     BadRequest(String),
+    /// A path that resolves outside the root directory
+    Forbidden(String),
+    /// The target of a create, upload, copy or link already exists
+    Conflict(String),
+    /// A request body that failed to deserialize, `field` points at the culprit
+    Validation {
+        message: String,
+        field: Option<String>,
+    },
+    /// An error with a machine-readable code and optional detail for the client
+    Detailed {
+        status: StatusCode,
+        code: &'static str,
+        message: String,
+        detail: Option<String>,
+    },
+}
+
+impl AppError {
+    /// Status and JSON error body this error is reported with, for responses
+    /// that embed errors such as per-file results of a batch upload
+    pub fn into_parts(self) -> (StatusCode, ErrorBody) {
+        let mut field = None;
+        let (status, code, message, detail) = match self {
+            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, "not_found", msg, None),
+            AppError::InternalError(msg) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal_error",
+                msg,
+                None,
+            ),
+            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "bad_request", msg, None),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, "forbidden", msg, None),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, "already_exists", msg, None),
+            AppError::Validation {
+                message,
+                field: culprit,
+            } => {
+                field = culprit;
+                (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "validation_error",
+                    message,
+                    None,
+                )
+            }
+            AppError::Detailed {
+                status,
+                code,
+                message,
+                detail,
+            } => (status, code, message, detail),
+        };
+
+        (
+            status,
+            ErrorBody {
+                code: code.to_string(),
+                message,
+                detail,
+                field,
+            },
+        )
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
-            AppError::InternalError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+        let (status, error) = self.into_parts();
+        (status, axum::Json(ErrorResponse { error })).into_response()
+    }
+}
+
+impl From<PathError> for AppError {
+    fn from(err: PathError) -> Self {
+        error!("Path validation failed: {}", err);
+        let (status, code) = match err {
+            PathError::OutsideRoot(_) => (StatusCode::BAD_REQUEST, "path_outside_root"),
+            PathError::NotFound(_) => (StatusCode::NOT_FOUND, "path_not_found"),
+            PathError::NotADirectory(_) => (StatusCode::BAD_REQUEST, "not_a_directory"),
+            PathError::Io { .. } => (StatusCode::INTERNAL_SERVER_ERROR, "path_io_error"),
         };
+        AppError::Detailed {
+            status,
+            code,
+            message: err.client_message().to_string(),
+            detail: None,
+        }
+    }
+}
+
+/// A client supplied path made safe to echo in an error message: control
+/// characters are escaped and overlong input is cut short. Pass the path as
+/// the client sent it, a resolved path would reveal `root_dir`; that detail
+/// belongs in the server log.
+pub fn display_path(path: &str) -> String {
+    let mut shown = String::new();
+    for (i, c) in path.chars().enumerate() {
+        if i == MAX_ECHOED_PATH_CHARS {
+            shown.push('…');
+            break;
+        }
+        if c.is_control() {
+            shown.extend(c.escape_default());
+        } else {
+            shown.push(c);
+        }
+    }
+    shown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_path_escapes_control_characters() {
+        assert_eq!(display_path("docs/a.txt"), "docs/a.txt");
+        assert_eq!(display_path("bad\nname\u{1b}[31m"), "bad\\nname\\u{1b}[31m");
+    }
+
+    #[test]
+    fn display_path_cuts_long_paths() {
+        let shown = display_path(&"a/".repeat(MAX_ECHOED_PATH_CHARS));
 
-        let body = axum::Json(ErrorResponse { error: message });
-        (status, body).into_response()
+        assert_eq!(shown.chars().count(), MAX_ECHOED_PATH_CHARS + 1);
+        assert!(shown.ends_with('…'));
     }
 }
@@ -0,0 +1,110 @@
+use axum::{Json, extract::State, http::StatusCode};
+use lofty::file::TaggedFile;
+use lofty::picture::PictureType;
+use lofty::prelude::*;
+use lofty::probe::Probe;
+use mime_guess::from_path;
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::config::Config;
+use crate::handlers::app_error::AppError;
+use crate::handlers::extractors::WildcardPath;
+use crate::models::audio_info::AudioInfo;
+
+// Handler for GET /api/v1/audioinfo/{*path}
+pub async fn get_audio_info(
+    State(config): State<Arc<Config>>,
+    WildcardPath(file_path): WildcardPath,
+) -> Result<Json<AudioInfo>, AppError> {
+    let root_dir = PathBuf::from(&config.root_dir);
+    let abs_path =
+        syncfusion_fm_backend::validate_path(&root_dir, file_path.trim_start_matches('/'))?;
+
+    if !abs_path.is_file() {
+        return Err(AppError::NotFound("File not found".to_string()));
+    }
+
+    if !is_audio_file(&abs_path) {
+        return Err(AppError::BadRequest("Not an audio file".to_string()));
+    }
+
+    info!("Reading audio tags of {:?}", abs_path);
+
+    let source = abs_path.clone();
+    let tagged_file = tokio::task::spawn_blocking(move || read_tagged_file(&source))
+        .await
+        .map_err(|e| {
+            error!("Audio tag task failed: {}", e);
+            AppError::InternalError(format!("Failed to read audio tags: {}", e))
+        })??;
+
+    Ok(Json(audio_info(&tagged_file)))
+}
+
+pub fn is_audio_file(path: &FsPath) -> bool {
+    from_path(path)
+        .first_or_octet_stream()
+        .essence_str()
+        .starts_with("audio/")
+}
+
+/// Reads the tags and stream properties of an audio file
+pub fn read_tagged_file(path: &FsPath) -> Result<TaggedFile, AppError> {
+    Probe::open(path)
+        .and_then(|probe| probe.read())
+        .map_err(|e| {
+            error!("Failed to read audio file {:?}: {}", path, e);
+            AppError::Detailed {
+                status: StatusCode::BAD_REQUEST,
+                code: "unreadable_audio",
+                message: "Unsupported or corrupt audio file".to_string(),
+                detail: Some(e.to_string()),
+            }
+        })
+}
+
+/// The embedded front cover of an audio file, or the first picture when none
+/// is marked as front cover. None when there is no art or the file can't be read.
+pub fn cover_art(path: &FsPath) -> Option<Vec<u8>> {
+    let tagged_file = read_tagged_file(path).ok()?;
+    let tag = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag())?;
+    let pictures = tag.pictures();
+
+    pictures
+        .iter()
+        .find(|picture| picture.pic_type() == PictureType::CoverFront)
+        .or_else(|| pictures.first())
+        .map(|picture| picture.data().to_vec())
+}
+
+fn audio_info(tagged_file: &TaggedFile) -> AudioInfo {
+    let properties = tagged_file.properties();
+    // Files can carry several tag formats (e.g. ID3v2 and APE), prefer the
+    // one native to the container
+    let tag = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag());
+
+    AudioInfo {
+        title: tag.and_then(|t| t.title()).map(|s| s.to_string()),
+        artist: tag.and_then(|t| t.artist()).map(|s| s.to_string()),
+        album: tag.and_then(|t| t.album()).map(|s| s.to_string()),
+        album_artist: tag
+            .and_then(|t| t.get_string(&ItemKey::AlbumArtist))
+            .map(|s| s.to_string()),
+        genre: tag.and_then(|t| t.genre()).map(|s| s.to_string()),
+        track: tag.and_then(|t| t.track()),
+        track_total: tag.and_then(|t| t.track_total()),
+        disc: tag.and_then(|t| t.disk()),
+        disc_total: tag.and_then(|t| t.disk_total()),
+        duration: properties.duration().as_secs_f64(),
+        bit_rate: properties.audio_bitrate(),
+        sample_rate: properties.sample_rate(),
+        channels: properties.channels(),
+        has_cover: tag.is_some_and(|t| !t.pictures().is_empty()),
+    }
+}
@@ -0,0 +1,78 @@
+use axum::{Json, extract::State};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::handlers::result_handler::DEFAULT_PAGE_SIZE;
+use crate::models::{CapabilitiesResponse, CapabilityFeatures, CapabilityLimits, MAX_UPLOAD_SIZE};
+
+// Operations every deployment answers, named after what they do rather than
+// their routes
+pub const ACTIONS: [&str; 27] = [
+    "list",
+    "search",
+    "media",
+    "tree",
+    "tags",
+    "favorites",
+    "thumbnails",
+    "regenerate_thumbnail",
+    "animated_previews",
+    "resized_images",
+    "media_info",
+    "audio_info",
+    "subtitles",
+    "hls",
+    "read_text",
+    "save_text",
+    "tail",
+    "create_folder",
+    "create_file",
+    "link",
+    "touch",
+    "empty",
+    "upload",
+    "copy",
+    "download",
+    "syncfusion",
+    "metrics",
+];
+
+// Handler for GET /api/v1/capabilities
+// Describes what this deployment supports, so clients can hide what it
+// doesn't instead of finding out from a failed request
+pub async fn get_capabilities(State(config): State<Arc<Config>>) -> Json<CapabilitiesResponse> {
+    let admin = config.admin_token.is_some();
+
+    let mut actions = ACTIONS.to_vec();
+    if cfg!(unix) {
+        actions.push("chmod");
+    }
+    if admin {
+        actions.push("shutdown");
+    }
+
+    Json(CapabilitiesResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        features: CapabilityFeatures {
+            tls: config.tls_cert.is_some(),
+            admin,
+            thumbnail_fallback: config.thumb_fallback,
+            mime_sniff: config.mime.sniff,
+            permissions: cfg!(unix),
+            disk_space: cfg!(unix),
+        },
+        limits: CapabilityLimits {
+            max_upload_size: MAX_UPLOAD_SIZE,
+            default_page_size: DEFAULT_PAGE_SIZE,
+            max_page_size: None,
+            max_text_file_size: config.max_text_file_size,
+            max_search_results: (config.max_search_results > 0)
+                .then_some(config.max_search_results),
+            search_timeout_secs: (config.search_timeout_secs > 0)
+                .then_some(config.search_timeout_secs),
+            request_timeout_secs: (config.request_timeout_secs > 0)
+                .then_some(config.request_timeout_secs),
+        },
+        actions,
+    })
+}
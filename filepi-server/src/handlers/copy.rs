@@ -0,0 +1,223 @@
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{error, info};
+use walkdir::WalkDir;
+
+use crate::config::Config;
+use crate::handlers::app_error::{AppError, display_path};
+use crate::handlers::extractors::AppJson;
+use crate::handlers::listing_cache::ListingCache;
+use crate::models::file_info::{FileInfo, MimeSettings};
+use crate::models::{CopyProgress, CopyRequest, CopyResponse};
+
+// Handler for POST /api/v1/copy
+// Copies a file or folder tree into the `target` folder. With `progress` the
+// answer is a stream of server-sent events: `progress` after every copied
+// file, then `complete` with the copy or `error`. Otherwise it answers once
+// the copy is done. Either way the copy carries on if the client goes away.
+pub async fn copy(
+    State(config): State<Arc<Config>>,
+    State(listing_cache): State<Arc<ListingCache>>,
+    AppJson(params): AppJson<CopyRequest>,
+) -> Result<Response, AppError> {
+    let source = params.source.trim_matches('/');
+    let target = params.target.trim_matches('/');
+    if source.is_empty() {
+        return Err(AppError::Validation {
+            message: "source is required".to_string(),
+            field: Some("source".to_string()),
+        });
+    }
+
+    let root_dir = PathBuf::from(&config.root_dir);
+    let source_path = syncfusion_fm_backend::validate_path(&root_dir, source)?;
+    let target_dir = syncfusion_fm_backend::validate_path(&root_dir, target)?;
+
+    if source_path.symlink_metadata().is_err() {
+        return Err(AppError::NotFound(format!(
+            "Path not found: {}",
+            display_path(source)
+        )));
+    }
+
+    if !target_dir.is_dir() {
+        return Err(AppError::NotFound(format!(
+            "Target folder not found: {}",
+            display_path(target)
+        )));
+    }
+
+    // A folder copied into itself would keep finding its own copy
+    if source_path.is_dir() && !source_path.is_symlink() {
+        let canonical_source = source_path.canonicalize();
+        let canonical_target = target_dir.canonicalize();
+        if let (Ok(canonical_source), Ok(canonical_target)) = (canonical_source, canonical_target)
+            && canonical_target.starts_with(&canonical_source)
+        {
+            return Err(AppError::BadRequest(
+                "Cannot copy a folder into itself".to_string(),
+            ));
+        }
+    }
+
+    let name = source_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut destination = target_dir.join(&name);
+    if destination.symlink_metadata().is_ok() {
+        if !params.rename {
+            return Err(already_exists(&name));
+        }
+        destination = syncfusion_fm_backend::next_free_path(&target_dir, &name);
+    }
+
+    info!("Copying {:?} to {:?}", source_path, destination);
+
+    if !params.progress {
+        let response = tokio::task::spawn_blocking(move || {
+            run_copy(
+                &source_path,
+                &destination,
+                &target_dir,
+                &listing_cache,
+                &config.mime,
+                |_| {},
+            )
+        })
+        .await
+        .map_err(|e| {
+            error!("Copy task failed: {}", e);
+            AppError::InternalError(format!("Failed to copy: {}", e))
+        })??;
+        return Ok(Json(response).into_response());
+    }
+
+    let (tx, rx) = mpsc::channel::<Result<Event, Infallible>>(64);
+    tokio::task::spawn_blocking(move || {
+        let result = run_copy(
+            &source_path,
+            &destination,
+            &target_dir,
+            &listing_cache,
+            &config.mime,
+            |progress| {
+                let _ = tx.blocking_send(Ok(event("progress", &progress)));
+            },
+        );
+        let last = match result {
+            Ok(response) => event("complete", &response),
+            Err(e) => event("error", &e.into_parts().1),
+        };
+        let _ = tx.blocking_send(Ok(last));
+    });
+
+    Ok(Sse::new(ReceiverStream::new(rx))
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+fn already_exists(name: &str) -> AppError {
+    AppError::Detailed {
+        status: StatusCode::CONFLICT,
+        code: "already_exists",
+        message: format!("File already exists: {}", display_path(name)),
+        detail: Some("Set rename to copy it under a numbered name".to_string()),
+    }
+}
+
+// Counts the tree for the totals, then copies it reporting the progress
+// before the first and after every copied file
+fn run_copy(
+    source: &Path,
+    destination: &Path,
+    target_dir: &Path,
+    listing_cache: &ListingCache,
+    mime: &MimeSettings,
+    mut report: impl FnMut(CopyProgress),
+) -> Result<CopyResponse, AppError> {
+    let mut progress = count_tree(source).map_err(|e| {
+        error!("Failed to count {:?}: {}", source, e);
+        AppError::InternalError("Failed to read the source".to_string())
+    })?;
+    report(progress);
+
+    let copied = syncfusion_fm_backend::copy_item(source, destination, false, &mut |bytes| {
+        progress.files_copied += 1;
+        progress.bytes_copied += bytes;
+        report(progress);
+        Ok(())
+    });
+
+    // A failed copy was removed, but its first folder may have been listed
+    listing_cache.invalidate(target_dir);
+
+    copied.map_err(|e| {
+        error!("Failed to copy {:?} to {:?}: {}", source, destination, e);
+        if e.kind() == io::ErrorKind::AlreadyExists {
+            // Created by someone else since the handler checked
+            let name = destination
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            return already_exists(&name);
+        }
+        AppError::InternalError(format!("Failed to copy: {}", e))
+    })?;
+
+    info!(
+        "Copied {} files, {} bytes to {:?}",
+        progress.files_copied, progress.bytes_copied, destination
+    );
+
+    let file = FileInfo::from_path(destination, target_dir, mime).map_err(|e| {
+        error!("Error creating FileInfo: {}", e);
+        AppError::InternalError(format!("Failed to read file info: {}", e))
+    })?;
+
+    Ok(CopyResponse {
+        message: "Copied successfully".to_string(),
+        file,
+        files_copied: progress.files_copied,
+        bytes_copied: progress.bytes_copied,
+    })
+}
+
+// Files and links below `path`, or `path` itself when it isn't a folder,
+// and their combined size. Links aren't followed and count as 0 bytes, the
+// way they are copied.
+fn count_tree(path: &Path) -> io::Result<CopyProgress> {
+    let mut totals = CopyProgress::default();
+    for entry in WalkDir::new(path).follow_root_links(false) {
+        let entry = entry?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        totals.files_total += 1;
+        if entry.file_type().is_file() {
+            totals.bytes_total += entry.metadata()?.len();
+        }
+    }
+    Ok(totals)
+}
+
+fn event(name: &'static str, data: &impl Serialize) -> Event {
+    Event::default()
+        .event(name)
+        .json_data(data)
+        .unwrap_or_default()
+}
@@ -0,0 +1,103 @@
+use axum::extract::{FromRequest, FromRequestParts, Path, Request};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, StatusCode, header};
+use bytes::Bytes;
+use serde::de::DeserializeOwned;
+use tracing::error;
+
+use crate::handlers::app_error::AppError;
+
+/// Drop-in replacement for `axum::Json` whose rejections use our error
+/// envelope. Bodies that fail to deserialize are reported as `422` with the
+/// offending field, e.g. `{ "error": { "message": "...", "field": "path" } }`.
+pub struct AppJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for AppJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !has_json_content_type(req.headers()) {
+            return Err(AppError::Detailed {
+                status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                code: "unsupported_media_type",
+                message: "Expected request with `Content-Type: application/json`".to_string(),
+                detail: None,
+            });
+        }
+
+        let bytes = Bytes::from_request(req, state).await.map_err(|e| {
+            error!("Failed to read request body: {}", e);
+            if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+                AppError::Detailed {
+                    status: StatusCode::PAYLOAD_TOO_LARGE,
+                    code: "payload_too_large",
+                    message: "Request body is too large".to_string(),
+                    detail: None,
+                }
+            } else {
+                AppError::BadRequest(format!("Failed to read request body: {}", e))
+            }
+        })?;
+
+        let deserializer = &mut serde_json::Deserializer::from_slice(&bytes);
+        match serde_path_to_error::deserialize(deserializer) {
+            Ok(value) => Ok(AppJson(value)),
+            Err(err) => {
+                // The path is "." when the error isn't tied to a field, e.g. a syntax error
+                let path = err.path().to_string();
+                let field = if path == "." { None } else { Some(path) };
+                let message = err.into_inner().to_string();
+                error!("Invalid JSON body (field: {:?}): {}", field, message);
+                Err(AppError::Validation { message, field })
+            }
+        }
+    }
+}
+
+/// The percent-decoded `{*wildcard}` file path of a route. Unlike a plain
+/// `Path<String>`, a request with an encoded separator such as `%2F` is
+/// rejected with `400` instead of the separator becoming part of the path.
+pub struct WildcardPath(pub String);
+
+impl<S> FromRequestParts<S> for WildcardPath
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // The captured value is already decoded, only the URI still shows
+        // which separators were encoded
+        if has_encoded_separator(parts.uri.path()) {
+            return Err(AppError::BadRequest(
+                "Invalid path: encoded path separator".to_string(),
+            ));
+        }
+
+        let Path(path) = Path::<String>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Invalid path: {}", e)))?;
+        Ok(WildcardPath(path))
+    }
+}
+
+// `%2F`, and on Windows `%5C`, would decode into a path separator
+fn has_encoded_separator(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.contains("%2f") || (cfg!(windows) && path.contains("%5c"))
+}
+
+fn has_json_content_type(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<mime_guess::Mime>().ok())
+        .is_some_and(|mime| {
+            mime.type_() == "application"
+                && (mime.subtype() == "json" || mime.suffix().is_some_and(|s| s == "json"))
+        })
+}
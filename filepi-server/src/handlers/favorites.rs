@@ -0,0 +1,96 @@
+use axum::{Json, extract::State};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::config::Config;
+use crate::handlers::app_error::{AppError, display_path};
+use crate::handlers::extractors::AppJson;
+use crate::handlers::json_file_store::JsonFileStore;
+use crate::models::{FavoriteAction, FavoritesRequest, FavoritesResponse};
+
+const FAVORITES_FILE: &str = "favorites.json";
+
+/// Pinned paths, relative to `root_dir`, shared by everyone using this
+/// deployment. Kept in `favorites.json` under `cache_dir` in pinning order.
+pub struct FavoriteStore {
+    paths: JsonFileStore<Vec<String>>,
+}
+
+impl FavoriteStore {
+    /// Loads the list, starting empty when it is absent or unreadable
+    pub fn load(config: &Config) -> Self {
+        FavoriteStore {
+            paths: JsonFileStore::load(config.cache_dir.join(FAVORITES_FILE)),
+        }
+    }
+
+    fn list(&self) -> Vec<String> {
+        self.paths.lock().clone()
+    }
+
+    fn update(&self, path: &str, action: FavoriteAction) -> std::io::Result<Vec<String>> {
+        let mut paths = self.paths.lock();
+        match action {
+            FavoriteAction::Add if !paths.iter().any(|p| p == path) => paths.push(path.to_string()),
+            FavoriteAction::Add => return Ok(paths.clone()),
+            FavoriteAction::Remove => paths.retain(|p| p != path),
+        }
+        self.paths.save(&paths)?;
+
+        Ok(paths.clone())
+    }
+}
+
+// Handler for GET /api/v1/favorites
+// Favorites whose path no longer exists are left out, but kept in the file
+// in case a drive is only temporarily unmounted.
+pub async fn get_favorites(
+    State(config): State<Arc<Config>>,
+    State(favorites): State<Arc<FavoriteStore>>,
+) -> Json<FavoritesResponse> {
+    Json(existing_favorites(&config, favorites.list()))
+}
+
+// Handler for POST /api/v1/favorites
+pub async fn update_favorites(
+    State(config): State<Arc<Config>>,
+    State(favorites): State<Arc<FavoriteStore>>,
+    AppJson(params): AppJson<FavoritesRequest>,
+) -> Result<Json<FavoritesResponse>, AppError> {
+    let path = params.path.trim_matches('/');
+    let action = params.action.unwrap_or(FavoriteAction::Add);
+
+    if action == FavoriteAction::Add {
+        let root_dir = PathBuf::from(&config.root_dir);
+        let full_path = syncfusion_fm_backend::validate_path(&root_dir, path)?;
+        if !full_path.exists() {
+            return Err(AppError::NotFound(format!(
+                "Path not found: {}",
+                display_path(path)
+            )));
+        }
+    }
+
+    let paths = favorites.update(path, action).map_err(|e| {
+        error!("Failed to store favorites: {}", e);
+        AppError::InternalError(format!("Failed to store favorites: {}", e))
+    })?;
+
+    info!("Favorites: {:?} {}", action, path);
+
+    Ok(Json(existing_favorites(&config, paths)))
+}
+
+fn existing_favorites(config: &Config, paths: Vec<String>) -> FavoritesResponse {
+    let root_dir = PathBuf::from(&config.root_dir);
+    let favorites = paths
+        .into_iter()
+        .filter(|path| {
+            syncfusion_fm_backend::validate_path(&root_dir, path)
+                .is_ok_and(|full_path| full_path.exists())
+        })
+        .collect();
+
+    FavoritesResponse { favorites }
+}
@@ -1,29 +1,47 @@
 use axum::{
     Json,
     body::Body,
-    extract::{Path, Query, State},
-    http::{StatusCode, header},
-    response::IntoResponse,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
 };
 
 use axum_typed_multipart::TypedMultipart;
-use mime_guess::from_path;
+use bytes::Bytes;
+use filetime::FileTime;
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use rayon::prelude::*;
+use std::collections::HashSet;
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::fs::File;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 use tokio_util::io::ReaderStream;
-use tracing::{error, info};
+use tracing::{debug, error, info, warn};
 use walkdir::WalkDir;
 
 use crate::config::Config;
-use crate::handlers::hash_utilities::compute_file_sha512;
-use crate::handlers::thumbnail_manager::ThumbnailError;
-use crate::handlers::{app_error::AppError, result_handler};
-use crate::models::file_info::FileInfo;
+use crate::handlers::app_error::{AppError, display_path};
+use crate::handlers::extractors::{AppJson, WildcardPath};
+use crate::handlers::hash_utilities::{compute_file_sha512, hash_prefix};
+use crate::handlers::image_resizer::{self, Fit, OutputFormat, ResizeRequest};
+use crate::handlers::listing_cache::ListingCache;
+use crate::handlers::metrics::UploadStats;
+use crate::handlers::tag_store::TagStore;
+use crate::handlers::thumbnail_manager::{self, ThumbnailError};
+use crate::handlers::{http_cache, result_handler};
+use crate::models::file_info::{FileInfo, MimeSettings, guess_mime, guess_mime_with_fallback};
 use crate::models::{
-    CreateFolderRequest, CreateFolderResponse, FileQuery, FilesResponse, UploadForm,
+    ChmodRequest, ChmodResponse, CreateFileRequest, CreateFolderRequest, CreateFolderResponse,
+    DedupSummary, EmptyRequest, EmptyResponse, ErrorBody, FileQuery, FilesResponse, LinkRequest,
+    LinkType, MediaQuery, ThumbnailPrefetchRequest, ThumbnailPrefetchResponse,
+    ThumbnailRegenerateRequest, TouchRequest, UploadBatchResponse, UploadFailure, UploadForm,
+    UploadResult,
 };
 use serde::Deserialize;
 
@@ -32,9 +50,35 @@ pub struct ServeFileParams {
     pub inline: Option<bool>,
 }
 
+#[derive(Deserialize)]
+pub struct ThumbnailParams {
+    /// Serve a short animated WebP preview instead of the still, videos only
+    #[serde(default)]
+    pub animated: bool,
+    /// Overrides `FILE_PI_THUMB_FALLBACK`, `false` returns the error instead
+    /// of a placeholder when no thumbnail can be generated
+    pub fallback: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct ImageParams {
+    pub w: Option<u32>,
+    pub h: Option<u32>,
+    /// `contain` (default) or `cover`
+    pub fit: Option<String>,
+    /// `jpeg` (default) or `webp`
+    pub format: Option<String>,
+}
+
+// Directories with more entries than this are listed a page at a time when
+// no cached listing exists, instead of reading every entry's FileInfo
+const PAGED_LISTING_THRESHOLD: usize = 1000;
+
 // Handler for GET /api/v1/files
 pub async fn get_files(
     State(config): State<Arc<Config>>,
+    State(listing_cache): State<Arc<ListingCache>>,
+    State(tag_store): State<Arc<TagStore>>,
     Query(params): Query<FileQuery>,
 ) -> Result<Json<FilesResponse>, AppError> {
     let path = params.path.as_deref().unwrap_or_default();
@@ -48,13 +92,16 @@ pub async fn get_files(
     // Validate the path exists
     if !full_path.exists() {
         error!("Path not found: {:?}", full_path);
-        return Err(AppError::NotFound(format!("Path not found: {}", path)));
+        return Err(AppError::NotFound(format!(
+            "Path not found: {}",
+            display_path(path)
+        )));
     }
 
     // Canonicalize to resolve . and .. and get the clean absolute path
     let full_path = full_path.canonicalize().map_err(|e| {
         error!("Failed to canonicalize path {:?}: {}", full_path, e);
-        AppError::NotFound(format!("Path not found: {}", path))
+        AppError::NotFound(format!("Path not found: {}", display_path(path)))
     })?;
 
     // Security: ensure the canonicalized path is still within root_dir
@@ -75,9 +122,147 @@ pub async fn get_files(
     if !full_path.is_dir() {
         return Err(AppError::BadRequest("Path is not a directory".to_string()));
     }
+    let system_dirs = config.system_dirs(&canonical_root);
+
+    // The default order only needs names, so a large directory without a
+    // cached listing is paged before building FileInfo and isn't cached.
+    // Smaller ones are read in full and cached for the next page or sort.
+    let cached = listing_cache.get(&full_path);
+    let paged = cached.is_none()
+        && params.sort_by.is_none()
+        && fs::read_dir(&full_path)
+            .map(|entries| entries.take(PAGED_LISTING_THRESHOLD + 1).count())
+            .is_ok_and(|count| count > PAGED_LISTING_THRESHOLD);
+    let mut response = if paged {
+        let listing_dir = full_path.clone();
+        let skip = params.skip.unwrap_or(0);
+        let limit = params.limit.unwrap_or(result_handler::DEFAULT_PAGE_SIZE);
+        let group_dirs = params.group_dirs;
+        let system_dirs = system_dirs.clone();
+        let mime = config.mime.clone();
+        let (mut page, total, total_size) = tokio::task::spawn_blocking(move || {
+            read_directory_page(
+                &listing_dir,
+                !skip_hidden,
+                &system_dirs,
+                &mime,
+                group_dirs,
+                skip,
+                limit,
+            )
+        })
+        .await
+        .map_err(|e| {
+            error!("Directory listing task failed: {}", e);
+            AppError::InternalError(format!("Failed to read directory: {}", e))
+        })??;
+
+        if params.with_tags {
+            tag_store.annotate(&mut page, &canonical_root);
+        }
+
+        result_handler::format_page(page, total, total_size, &params)?
+    } else {
+        let mut files = match cached {
+            Some(files) => files,
+            None => {
+                // Read directory contents off the async executor so a slow mount
+                // doesn't stall other requests
+                let listing_dir = full_path.clone();
+                let mime = config.mime.clone();
+                let files =
+                    tokio::task::spawn_blocking(move || read_directory(&listing_dir, &mime))
+                        .await
+                        .map_err(|e| {
+                            error!("Directory listing task failed: {}", e);
+                            AppError::InternalError(format!("Failed to read directory: {}", e))
+                        })??;
+
+                listing_cache.insert(full_path.clone(), files.clone());
+                files
+            }
+        };
+
+        // Skip hidden files (starting with .) and the server's own folders, the
+        // cache holds all of them
+        files.retain(|file| {
+            syncfusion_fm_backend::is_listed(Path::new(&file.full_name), !skip_hidden, &system_dirs)
+        });
+
+        if params.with_tags {
+            tag_store.annotate(&mut files, &canonical_root);
+        }
+
+        result_handler::format_result(&mut files, &params)?
+    };
+
+    let mut cwd = describe_cwd(&full_path, &canonical_root, &config.mime)?;
+    if params.iso_dates {
+        cwd.add_iso_dates();
+    }
+    response.cwd = Some(cwd);
+
+    // Only the current page is counted
+    if params.with_child_count {
+        let mut page = std::mem::take(&mut response.files);
+        response.files = tokio::task::spawn_blocking(move || {
+            count_children(&mut page, skip_hidden, &system_dirs);
+            page
+        })
+        .await
+        .map_err(|e| {
+            error!("Child count task failed: {}", e);
+            AppError::InternalError(format!("Failed to count directory entries: {}", e))
+        })?;
+    }
+
+    if params.with_disk
+        && let Some((free, total)) = disk_space(&canonical_root)
+    {
+        response.disk_free = Some(free);
+        response.disk_total = Some(total);
+    }
+
+    Ok(response)
+}
+
+// Fills in `child_count` of the directories, unreadable ones are left out
+fn count_children(files: &mut [FileInfo], skip_hidden: bool, system_dirs: &[PathBuf]) {
+    files
+        .par_iter_mut()
+        .filter(|file| file.is_directory)
+        .for_each(|file| {
+            file.child_count = fs::read_dir(&file.full_name).ok().map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|entry| {
+                        syncfusion_fm_backend::is_listed(&entry.path(), !skip_hidden, system_dirs)
+                    })
+                    .count()
+            });
+        });
+}
+
+// The listed directory, with `rel_path` relative to the root. The root itself
+// is named after its directory, or "/" when it has no name.
+fn describe_cwd(
+    full_path: &std::path::Path,
+    canonical_root: &std::path::Path,
+    mime: &MimeSettings,
+) -> Result<FileInfo, AppError> {
+    let mut cwd = FileInfo::from_path(full_path, canonical_root, mime).map_err(|e| {
+        error!("Error reading metadata for {:?}: {}", full_path, e);
+        AppError::InternalError(format!("Failed to read directory info: {}", e))
+    })?;
+    if cwd.name.is_empty() {
+        cwd.name = "/".to_string();
+    }
+    Ok(cwd)
+}
 
-    // Read directory contents
-    let entries = fs::read_dir(&full_path).map_err(|e| {
+// Blocking part of get_files: reads the directory and stats every entry
+fn read_directory(full_path: &PathBuf, mime: &MimeSettings) -> Result<Vec<FileInfo>, AppError> {
+    let entries = fs::read_dir(full_path).map_err(|e| {
         error!("Error reading directory: {}", e);
         AppError::InternalError(format!("Failed to read directory: {}", e))
     })?;
@@ -85,48 +270,202 @@ pub async fn get_files(
     // Collect file information
     let mut files: Vec<FileInfo> = Vec::new();
 
+    // An entry that can't be read, e.g. removed since the directory was read,
+    // is left out instead of failing the whole listing. Broken links are
+    // still listed, FileInfo describes them by the link itself.
     for entry in entries {
-        let entry = entry.map_err(|e| {
-            error!("Error reading entry: {}", e);
-            AppError::InternalError(format!("Failed to read entry: {}", e))
-        })?;
-
-        let file_name = entry.file_name().to_string_lossy().to_string();
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("Skipping unreadable entry in {:?}: {}", full_path, e);
+                continue;
+            }
+        };
 
-        // Skip hidden files (starting with .)
-        if skip_hidden && file_name.starts_with('.') {
-            continue;
-        }
         // Get the absolute path of the entry
         let entry_path = entry.path();
 
         // Create FileInfo with absolute path and current directory context
-        files.push(FileInfo::from_path(&entry_path, &full_path).map_err(|e| {
-            error!("Error creating FileInfo: {}", e);
-            AppError::InternalError(format!("Failed to read file info: {}", e))
-        })?);
+        match FileInfo::from_path(&entry_path, full_path, mime) {
+            Ok(file) => files.push(file),
+            Err(e) => warn!("Skipping {:?}, failed to read file info: {}", entry_path, e),
+        }
+    }
+
+    Ok(files)
+}
+
+// A directory entry with only what the default order and the totals need
+struct ListedEntry {
+    path: PathBuf,
+    name: String,
+    is_directory: bool,
+    size: u64,
+}
+
+// Lists the directory in the default order, with or without directories
+// first, building FileInfo only for the `limit` entries after `skip`. Owner,
+// MIME and child lookups are what make FileInfo expensive, so huge
+// directories only pay them for one page. Returns the page, the number of
+// listed entries and the size of their files. Entries of the page that
+// vanish before their FileInfo is built are taken off both totals, those on
+// other pages are only noticed when that page is read.
+fn read_directory_page(
+    full_path: &PathBuf,
+    show_hidden: bool,
+    system_dirs: &[PathBuf],
+    mime: &MimeSettings,
+    group_dirs: bool,
+    skip: usize,
+    limit: usize,
+) -> Result<(Vec<FileInfo>, usize, u64), AppError> {
+    let entries = fs::read_dir(full_path).map_err(|e| {
+        error!("Error reading directory: {}", e);
+        AppError::InternalError(format!("Failed to read directory: {}", e))
+    })?;
+
+    // Unreadable entries are left out like in read_directory
+    let mut listed = Vec::new();
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("Skipping unreadable entry in {:?}: {}", full_path, e);
+                continue;
+            }
+        };
+
+        let path = entry.path();
+        if !syncfusion_fm_backend::is_listed(&path, show_hidden, system_dirs) {
+            continue;
+        }
+
+        // Links are described by their target like in FileInfo, broken links
+        // by the link itself
+        let name = entry.file_name().to_string_lossy().to_string();
+        let metadata = match fs::metadata(&path).or_else(|_| entry.metadata()) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                warn!("Skipping {:?}, failed to read metadata: {}", path, e);
+                continue;
+            }
+        };
+
+        listed.push(ListedEntry {
+            path,
+            name,
+            is_directory: metadata.is_dir(),
+            size: if metadata.is_file() {
+                metadata.len()
+            } else {
+                0
+            },
+        });
+    }
+
+    let mut total = listed.len();
+    let mut total_size: u64 = listed
+        .iter()
+        .filter(|entry| !entry.is_directory)
+        .map(|entry| entry.size)
+        .sum();
+
+    listed.sort_by(|a, b| {
+        result_handler::default_order(
+            (a.is_directory, &a.name),
+            (b.is_directory, &b.name),
+            group_dirs,
+        )
+    });
+
+    let mut page = Vec::new();
+    for entry in listed.iter().skip(skip).take(limit) {
+        match FileInfo::from_path(&entry.path, full_path, mime) {
+            Ok(file) => page.push(file),
+            Err(e) => {
+                warn!("Skipping {:?}, failed to read file info: {}", entry.path, e);
+                total -= 1;
+                total_size -= entry.size;
+            }
+        }
     }
 
-    result_handler::format_result(&mut files, &params)
+    Ok((page, total, total_size))
 }
 
 // recursivley get all videos present in path
 pub async fn get_videos(
     State(config): State<Arc<Config>>,
+    State(tag_store): State<Arc<TagStore>>,
+    Query(params): Query<FileQuery>,
+) -> Result<Json<FilesResponse>, AppError> {
+    list_media(config, tag_store, params, "video/".to_string()).await
+}
+
+// Handler for GET /api/v1/audios
+// Recursively lists the audio files present in path, like get_videos
+pub async fn get_audios(
+    State(config): State<Arc<Config>>,
+    State(tag_store): State<Arc<TagStore>>,
+    Query(params): Query<FileQuery>,
+) -> Result<Json<FilesResponse>, AppError> {
+    list_media(config, tag_store, params, "audio/".to_string()).await
+}
+
+// Handler for GET /api/v1/images
+// Recursively lists the image files present in path, like get_videos
+pub async fn get_images(
+    State(config): State<Arc<Config>>,
+    State(tag_store): State<Arc<TagStore>>,
+    Query(params): Query<FileQuery>,
+) -> Result<Json<FilesResponse>, AppError> {
+    list_media(config, tag_store, params, "image/".to_string()).await
+}
+
+// Handler for GET /api/v1/media?type=image/&path=...
+// Recursively lists the files whose MIME type starts with `type`
+pub async fn get_media(
+    State(config): State<Arc<Config>>,
+    State(tag_store): State<Arc<TagStore>>,
+    Query(media): Query<MediaQuery>,
     Query(params): Query<FileQuery>,
+) -> Result<Json<FilesResponse>, AppError> {
+    let mime_prefix = media.mime_prefix.trim().to_lowercase();
+    let valid = !mime_prefix.is_empty()
+        && mime_prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/.+-_".contains(c));
+    if !valid {
+        return Err(AppError::Validation {
+            message: format!("Invalid media type: {}", media.mime_prefix),
+            field: Some("type".to_string()),
+        });
+    }
+
+    list_media(config, tag_store, params, mime_prefix).await
+}
+
+// Recursively lists the files below `params.path` whose MIME type, guessed
+// from the extension, starts with `mime_prefix`. Video listings also include
+// the configured video extensions, some containers are only recognized by them.
+async fn list_media(
+    config: Arc<Config>,
+    tag_store: Arc<TagStore>,
+    params: FileQuery,
+    mime_prefix: String,
 ) -> Result<Json<FilesResponse>, AppError> {
     let path = params.path.as_deref().unwrap_or_default();
     let skip_hidden = params.skip_hidden;
 
-    info!("Getting videos from path: {}", path);
+    info!("Getting {} files from path: {}", mime_prefix, path);
 
     // Construct the full absolute path
-    let full_path = PathBuf::from(&config.root_dir).join(&path);
+    let full_path = PathBuf::from(&config.root_dir).join(path);
 
     // Canonicalize to resolve . and .. and get the clean absolute path
     let full_path = full_path.canonicalize().map_err(|e| {
         error!("Failed to canonicalize path {:?}: {}", full_path, e);
-        AppError::NotFound(format!("Path not found: {}", path))
+        AppError::NotFound(format!("Path not found: {}", display_path(path)))
     })?;
 
     // Security: ensure the canonicalized path is still within root_dir
@@ -146,7 +485,10 @@ pub async fn get_videos(
     // Validate the path exists
     if !full_path.exists() {
         error!("Path not found: {:?}", full_path);
-        return Err(AppError::NotFound(format!("Path not found: {}", path)));
+        return Err(AppError::NotFound(format!(
+            "Path not found: {}",
+            display_path(path)
+        )));
     }
 
     // Must be a directory to walk
@@ -154,61 +496,89 @@ pub async fn get_videos(
         return Err(AppError::BadRequest("Path is not a directory".to_string()));
     }
 
-    let mut video_files: Vec<FileInfo> = Vec::new();
+    // The walk and the per-file stat calls are blocking, keep them off the
+    // async executor
+    let walk_root = full_path.clone();
+    let max_depth = params.max_depth;
+    let extensions = if "video/".starts_with(mime_prefix.as_str()) {
+        config.video_extensions.clone()
+    } else {
+        Vec::new()
+    };
+    let system_dirs = config.system_dirs(&canonical_root);
+    let mime = config.mime.clone();
+    let mut media_files = tokio::task::spawn_blocking(move || {
+        collect_media(
+            &walk_root,
+            skip_hidden,
+            system_dirs,
+            &mime,
+            max_depth,
+            &mime_prefix,
+            &extensions,
+        )
+    })
+    .await
+    .map_err(|e| {
+        error!("Media walk task failed: {}", e);
+        AppError::InternalError(format!("Failed to traverse directory: {}", e))
+    })??;
+
+    if params.with_tags {
+        tag_store.annotate(&mut media_files, &canonical_root);
+    }
 
-    // Walk the directory recursively
-    for entry in WalkDir::new(&full_path) {
-        let entry = entry.map_err(|e| {
-            error!("Error walking directory: {}", e);
-            AppError::InternalError(format!("Failed to traverse directory: {}", e))
-        })?;
+    result_handler::format_result(&mut media_files, &params)
+}
 
-        let metadata = entry.metadata().map_err(|e| {
-            error!("Error reading metadata for {:?}: {}", entry.path(), e);
-            AppError::InternalError(format!("Failed to read file metadata: {}", e))
+// Walks full_path recursively and builds FileInfo for every matching file in
+// parallel. The result is unordered, format_result sorts it afterwards.
+fn collect_media(
+    full_path: &PathBuf,
+    skip_hidden: bool,
+    system_dirs: Vec<PathBuf>,
+    mime: &MimeSettings,
+    max_depth: Option<usize>,
+    mime_prefix: &str,
+    extensions: &[String],
+) -> Result<Vec<FileInfo>, AppError> {
+    let entries = walk(full_path, max_depth, !skip_hidden, system_dirs)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| {
+            // Walk errors name the absolute path, only the log gets it
+            error!("Error walking directory: {}", e);
+            AppError::InternalError("Failed to traverse directory".to_string())
         })?;
 
+    entries
+        .par_iter()
         // Skip directories
-        if metadata.is_dir() {
-            continue;
-        }
-
-        let file_path = entry.path();
-        let file_name = match file_path.file_name() {
-            Some(name) => name.to_string_lossy().to_string(),
-            None => continue, // Skip if no filename (shouldn't happen normally)
-        };
-
-        // Skip hidden files
-        if skip_hidden && file_name.starts_with('.') {
-            continue;
-        }
-
-        // Guess MIME type from file extension
-        let mime_type = from_path(file_path);
-        if !mime_type
-            .first_or_octet_stream()
-            .essence_str()
-            .starts_with("video/")
-        {
-            continue;
-        }
-
-        video_files.push(FileInfo::from_path(&file_path, &full_path).unwrap());
-    }
-
-    result_handler::format_result(&mut video_files, &params)
+        .filter(|entry| !entry.file_type().is_dir())
+        // Guess MIME type from file extension, or contents when it has none
+        .filter(|entry| {
+            has_extension(entry.path(), extensions)
+                || guess_mime_with_fallback(entry.path(), mime).starts_with(mime_prefix)
+        })
+        .map(|entry| {
+            FileInfo::from_path(entry.path(), full_path, mime).map_err(|e| {
+                error!("Error reading metadata for {:?}: {}", entry.path(), e);
+                AppError::InternalError(format!("Failed to read file metadata: {}", e))
+            })
+        })
+        .collect()
 }
 
 pub async fn search(
     State(config): State<Arc<Config>>,
+    State(tag_store): State<Arc<TagStore>>,
     Query(params): Query<FileQuery>,
-) -> Result<Json<FilesResponse>, AppError> {
+) -> Result<Response, AppError> {
     let path = params.path.as_deref().unwrap_or_default();
     let query = params.query.as_deref().unwrap_or_default().to_lowercase();
     let skip_hidden = params.skip_hidden;
 
-    if query.is_empty() {
+    // A tag alone is enough, the empty query then matches every name
+    if query.is_empty() && params.tag.is_none() {
         error!("Search query is needed!");
         return Err(AppError::BadRequest(String::from("Missing search query")));
     }
@@ -221,7 +591,7 @@ pub async fn search(
     // Canonicalize to resolve . and .. and get the clean absolute path
     let full_path = full_path.canonicalize().map_err(|e| {
         error!("Failed to canonicalize path {:?}: {}", full_path, e);
-        AppError::NotFound(format!("Path not found: {}", path))
+        AppError::NotFound(format!("Path not found: {}", display_path(path)))
     })?;
 
     // Security: ensure the canonicalized path is still within root_dir
@@ -241,7 +611,10 @@ pub async fn search(
     // Validate the path exists
     if !full_path.exists() {
         error!("Path not found: {:?}", full_path);
-        return Err(AppError::NotFound(format!("Path not found: {}", path)));
+        return Err(AppError::NotFound(format!(
+            "Path not found: {}",
+            display_path(path)
+        )));
     }
 
     // Must be a directory to walk
@@ -249,134 +622,370 @@ pub async fn search(
         return Err(AppError::BadRequest("Path is not a directory".to_string()));
     }
 
-    let mut matching_files: Vec<FileInfo> = Vec::new();
+    // Absolute paths of everything carrying the tag
+    let tagged: Option<HashSet<PathBuf>> = params.tag.as_deref().map(|tag| {
+        tag_store
+            .paths_with_tag(tag.trim())
+            .into_iter()
+            .map(|relative| canonical_root.join(relative))
+            .collect()
+    });
+
+    let filter = SearchFilter {
+        query,
+        skip_hidden,
+        system_dirs: config.system_dirs(&canonical_root),
+        tagged,
+        max_depth: params.max_depth,
+        fuzzy: params.fuzzy.then(|| SkimMatcherV2::default().ignore_case()),
+        deadline: (config.search_timeout_secs > 0)
+            .then(|| Instant::now() + Duration::from_secs(config.search_timeout_secs)),
+    };
+
+    if params.stream {
+        let tags = params
+            .with_tags
+            .then(|| (tag_store.clone(), canonical_root.clone()));
+        return Ok(search_stream(
+            full_path,
+            path.to_string(),
+            filter,
+            tags,
+            config.mime.clone(),
+            params.iso_dates,
+            config.max_search_results,
+        ));
+    }
+
+    let mut matches: Vec<(PathBuf, Option<i64>)> = Vec::new();
+    let mut truncated = false;
+    let mut timed_out = false;
+
+    for entry in walk(
+        &full_path,
+        filter.max_depth,
+        !filter.skip_hidden,
+        filter.system_dirs.clone(),
+    ) {
+        if filter.timed_out() {
+            info!(
+                "Search timed out after {} seconds",
+                config.search_timeout_secs
+            );
+            truncated = true;
+            timed_out = true;
+            break;
+        }
 
-    for entry in WalkDir::new(&full_path) {
         let entry = entry.map_err(|e| {
+            // Walk errors name the absolute path, only the log gets it
             error!("Error walking dir {}", e);
-            AppError::InternalError(format!("Failed to traverse directory: {}", e))
-        })?;
-
-        let metadata = entry.metadata().map_err(|e| {
-            error!("Error reading metadata for {:?}: {}", entry.path(), e);
-            AppError::InternalError(format!("Failed to read file metadata: {}", e))
+            AppError::InternalError("Failed to traverse directory".to_string())
         })?;
 
-        // Skip directories
-        if metadata.is_dir() {
+        let Some(score) = filter.score(&entry) else {
             continue;
+        };
+
+        // Fuzzy searches walk everything so the best matches can be kept
+        if filter.fuzzy.is_none() && reached_limit(matches.len(), config.max_search_results) {
+            info!("Search stopped at {} matches", config.max_search_results);
+            truncated = true;
+            break;
         }
 
-        let file_path = entry.path();
-        let file_name = match file_path.file_name() {
-            Some(name) => name.to_string_lossy().to_string(),
-            None => continue, // Skip if no filename (shouldn't happen normally)
-        };
+        matches.push((entry.into_path(), score));
+    }
 
-        // Skip hidden files
-        if skip_hidden && file_name.starts_with('.') {
-            continue;
+    if filter.fuzzy.is_some() && config.max_search_results > 0 {
+        matches.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+        if matches.len() > config.max_search_results {
+            info!("Search kept the best {} matches", config.max_search_results);
+            matches.truncate(config.max_search_results);
+            truncated = true;
         }
+    }
 
-        if !file_name.to_lowercase().contains(&query) {
-            continue;
+    let mut matching_files = Vec::with_capacity(matches.len());
+    for (match_path, score) in matches {
+        let mut file_info = FileInfo::from_path(&match_path, path, &config.mime).map_err(|e| {
+            error!("Error reading metadata for {:?}: {}", match_path, e);
+            AppError::InternalError(format!("Failed to read file metadata: {}", e))
+        })?;
+        file_info.score = score;
+        matching_files.push(file_info);
+    }
+
+    if params.with_tags {
+        tag_store.annotate(&mut matching_files, &canonical_root);
+    }
+
+    // Best fuzzy matches first unless the client picked an order
+    let mut params = params;
+    if params.fuzzy && params.sort_by.is_none() {
+        params.sort_by = Some("score".to_string());
+        params.order = Some("desc".to_string());
+    }
+
+    let mut response = result_handler::format_result(&mut matching_files, &params)?;
+    response.truncated = truncated;
+    response.timed_out = timed_out;
+
+    Ok(response.into_response())
+}
+
+// Walks `root` recursively, at most `max_depth` levels down when given.
+// Entries that aren't listed, see `is_listed`, are skipped with everything
+// below them.
+fn walk(
+    root: &std::path::Path,
+    max_depth: Option<usize>,
+    show_hidden: bool,
+    system_dirs: Vec<PathBuf>,
+) -> impl Iterator<Item = walkdir::Result<walkdir::DirEntry>> {
+    let mut walker = WalkDir::new(root);
+    if let Some(depth) = max_depth {
+        walker = walker.max_depth(depth);
+    }
+    walker.into_iter().filter_entry(move |entry| {
+        entry.depth() == 0
+            || syncfusion_fm_backend::is_listed(entry.path(), show_hidden, &system_dirs)
+    })
+}
+
+fn has_extension(path: &std::path::Path, extensions: &[String]) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| extensions.contains(&ext))
+}
+
+// 0 means no limit
+fn reached_limit(count: usize, max: usize) -> bool {
+    max > 0 && count >= max
+}
+
+struct SearchFilter {
+    /// Already lowercased
+    query: String,
+    skip_hidden: bool,
+    /// Never walked into, see `Config::system_dirs`
+    system_dirs: Vec<PathBuf>,
+    /// Absolute paths an entry must be one of, when filtering by tag
+    tagged: Option<HashSet<PathBuf>>,
+    max_depth: Option<usize>,
+    /// Set for fuzzy searches
+    fuzzy: Option<SkimMatcherV2>,
+    /// The walk stops once this has passed
+    deadline: Option<Instant>,
+}
+
+impl SearchFilter {
+    fn timed_out(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    // `None` when `entry` doesn't match, otherwise `Some` with the fuzzy
+    // score for fuzzy searches and `None` for substring ones
+    fn score(&self, entry: &walkdir::DirEntry) -> Option<Option<i64>> {
+        let tagged = self
+            .tagged
+            .as_ref()
+            .is_none_or(|tagged| tagged.contains(entry.path()));
+        // Only files match, hidden ones were already skipped by the walk
+        if !tagged || entry.file_type().is_dir() {
+            return None;
         }
 
-        matching_files.push(FileInfo::from_path(&file_path, &path).unwrap());
+        let file_name = entry.file_name().to_string_lossy().to_lowercase();
+        match &self.fuzzy {
+            Some(matcher) => matcher
+                .fuzzy_match(&file_name, &self.query)
+                .filter(|score| *score >= fuzzy_threshold(&self.query))
+                .map(Some),
+            None => file_name.contains(&self.query).then_some(None),
+        }
     }
+}
+
+// Every matched character scores around 16, less the gaps before it. Half of
+// that per query character drops matches scattered across a long name.
+const FUZZY_MIN_SCORE_PER_CHAR: i64 = 8;
 
-    result_handler::format_result(&mut matching_files, &params)
+fn fuzzy_threshold(query: &str) -> i64 {
+    query.chars().count() as i64 * FUZZY_MIN_SCORE_PER_CHAR
 }
 
-pub async fn serve_file(
-    State(config): State<Arc<Config>>,
-    Path(file_path): Path<String>,
-    Query(params): Query<ServeFileParams>,
-) -> Result<impl IntoResponse, AppError> {
+// Streams search matches as newline delimited JSON while the walk is running.
+// The walk stops as soon as the client drops the connection.
+// `tags` holds the store and canonical root when tags should be included.
+// The stream simply ends after `max_results` matches or at the deadline, so
+// fuzzy matches arrive in walk order rather than ranked.
+fn search_stream(
+    full_path: PathBuf,
+    path: String,
+    filter: SearchFilter,
+    tags: Option<(Arc<TagStore>, PathBuf)>,
+    mime: MimeSettings,
+    iso_dates: bool,
+    max_results: usize,
+) -> Response {
+    let (tx, rx) = mpsc::channel::<Result<Bytes, std::io::Error>>(64);
+
+    tokio::task::spawn_blocking(move || {
+        let mut sent = 0;
+        for entry in walk(
+            &full_path,
+            filter.max_depth,
+            !filter.skip_hidden,
+            filter.system_dirs.clone(),
+        ) {
+            if filter.timed_out() {
+                info!("Search stream timed out, stopping walk");
+                break;
+            }
+
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    error!("Error walking dir {}", e);
+                    continue;
+                }
+            };
+
+            let Some(score) = filter.score(&entry) else {
+                continue;
+            };
+
+            if reached_limit(sent, max_results) {
+                info!("Search stream stopped at {} matches", max_results);
+                break;
+            }
+
+            let mut file_info = match FileInfo::from_path(entry.path(), &path, &mime) {
+                Ok(file_info) => file_info,
+                Err(e) => {
+                    error!("Error reading metadata for {:?}: {}", entry.path(), e);
+                    continue;
+                }
+            };
+            file_info.score = score;
+            if iso_dates {
+                file_info.add_iso_dates();
+            }
+
+            if let Some((tag_store, root)) = &tags {
+                tag_store.annotate(std::slice::from_mut(&mut file_info), root);
+            }
+
+            let mut line = match serde_json::to_vec(&file_info) {
+                Ok(line) => line,
+                Err(e) => {
+                    error!("Failed to serialize search result: {}", e);
+                    continue;
+                }
+            };
+            line.push(b'\n');
+
+            if tx.blocking_send(Ok(Bytes::from(line))).is_err() {
+                info!("Search stream closed by client, stopping walk");
+                break;
+            }
+            sent += 1;
+        }
+    });
+
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response()
+}
+
+// Resolves the `{*wildcard}` path of a file route below root_dir. Answers
+// 404 when nothing is there, 403 when it resolves outside the root, through
+// `..` or a symlink, and 400 for a directory.
+fn resolve_file(config: &Config, file_path: &str) -> Result<PathBuf, AppError> {
     let file_path = file_path.trim_start_matches('/');
-    let abs_path = PathBuf::from(&config.root_dir).join(file_path);
 
-    // Security: prevent directory traversal
-    if !abs_path.starts_with(&config.root_dir) {
-        return Err(AppError::BadRequest("Invalid path".to_string()));
+    // `..` is refused before touching the filesystem, so the answer doesn't
+    // tell which files exist outside the root
+    let lexically_inside = std::path::Path::new(file_path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !lexically_inside {
+        return Err(AppError::Forbidden(
+            "Invalid path: outside root directory".to_string(),
+        ));
     }
 
-    // Check if file exists and is not a directory
+    let abs_path = PathBuf::from(&config.root_dir).join(file_path);
+
     if !abs_path.exists() {
         return Err(AppError::NotFound("File not found".to_string()));
     }
 
+    let abs_path = abs_path.canonicalize().map_err(|e| {
+        error!("Failed to canonicalize path {:?}: {}", abs_path, e);
+        AppError::NotFound("File not found".to_string())
+    })?;
+    let canonical_root = PathBuf::from(&config.root_dir)
+        .canonicalize()
+        .map_err(|e| {
+            error!("Failed to canonicalize root directory: {}", e);
+            AppError::InternalError("Invalid root directory configuration".to_string())
+        })?;
+
+    if !abs_path.starts_with(&canonical_root) {
+        return Err(AppError::Forbidden(
+            "Invalid path: outside root directory".to_string(),
+        ));
+    }
+
     if abs_path.is_dir() {
         return Err(AppError::BadRequest("Path is a directory".to_string()));
     }
 
-    info!("Serving file: {:?}", abs_path);
+    Ok(abs_path)
+}
 
-    // Open the file
-    let file = File::open(&abs_path).await.map_err(|e| {
-        error!("Failed to open file: {}", e);
-        AppError::InternalError(format!("Failed to open file: {}", e))
-    })?;
+// Served with Range support, see `http_cache::serve_ranged_file`
+pub async fn serve_file(
+    State(config): State<Arc<Config>>,
+    WildcardPath(file_path): WildcardPath,
+    Query(params): Query<ServeFileParams>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let abs_path = resolve_file(&config, &file_path)?;
 
-    // Get file metadata for content length
-    let metadata = file.metadata().await.map_err(|e| {
-        error!("Failed to read file metadata: {}", e);
-        AppError::InternalError(format!("Failed to read metadata: {}", e))
-    })?;
+    info!("Serving file: {:?}", abs_path);
 
-    // Guess MIME type from file extension
-    let mime_type = from_path(&abs_path).first_or_octet_stream().to_string();
+    // Guess MIME type from file extension, or contents when sniffing is enabled
+    let mime_type = guess_mime(&abs_path, &config.mime)
+        .parse()
+        .unwrap_or(mime_guess::mime::APPLICATION_OCTET_STREAM);
 
     // Get filename for Content-Disposition header
     let file_name = abs_path
         .file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("download");
+    let disposition = if params.inline.unwrap_or(false) {
+        format!("inline; filename=\"{}\"", file_name)
+    } else {
+        format!("attachment; filename=\"{}\"", file_name)
+    };
 
-    // Create a stream from the file
-    let stream = ReaderStream::new(file);
-    let body = Body::from_stream(stream);
-
-    // Build response with appropriate headers
-    Ok((
-        StatusCode::OK,
-        [
-            (header::CONTENT_TYPE, mime_type),
-            (header::CONTENT_LENGTH, metadata.len().to_string()),
-            (
-                header::CONTENT_DISPOSITION,
-                if params.inline.unwrap_or(false) {
-                    format!("inline; filename=\"{}\"", file_name)
-                } else {
-                    format!("attachment; filename=\"{}\"", file_name)
-                },
-            ),
-        ],
-        body,
-    ))
+    http_cache::serve_ranged_file(&abs_path, &mime_type, &headers, &disposition).await
 }
 
 // Stream file (for video streaming)
 pub async fn stream_file(
     State(config): State<Arc<Config>>,
-    Path(file_path): Path<String>,
+    WildcardPath(file_path): WildcardPath,
 ) -> Result<impl IntoResponse, AppError> {
-    let file_path = file_path.trim_start_matches('/');
-    let abs_path = PathBuf::from(&config.root_dir).join(file_path);
-
-    // Security: prevent directory traversal
-    if !abs_path.starts_with(&config.root_dir) {
-        return Err(AppError::BadRequest("Invalid path".to_string()));
-    }
-
-    // Check if file exists and is not a directory
-    if !abs_path.exists() {
-        return Err(AppError::NotFound("File not found".to_string()));
-    }
-
-    if abs_path.is_dir() {
-        return Err(AppError::BadRequest("Path is a directory".to_string()));
-    }
+    let abs_path = resolve_file(&config, &file_path)?;
 
     info!("Streaming file: {:?}", abs_path);
 
@@ -393,7 +1002,7 @@ pub async fn stream_file(
     })?;
 
     // Guess MIME type from file extension
-    let mime_type = from_path(&abs_path).first_or_octet_stream().to_string();
+    let mime_type = guess_mime(&abs_path, &config.mime);
 
     // Create a stream from the file
     let stream = ReaderStream::new(file);
@@ -414,56 +1023,256 @@ pub async fn stream_file(
 
 pub async fn get_thumbnail(
     State(config): State<Arc<Config>>,
-    Path(file_path): Path<String>,
+    WildcardPath(file_path): WildcardPath,
+    Query(params): Query<ThumbnailParams>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse, AppError> {
-    let file_path = file_path.trim_start_matches('/');
-    let abs_path = PathBuf::from(&config.root_dir).join(file_path);
+    let max_age = config.thumb_cache_max_age_secs;
+    resolve_file(&config, &file_path)?;
 
-    // Security: prevent directory traversal
-    if !abs_path.starts_with(&config.root_dir) {
-        return Err(AppError::BadRequest("Invalid path".to_string()));
-    }
+    // Thumbnails are cached under the joined path, the one uploads warm
+    let abs_path = PathBuf::from(&config.root_dir).join(file_path.trim_start_matches('/'));
 
-    let thumbnail_path =
-        crate::handlers::thumbnail_manager::get_thumbnail(State(config), &abs_path)
+    let fallback = params.fallback.unwrap_or(config.thumb_fallback);
+    let mime = config.mime.clone();
+    let generated = if params.animated {
+        thumbnail_manager::get_animated_preview(config, &abs_path)
             .await
-            .map_err(|e| match e {
-                ThumbnailError::InvalidInput => {
-                    AppError::BadRequest("Invalid file for thumbnail generation".to_string())
-                }
-                ThumbnailError::InternalError(msg) => AppError::InternalError(msg),
-            })?;
-
+            .map(|path| (path, "image/webp"))
+    } else {
+        thumbnail_manager::get_thumbnail(State(config), &abs_path)
+            .await
+            .map(|path| (path, "image/jpeg"))
+    };
+
+    let (thumbnail_path, content_type) = match generated {
+        Ok(generated) => generated,
+        Err(e) if fallback => {
+            debug!("Serving placeholder thumbnail for {:?}: {:?}", abs_path, e);
+            return Ok(placeholder_thumbnail(&abs_path, &mime));
+        }
+        Err(e) => {
+            let kind = if params.animated {
+                "preview"
+            } else {
+                "thumbnail"
+            };
+            return Err(thumbnail_error(e, kind));
+        }
+    };
+
     // Now serve the thumbnail file
     info!("Serving thumbnail: {:?}", thumbnail_path);
 
-    let file = File::open(&thumbnail_path).await.map_err(|e| {
-        error!("Failed to open thumbnail: {}", e);
-        AppError::InternalError(format!("Failed to open thumbnail: {}", e))
+    serve_cached_image(&thumbnail_path, content_type, max_age, &headers).await
+}
+
+// Handler for POST /api/v1/thumbnails/regenerate
+// Throws away the cached thumbnail of one file and answers with a new one,
+// taken at `seek` seconds for videos when given. Failures are returned as
+// errors, never as a placeholder.
+pub async fn regenerate_thumbnail(
+    State(config): State<Arc<Config>>,
+    AppJson(params): AppJson<ThumbnailRegenerateRequest>,
+) -> Result<Response, AppError> {
+    if params
+        .seek
+        .is_some_and(|seek| !seek.is_finite() || seek < 0.0)
+    {
+        return Err(AppError::Validation {
+            message: "Seek should be zero or more seconds".to_string(),
+            field: Some("seek".to_string()),
+        });
+    }
+
+    let max_age = config.thumb_cache_max_age_secs;
+    resolve_file(&config, &params.path)?;
+
+    // Cached under the joined path like in get_thumbnail
+    let abs_path = PathBuf::from(&config.root_dir).join(params.path.trim_start_matches('/'));
+
+    let thumbnail_path = thumbnail_manager::regenerate_thumbnail(config, &abs_path, params.seek)
+        .await
+        .map_err(|e| thumbnail_error(e, "thumbnail"))?;
+
+    info!("Regenerated thumbnail: {:?}", thumbnail_path);
+
+    serve_cached_image(&thumbnail_path, "image/jpeg", max_age, &HeaderMap::new()).await
+}
+
+fn thumbnail_error(err: ThumbnailError, kind: &str) -> AppError {
+    match err {
+        ThumbnailError::InvalidInput => {
+            AppError::BadRequest(format!("Invalid file for {} generation", kind))
+        }
+        ThumbnailError::NotFound => AppError::NotFound("No thumbnail available".to_string()),
+        ThumbnailError::InternalError(msg) => AppError::InternalError(msg),
+    }
+}
+
+// Browsers refetch placeholders every time, so the real thumbnail shows up once
+// it can be generated, e.g. after installing ffmpeg
+fn placeholder_thumbnail(path: &std::path::Path, mime: &MimeSettings) -> Response {
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "image/svg+xml".to_string()),
+            (header::CACHE_CONTROL, "no-cache".to_string()),
+            (
+                header::HeaderName::from_static("x-thumbnail-placeholder"),
+                "true".to_string(),
+            ),
+        ],
+        thumbnail_manager::placeholder_svg(path, mime),
+    )
+        .into_response()
+}
+
+// Thumbnails and resized images only change when they are regenerated for a
+// newer source, so browsers may reuse them for `max_age` seconds and then
+// revalidate with the ETag of the cached file
+async fn serve_cached_image(
+    path: &std::path::Path,
+    content_type: &str,
+    max_age: u64,
+    headers: &HeaderMap,
+) -> Result<Response, AppError> {
+    let file = File::open(path).await.map_err(|e| {
+        error!("Failed to open cached image {:?}: {}", path, e);
+        AppError::InternalError(format!("Failed to open image: {}", e))
     })?;
 
     let metadata = file.metadata().await.map_err(|e| {
-        error!("Failed to read thumbnail metadata: {}", e);
+        error!("Failed to read cached image metadata: {}", e);
         AppError::InternalError(format!("Failed to read metadata: {}", e))
     })?;
 
+    let etag = http_cache::etag(&metadata);
+    let cache_control = format!("public, max-age={}", max_age);
+
+    if http_cache::etag_matches(headers, &etag) {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, etag), (header::CACHE_CONTROL, cache_control)],
+        )
+            .into_response());
+    }
+
     let stream = ReaderStream::new(file);
     let body = Body::from_stream(stream);
 
     Ok((
         StatusCode::OK,
         [
-            (header::CONTENT_TYPE, "image/jpeg".to_string()),
+            (header::CONTENT_TYPE, content_type.to_string()),
             (header::CONTENT_LENGTH, metadata.len().to_string()),
-            (header::CACHE_CONTROL, "no-cache".to_string()),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, cache_control),
         ],
         body,
-    ))
+    )
+        .into_response())
+}
+
+// Handler for POST /api/v1/thumbnails/prefetch
+// Queues thumbnail generation for every image and video directly inside the
+// directory and returns right away. Generation is bounded by the thumbnail
+// semaphore, already cached thumbnails are returned without work.
+pub async fn prefetch_thumbnails(
+    State(config): State<Arc<Config>>,
+    AppJson(params): AppJson<ThumbnailPrefetchRequest>,
+) -> Result<Json<ThumbnailPrefetchResponse>, AppError> {
+    let path = params.path.as_deref().unwrap_or_default().trim_matches('/');
+
+    let root_dir = PathBuf::from(&config.root_dir);
+    let full_path = syncfusion_fm_backend::validate_path(&root_dir, path)?;
+    if !full_path.is_dir() {
+        return Err(AppError::BadRequest(format!(
+            "Not a directory: {}",
+            display_path(path)
+        )));
+    }
+
+    // Build paths the same way get_thumbnail does so they share cache entries
+    let dir = root_dir.join(path);
+    let entries = fs::read_dir(&dir).map_err(|e| {
+        error!("Failed to read directory {:?}: {}", dir, e);
+        AppError::InternalError(format!("Failed to read directory: {}", e))
+    })?;
+
+    let mut queued = 0;
+    for entry in entries.flatten() {
+        let file_path = entry.path();
+        if file_path.is_file() && thumbnail_manager::is_thumbnailable(&file_path, &config.mime) {
+            thumbnail_manager::queue_thumbnail(config.clone(), file_path);
+            queued += 1;
+        }
+    }
+
+    info!("Queued {} thumbnails for {:?}", queued, dir);
+
+    Ok(Json(ThumbnailPrefetchResponse { queued }))
+}
+
+pub async fn get_resized_image(
+    State(config): State<Arc<Config>>,
+    WildcardPath(file_path): WildcardPath,
+    Query(params): Query<ImageParams>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    let max_age = config.thumb_cache_max_age_secs;
+    let fit = match params.fit.as_deref().unwrap_or("contain") {
+        "contain" => Fit::Contain,
+        "cover" => Fit::Cover,
+        other => return Err(AppError::BadRequest(format!("Invalid fit: {}", other))),
+    };
+
+    let format = match params.format.as_deref().unwrap_or("jpeg") {
+        "jpeg" | "jpg" => OutputFormat::Jpeg,
+        "webp" => OutputFormat::WebP,
+        other => return Err(AppError::BadRequest(format!("Invalid format: {}", other))),
+    };
+
+    if params.w == Some(0) || params.h == Some(0) {
+        return Err(AppError::BadRequest(
+            "Width and height must be greater than 0".to_string(),
+        ));
+    }
+
+    if params.w.is_none() && params.h.is_none() {
+        return Err(AppError::BadRequest(
+            "At least one of w or h is required".to_string(),
+        ));
+    }
+
+    let root_dir = PathBuf::from(&config.root_dir);
+    let abs_path =
+        syncfusion_fm_backend::validate_path(&root_dir, file_path.trim_start_matches('/'))?;
+
+    let request = ResizeRequest {
+        width: params.w,
+        height: params.h,
+        fit,
+        format,
+    };
+
+    let image_path = image_resizer::get_resized_image(config, &abs_path, request)
+        .await
+        .map_err(|e| match e {
+            ThumbnailError::InvalidInput => AppError::BadRequest("Invalid image file".to_string()),
+            ThumbnailError::NotFound => AppError::NotFound("Image not found".to_string()),
+            ThumbnailError::InternalError(msg) => AppError::InternalError(msg),
+        })?;
+
+    info!("Serving resized image: {:?}", image_path);
+
+    serve_cached_image(&image_path, format.content_type(), max_age, &headers).await
 }
 
 pub async fn create_folder(
     State(config): State<Arc<Config>>,
-    Json(params): Json<CreateFolderRequest>,
+    State(listing_cache): State<Arc<ListingCache>>,
+    AppJson(params): AppJson<CreateFolderRequest>,
 ) -> Result<Json<CreateFolderResponse>, AppError> {
     let path = params.path.as_deref().unwrap_or_default();
     let folder_name = params.foldername.as_deref().unwrap_or_default();
@@ -480,13 +1289,16 @@ pub async fn create_folder(
     // Validate the path exists
     if !full_path.exists() {
         error!("Path not found: {:?}", full_path);
-        return Err(AppError::NotFound(format!("Path not found: {}", path)));
+        return Err(AppError::NotFound(format!(
+            "Path not found: {}",
+            display_path(path)
+        )));
     }
 
     // Canonicalize to resolve . and .. and get the clean absolute path
     let full_path = full_path.canonicalize().map_err(|e| {
         error!("Failed to canonicalize path {:?}: {}", full_path, e);
-        AppError::NotFound(format!("Path not found: {}", path))
+        AppError::NotFound(format!("Path not found: {}", display_path(path)))
     })?;
 
     // Security: ensure the canonicalized path is still within root_dir
@@ -503,38 +1315,591 @@ pub async fn create_folder(
         ));
     }
 
-    let dir_path = PathBuf::from(&full_path).join(&folder_name);
-
-    if dir_path.exists() {
-        return Err(AppError::BadRequest("Directory already exist".to_string()));
+    // Nested folder names get their missing parents. The folder itself is
+    // made with create_dir, which fails when it exists, so of two requests
+    // racing for the same name exactly one succeeds.
+    let dir_path = syncfusion_fm_backend::validate_path(&full_path, folder_name)?;
+    if let Some(parent) = dir_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| {
+            error!("Error creating parent directory: {}", e);
+            AppError::InternalError(format!("Failed to create directory: {}", e))
+        })?;
     }
 
-    let _res = fs::create_dir_all(dir_path).map_err(|e| {
-        error!("Error creating directory: {}", e);
-        AppError::InternalError(format!("Failed to create directory: {}", e))
+    fs::create_dir(&dir_path).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::AlreadyExists {
+            AppError::Conflict(format!(
+                "Directory already exists: {}",
+                display_path(folder_name)
+            ))
+        } else {
+            error!("Error creating directory: {}", e);
+            AppError::InternalError(format!("Failed to create directory: {}", e))
+        }
     })?;
 
+    listing_cache.invalidate(&full_path);
+
     Ok(Json(CreateFolderResponse {
         message: String::from("Folder created successfully"),
     }))
 }
 
+// Handler for POST /api/v1/createfile
+pub async fn create_file(
+    State(config): State<Arc<Config>>,
+    State(listing_cache): State<Arc<ListingCache>>,
+    AppJson(params): AppJson<CreateFileRequest>,
+) -> Result<Json<FileInfo>, AppError> {
+    let path = params.path.as_deref().unwrap_or_default();
+    let file_name = params.filename.as_deref().unwrap_or_default().trim();
+
+    if file_name.is_empty() {
+        return Err(AppError::Validation {
+            message: "File name should not be empty".to_string(),
+            field: Some("filename".to_string()),
+        });
+    }
+
+    // The name must stay a single component inside the target directory
+    if file_name.contains(['/', '\\']) || file_name == "." || file_name == ".." {
+        return Err(AppError::Validation {
+            message: format!("Invalid file name: {}", file_name),
+            field: Some("filename".to_string()),
+        });
+    }
+
+    let root_dir = PathBuf::from(&config.root_dir);
+    let dir_path = syncfusion_fm_backend::validate_path(&root_dir, path.trim_start_matches('/'))?;
+
+    if !dir_path.is_dir() {
+        return Err(AppError::NotFound(format!(
+            "Path not found: {}",
+            display_path(path)
+        )));
+    }
+
+    let file_path = dir_path.join(file_name);
+
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&file_path)
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::AlreadyExists {
+                AppError::Conflict(format!("File already exists: {}", display_path(file_name)))
+            } else {
+                error!("Error creating file {:?}: {}", file_path, e);
+                AppError::InternalError(format!("Failed to create file: {}", e))
+            }
+        })?;
+
+    listing_cache.invalidate(&dir_path);
+
+    info!("Created empty file {:?}", file_path);
+
+    let file_info = FileInfo::from_path(&file_path, &dir_path, &config.mime).map_err(|e| {
+        error!("Error creating FileInfo: {}", e);
+        AppError::InternalError(format!("Failed to read file info: {}", e))
+    })?;
+
+    Ok(Json(file_info))
+}
+
+// Limits of a recursive chmod, so one request can't walk the whole disk
+const CHMOD_MAX_DEPTH: usize = 32;
+const CHMOD_MAX_ENTRIES: usize = 100_000;
+
+// Handler for POST /api/v1/chmod
+// With `recursive` the mode is applied to the whole subtree, `dir_mode` and
+// `file_mode` allow the usual 755/644 split. Symlinks are skipped as changing
+// their mode would change their target.
+pub async fn chmod(
+    State(config): State<Arc<Config>>,
+    State(listing_cache): State<Arc<ListingCache>>,
+    AppJson(params): AppJson<ChmodRequest>,
+) -> Result<Json<ChmodResponse>, AppError> {
+    let path = params.path.trim_matches('/');
+    if path.is_empty() {
+        return Err(AppError::BadRequest(
+            "Permissions of the root directory can't be changed".to_string(),
+        ));
+    }
+
+    for (field, mode) in [
+        ("mode", Some(params.mode)),
+        ("dir_mode", params.dir_mode),
+        ("file_mode", params.file_mode),
+    ] {
+        if let Some(mode) = mode.filter(|mode| *mode > 0o7777) {
+            return Err(AppError::Validation {
+                message: format!("Invalid mode: {:o}", mode),
+                field: Some(field.to_string()),
+            });
+        }
+    }
+
+    let root_dir = PathBuf::from(&config.root_dir);
+    let full_path = syncfusion_fm_backend::validate_path(&root_dir, path)?;
+    if !full_path.exists() {
+        return Err(AppError::NotFound(format!(
+            "Path not found: {}",
+            display_path(path)
+        )));
+    }
+
+    let mode_for = move |is_dir: bool| {
+        if is_dir {
+            params.dir_mode.unwrap_or(params.mode)
+        } else {
+            params.file_mode.unwrap_or(params.mode)
+        }
+    };
+
+    let (changed, truncated) = if params.recursive && full_path.is_dir() {
+        let root = full_path.clone();
+        tokio::task::spawn_blocking(move || set_mode_recursively(&root, mode_for))
+            .await
+            .map_err(|e| {
+                error!("Recursive chmod task failed: {}", e);
+                AppError::InternalError(format!("Failed to change permissions: {}", e))
+            })??
+    } else {
+        let mode = mode_for(full_path.is_dir());
+        set_mode(&full_path, mode)?;
+        info!("Changed mode of {:?} to {:o}", full_path, mode);
+        (1, false)
+    };
+
+    let parent = full_path.parent().unwrap_or(&full_path).to_path_buf();
+    listing_cache.invalidate(&parent);
+    if params.recursive {
+        listing_cache.invalidate_tree(&full_path);
+    }
+
+    let file_info = FileInfo::from_path(&full_path, &parent, &config.mime).map_err(|e| {
+        error!("Error creating FileInfo: {}", e);
+        AppError::InternalError(format!("Failed to read file info: {}", e))
+    })?;
+
+    Ok(Json(ChmodResponse {
+        file: file_info,
+        changed,
+        truncated,
+    }))
+}
+
+// Returns how many entries changed and whether a limit cut the walk short
+fn set_mode_recursively(
+    root: &std::path::Path,
+    mode_for: impl Fn(bool) -> u32,
+) -> Result<(usize, bool), AppError> {
+    let mut changed = 0;
+    let mut truncated = false;
+
+    // Directories come after their contents, so a directory mode without
+    // the execute bit doesn't lock the walk out of what is below it
+    for entry in WalkDir::new(root)
+        .max_depth(CHMOD_MAX_DEPTH)
+        .contents_first(true)
+    {
+        let entry = entry.map_err(|e| {
+            error!("Failed to walk {:?}: {}", root, e);
+            AppError::InternalError("Failed to read directory".to_string())
+        })?;
+
+        let file_type = entry.file_type();
+        if file_type.is_symlink() {
+            continue;
+        }
+
+        if changed == CHMOD_MAX_ENTRIES {
+            truncated = true;
+            break;
+        }
+
+        if file_type.is_dir() && entry.depth() == CHMOD_MAX_DEPTH {
+            truncated = true;
+        }
+
+        set_mode(entry.path(), mode_for(file_type.is_dir()))?;
+        changed += 1;
+    }
+
+    info!(
+        "Changed mode of {} entries below {:?}{}",
+        changed,
+        root,
+        if truncated { " (truncated)" } else { "" }
+    );
+
+    Ok((changed, truncated))
+}
+
+// Free (for unprivileged users) and total bytes of the filesystem holding
+// `path`. Not supported outside Unix.
+#[cfg(unix)]
+fn disk_space(path: &std::path::Path) -> Option<(u64, u64)> {
+    let stats = nix::sys::statvfs::statvfs(path)
+        .inspect_err(|e| error!("Failed to read disk space of {:?}: {}", path, e))
+        .ok()?;
+    let fragment_size = stats.fragment_size() as u64;
+    Some((
+        stats.blocks_available() as u64 * fragment_size,
+        stats.blocks() as u64 * fragment_size,
+    ))
+}
+
+#[cfg(not(unix))]
+fn disk_space(_path: &std::path::Path) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(unix)]
+fn set_mode(path: &std::path::Path, mode: u32) -> Result<(), AppError> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).map_err(|e| {
+        error!("Failed to change mode of {:?}: {}", path, e);
+        AppError::InternalError(format!("Failed to change permissions: {}", e))
+    })
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &std::path::Path, _mode: u32) -> Result<(), AppError> {
+    Err(AppError::BadRequest(
+        "Changing permissions is only supported on Unix".to_string(),
+    ))
+}
+
+// Handler for POST /api/v1/touch
+// Sets the modification time of a file or directory, to now when no time is
+// given. The access time is left alone.
+pub async fn touch(
+    State(config): State<Arc<Config>>,
+    State(listing_cache): State<Arc<ListingCache>>,
+    AppJson(params): AppJson<TouchRequest>,
+) -> Result<Json<FileInfo>, AppError> {
+    let path = params.path.trim_matches('/');
+    if path.is_empty() {
+        return Err(AppError::BadRequest(
+            "The root directory can't be touched".to_string(),
+        ));
+    }
+
+    let root_dir = PathBuf::from(&config.root_dir);
+    let full_path = syncfusion_fm_backend::validate_path(&root_dir, path)?;
+    if !full_path.exists() {
+        return Err(AppError::NotFound(format!(
+            "Path not found: {}",
+            display_path(path)
+        )));
+    }
+
+    let modified = match params.modified_time {
+        Some(millis) => FileTime::from_unix_time(
+            millis.div_euclid(1000),
+            (millis.rem_euclid(1000) * 1_000_000) as u32,
+        ),
+        None => FileTime::now(),
+    };
+
+    filetime::set_file_mtime(&full_path, modified).map_err(|e| {
+        error!("Failed to set modification time of {:?}: {}", full_path, e);
+        AppError::InternalError(format!("Failed to set modification time: {}", e))
+    })?;
+
+    info!("Set modification time of {:?} to {}", full_path, modified);
+
+    let parent = full_path.parent().unwrap_or(&full_path).to_path_buf();
+    listing_cache.invalidate(&parent);
+
+    let file_info = FileInfo::from_path(&full_path, &parent, &config.mime).map_err(|e| {
+        error!("Error creating FileInfo: {}", e);
+        AppError::InternalError(format!("Failed to read file info: {}", e))
+    })?;
+
+    Ok(Json(file_info))
+}
+
+// Handler for POST /api/v1/empty
+// Deletes everything inside a directory but keeps the directory itself. Like
+// every other delete this is permanent. A child that can't be removed is
+// reported and doesn't stop the rest.
+pub async fn empty_directory(
+    State(config): State<Arc<Config>>,
+    State(listing_cache): State<Arc<ListingCache>>,
+    AppJson(params): AppJson<EmptyRequest>,
+) -> Result<Json<EmptyResponse>, AppError> {
+    let path = params.path.trim_matches('/');
+    if path.is_empty() {
+        return Err(AppError::BadRequest(
+            "The root directory can't be emptied".to_string(),
+        ));
+    }
+
+    let root_dir = PathBuf::from(&config.root_dir);
+    let full_path = syncfusion_fm_backend::validate_path(&root_dir, path)?;
+    if !full_path.exists() {
+        return Err(AppError::NotFound(format!(
+            "Path not found: {}",
+            display_path(path)
+        )));
+    }
+    if !full_path.is_dir() {
+        return Err(AppError::BadRequest(format!(
+            "Not a directory: {}",
+            display_path(path)
+        )));
+    }
+    if full_path.canonicalize().ok() == root_dir.canonicalize().ok() {
+        return Err(AppError::BadRequest(
+            "The root directory can't be emptied".to_string(),
+        ));
+    }
+
+    let dir = full_path.clone();
+    let response = tokio::task::spawn_blocking(move || remove_children(&dir))
+        .await
+        .map_err(|e| {
+            error!("Empty directory task failed: {}", e);
+            AppError::InternalError(format!("Failed to empty directory: {}", e))
+        })??;
+
+    info!(
+        "Emptied {:?}: removed {} entries ({} bytes), {} failed",
+        full_path,
+        response.removed,
+        response.bytes,
+        response.failed.len()
+    );
+
+    listing_cache.invalidate_tree(&full_path);
+    if let Some(parent) = full_path.parent() {
+        listing_cache.invalidate(parent);
+    }
+
+    Ok(Json(response))
+}
+
+fn remove_children(dir: &std::path::Path) -> Result<EmptyResponse, AppError> {
+    let entries = fs::read_dir(dir).map_err(|e| {
+        error!("Failed to read directory {:?}: {}", dir, e);
+        AppError::InternalError(format!("Failed to read directory: {}", e))
+    })?;
+
+    let mut response = EmptyResponse {
+        removed: 0,
+        bytes: 0,
+        failed: Vec::new(),
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        // Links are removed, never followed, not even a child that links to
+        // a directory
+        let size: u64 = WalkDir::new(&path)
+            .follow_root_links(false)
+            .into_iter()
+            .flatten()
+            .filter(|entry| !entry.file_type().is_dir())
+            .filter_map(|entry| entry.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum();
+
+        let result = match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => fs::remove_dir_all(&path),
+            _ => fs::remove_file(&path),
+        };
+
+        match result {
+            Ok(()) => {
+                response.removed += 1;
+                response.bytes += size;
+            }
+            Err(e) => {
+                warn!("Failed to delete {:?}: {}", path, e);
+                response.failed.push(name);
+            }
+        }
+    }
+
+    Ok(response)
+}
+
+// Handler for POST /api/v1/link
+// Creates a hard or symbolic link to `source` at `target`, both inside
+// root_dir. Symlinks store the path relative to the link's directory so they
+// keep working when the whole tree is moved or mounted elsewhere.
+pub async fn create_link(
+    State(config): State<Arc<Config>>,
+    State(listing_cache): State<Arc<ListingCache>>,
+    AppJson(params): AppJson<LinkRequest>,
+) -> Result<Json<FileInfo>, AppError> {
+    let source = params.source.trim_matches('/');
+    let target = params.target.trim_matches('/');
+    if source.is_empty() || target.is_empty() {
+        return Err(AppError::Validation {
+            message: "Both source and target are required".to_string(),
+            field: Some(
+                if source.is_empty() {
+                    "source"
+                } else {
+                    "target"
+                }
+                .to_string(),
+            ),
+        });
+    }
+
+    let root_dir = PathBuf::from(&config.root_dir);
+    let source_path = syncfusion_fm_backend::validate_path(&root_dir, source)?;
+    let target_path = syncfusion_fm_backend::validate_path(&root_dir, target)?;
+
+    if !source_path.exists() {
+        return Err(AppError::NotFound(format!(
+            "Path not found: {}",
+            display_path(source)
+        )));
+    }
+
+    let target_dir = target_path
+        .parent()
+        .filter(|parent| parent.is_dir())
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "Parent directory not found: {}",
+                display_path(target)
+            ))
+        })?
+        .to_path_buf();
+
+    if target_path.symlink_metadata().is_ok() {
+        return Err(AppError::Conflict(format!(
+            "File already exists: {}",
+            display_path(target)
+        )));
+    }
+
+    let result = match params.link_type {
+        LinkType::Hard => {
+            if source_path.is_dir() {
+                return Err(AppError::BadRequest(
+                    "Hard links to directories are not supported".to_string(),
+                ));
+            }
+            fs::hard_link(&source_path, &target_path)
+        }
+        LinkType::Symbolic => {
+            // Both ends are resolved so the relative path can't be thrown off
+            // by symlinked directories along the way
+            let canonical_source = source_path.canonicalize();
+            let canonical_dir = target_dir.canonicalize();
+            match (canonical_source, canonical_dir) {
+                (Ok(canonical_source), Ok(canonical_dir)) => create_symlink(
+                    &relative_path(&canonical_dir, &canonical_source),
+                    &target_path,
+                    canonical_source.is_dir(),
+                ),
+                (Err(e), _) | (_, Err(e)) => Err(e),
+            }
+        }
+    };
+
+    result.map_err(|e| {
+        error!(
+            "Failed to link {:?} to {:?}: {}",
+            target_path, source_path, e
+        );
+        AppError::InternalError(format!("Failed to create link: {}", e))
+    })?;
+
+    info!(
+        "Created {:?} link {:?} -> {:?}",
+        params.link_type, target_path, source_path
+    );
+
+    listing_cache.invalidate(&target_dir);
+
+    let file_info = FileInfo::from_path(&target_path, &target_dir, &config.mime).map_err(|e| {
+        error!("Error creating FileInfo: {}", e);
+        AppError::InternalError(format!("Failed to read file info: {}", e))
+    })?;
+
+    Ok(Json(file_info))
+}
+
+// Path leading from directory `from` to `to`, both absolute and canonical
+fn relative_path(from: &std::path::Path, to: &std::path::Path) -> PathBuf {
+    let from: Vec<_> = from.components().collect();
+    let to: Vec<_> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..from.len() {
+        relative.push("..");
+    }
+    for component in &to[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+#[cfg(unix)]
+fn create_symlink(
+    link_target: &std::path::Path,
+    link: &std::path::Path,
+    _is_dir: bool,
+) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(link_target, link)
+}
+
+#[cfg(windows)]
+fn create_symlink(
+    link_target: &std::path::Path,
+    link: &std::path::Path,
+    is_dir: bool,
+) -> std::io::Result<()> {
+    if is_dir {
+        std::os::windows::fs::symlink_dir(link_target, link)
+    } else {
+        std::os::windows::fs::symlink_file(link_target, link)
+    }
+}
+
 pub async fn upload_file(
     State(config): State<Arc<Config>>,
+    State(listing_cache): State<Arc<ListingCache>>,
+    State(upload_stats): State<Arc<UploadStats>>,
     TypedMultipart(form): TypedMultipart<UploadForm>,
-) -> Result<Json<crate::models::UploadResponse>, AppError> {
+) -> Result<Response, AppError> {
     info!("Starting file upload process");
 
     let location = form.location.trim();
     let user = form.user.trim();
-    let client_sha512 = form.sha512.as_ref().map(|h| h.trim().to_lowercase());
+    let policy = match form.on_conflict.as_deref().map(str::trim) {
+        None | Some("") if form.overwrite == Some(true) => ConflictPolicy::Overwrite,
+        None | Some("") | Some("fail") => ConflictPolicy::Fail,
+        Some("overwrite") => ConflictPolicy::Overwrite,
+        Some("rename") => ConflictPolicy::Rename,
+        Some(other) => {
+            return Err(AppError::Validation {
+                message: format!(
+                    "Invalid on_conflict value '{}', expected fail, overwrite or rename",
+                    other
+                ),
+                field: Some("on_conflict".to_string()),
+            });
+        }
+    };
 
     info!(
-        "Upload parameters - location: {}, user: {}, sha512: {:?}",
+        "Upload parameters - location: {}, user: {}, files: {}",
         location,
         user,
-        client_sha512.as_ref().map(|h| &h[..16])
-    ); // Log only first 16 chars
+        form.file.len()
+    );
 
     if location.is_empty() || user.is_empty() {
         return Err(AppError::BadRequest(
@@ -542,42 +1907,34 @@ pub async fn upload_file(
         ));
     }
 
-    // Get filename from the uploaded file
-    let filename = form
-        .file
-        .metadata
-        .file_name
-        .clone()
-        .unwrap_or_else(|| "unnamed".to_string());
-
-    info!(
-        "Received file: {} - Size: {}",
-        filename,
-        form.file.contents.len()
-    );
+    if form.file.is_empty() {
+        return Err(AppError::BadRequest(
+            "Missing required field: file".to_string(),
+        ));
+    }
 
-    // Construct the full path for upload location
-    let upload_dir = PathBuf::from(&config.root_dir).join(location);
+    // The files are written in parallel, two with the same name would race
+    // for it whatever the conflict policy
+    let mut names = HashSet::new();
+    if let Some(duplicate) = form
+        .file
+        .iter()
+        .map(|file| file.metadata.file_name.as_deref().unwrap_or("unnamed"))
+        .find(|name| !names.insert(*name))
+    {
+        return Err(AppError::BadRequest(format!(
+            "File '{}' is included more than once",
+            duplicate
+        )));
+    }
 
-    // Canonicalize and validate the upload directory
-    let upload_dir = if upload_dir.exists() {
-        upload_dir.canonicalize().map_err(|e| {
-            error!("Failed to canonicalize upload path: {}", e);
-            AppError::BadRequest("Invalid upload location".to_string())
-        })?
-    } else {
-        // Create the directory if it doesn't exist
-        fs::create_dir_all(&upload_dir).map_err(|e| {
-            error!("Failed to create upload directory: {}", e);
-            AppError::InternalError(format!("Failed to create directory: {}", e))
-        })?;
-        upload_dir.canonicalize().map_err(|e| {
-            error!("Failed to canonicalize upload path: {}", e);
-            AppError::BadRequest("Invalid upload location".to_string())
-        })?
-    };
+    // Creates missing folders and re-checks the result, so the upload
+    // directory can't have escaped root_dir
+    let upload_dir = syncfusion_fm_backend::create_dir_within_root(
+        std::path::Path::new(&config.root_dir),
+        location,
+    )?;
 
-    // Security: ensure the upload path is within root_dir
     let canonical_root = PathBuf::from(&config.root_dir)
         .canonicalize()
         .map_err(|e| {
@@ -585,14 +1942,166 @@ pub async fn upload_file(
             AppError::InternalError("Invalid root directory configuration".to_string())
         })?;
 
-    if !upload_dir.starts_with(&canonical_root) {
-        return Err(AppError::BadRequest(
-            "Invalid upload path: outside root directory".to_string(),
-        ));
+    // Files are written in parallel, each on its own blocking thread
+    let mut client_hashes = form.sha512.into_iter();
+    let tasks: Vec<_> = form
+        .file
+        .into_iter()
+        .map(|file| {
+            let client_sha512 = client_hashes
+                .next()
+                .map(|hash| hash.trim().to_lowercase())
+                .filter(|hash| !hash.is_empty());
+            let upload_dir = upload_dir.clone();
+            let canonical_root = canonical_root.clone();
+            let config = config.clone();
+            let filename = file
+                .metadata
+                .file_name
+                .clone()
+                .unwrap_or_else(|| "unnamed".to_string());
+            let size = file.contents.len() as u64;
+            let name = filename.clone();
+            let task = tokio::task::spawn_blocking(move || {
+                store_upload(
+                    &upload_dir,
+                    &canonical_root,
+                    &name,
+                    &file.contents,
+                    client_sha512,
+                    policy,
+                    &config.mime,
+                )
+            });
+            (filename, size, task)
+        })
+        .collect();
+
+    let mut dedup = DedupSummary::default();
+    let mut outcomes = Vec::with_capacity(tasks.len());
+    for (filename, size, task) in tasks {
+        let outcome = task.await.unwrap_or_else(|e| {
+            error!("Upload task for {} failed: {}", filename, e);
+            Err(AppError::InternalError(format!(
+                "Failed to store file: {}",
+                e
+            )))
+        });
+        if let Ok(StoredUpload::Uploaded { skipped, .. }) = &outcome {
+            dedup.record(size, *skipped);
+        }
+        outcomes.push((filename, outcome));
+    }
+    upload_stats.add(&dedup);
+
+    if outcomes
+        .iter()
+        .any(|(_, outcome)| matches!(outcome, Ok(StoredUpload::Uploaded { skipped: false, .. })))
+    {
+        listing_cache.invalidate(&upload_dir);
+        if let Some(parent) = upload_dir.parent() {
+            // The upload directory may have just been created
+            listing_cache.invalidate(parent);
+        }
+    }
+
+    // A single file keeps the response of the original one-file API
+    let single = outcomes.len() == 1;
+
+    let mut results = Vec::with_capacity(outcomes.len());
+    for (filename, outcome) in outcomes {
+        let result = match outcome {
+            Ok(StoredUpload::Uploaded {
+                filename,
+                location,
+                sha512,
+                skipped,
+            }) => {
+                if !skipped {
+                    // Warm the thumbnail under the same path the thumbnail route uses
+                    let thumbnail_source = PathBuf::from(&config.root_dir).join(&location);
+                    if thumbnail_manager::is_thumbnailable(&thumbnail_source, &config.mime) {
+                        thumbnail_manager::queue_thumbnail(config.clone(), thumbnail_source);
+                    }
+                }
+
+                let message = if skipped {
+                    "File already exists with identical content, upload skipped"
+                } else {
+                    "File uploaded successfully"
+                };
+
+                UploadResult::Uploaded(crate::models::UploadResponse {
+                    message: message.to_string(),
+                    filename,
+                    location,
+                    uploaded_by: user.to_string(),
+                    skipped,
+                    sha512: Some(sha512),
+                })
+            }
+            Ok(StoredUpload::Conflict { existing }) => {
+                UploadResult::Failed(Box::new(UploadFailure {
+                    error: ErrorBody {
+                        code: "already_exists".to_string(),
+                        message: format!("File already exists: {}", filename),
+                        detail: Some(
+                            "Set on_conflict to overwrite or rename to store it anyway".to_string(),
+                        ),
+                        field: None,
+                    },
+                    filename,
+                    existing: Some(*existing),
+                }))
+            }
+            Err(e) if single => return Err(e),
+            Err(e) => UploadResult::Failed(Box::new(UploadFailure {
+                filename,
+                error: e.into_parts().1,
+                existing: None,
+            })),
+        };
+        results.push(result);
+    }
+
+    if single {
+        // Errors were returned above, so a failure is a conflict
+        return Ok(match results.remove(0) {
+            UploadResult::Uploaded(response) => Json(response).into_response(),
+            UploadResult::Failed(failure) => (StatusCode::CONFLICT, Json(failure)).into_response(),
+        });
     }
 
+    Ok(Json(UploadBatchResponse { results, dedup }).into_response())
+}
+
+enum StoredUpload {
+    /// Written, or skipped because an identical file was already there
+    Uploaded {
+        filename: String,
+        location: String,
+        sha512: String,
+        skipped: bool,
+    },
+    /// The file exists and the conflict policy forbids replacing it
+    Conflict { existing: Box<FileInfo> },
+}
+
+// Writes one uploaded file into `upload_dir`, unless a file with the same
+// name and the client's SHA-512 is already there
+fn store_upload(
+    upload_dir: &std::path::Path,
+    canonical_root: &std::path::Path,
+    filename: &str,
+    contents: &[u8],
+    client_sha512: Option<String>,
+    policy: ConflictPolicy,
+    mime: &MimeSettings,
+) -> Result<StoredUpload, AppError> {
+    info!("Received file: {} - Size: {}", filename, contents.len());
+
     // Full path for the file
-    let file_path = upload_dir.join(&filename);
+    let file_path = upload_dir.join(filename);
 
     // Check if file already exists and SHA-512 hash is provided
     if file_path.exists() {
@@ -607,46 +2116,52 @@ pub async fn upload_file(
 
             info!(
                 "Client SHA-512: {}..., Existing file SHA-512: {}...",
-                &client_hash[..16],
-                &existing_hash[..16]
+                hash_prefix(&client_hash),
+                hash_prefix(&existing_hash)
             );
 
             // If hashes match, skip upload
             if client_hash == existing_hash {
                 info!("SHA-512 match - skipping upload for file: {}", filename);
 
-                let relative_path = file_path
-                    .strip_prefix(&canonical_root)
-                    .unwrap_or(&file_path)
-                    .to_string_lossy()
-                    .to_string();
-
-                return Ok(Json(crate::models::UploadResponse {
-                    message: "File already exists with identical content, upload skipped"
-                        .to_string(),
-                    filename,
-                    location: relative_path,
-                    uploaded_by: user.to_string(),
+                return Ok(StoredUpload::Uploaded {
+                    filename: filename.to_string(),
+                    location: relative_to(&file_path, canonical_root),
+                    sha512: existing_hash,
                     skipped: true,
-                    sha512: Some(existing_hash),
-                }));
+                });
             } else {
-                info!("SHA-512 mismatch - file will be replaced");
+                info!("SHA-512 mismatch with existing file");
             }
         } else {
-            info!("No SHA-512 provided - file will be replaced");
+            info!("File already exists and no SHA-512 provided");
         }
     }
 
     info!("Saving file to location: {:?}", file_path);
 
-    // Write the file (will overwrite if exists)
-    let mut file = std::fs::File::create(&file_path).map_err(|e| {
-        error!("Failed to create file: {}", e);
-        AppError::InternalError(format!("Failed to create file: {}", e))
-    })?;
+    let (mut file, file_path, filename) = match create_upload_target(upload_dir, filename, policy) {
+        Ok(created) => created,
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            info!("Refusing to overwrite existing file: {:?}", file_path);
+            let existing = FileInfo::from_path(&file_path, upload_dir, mime).map_err(|e| {
+                error!("Failed to read existing file info: {}", e);
+                AppError::InternalError(format!("Failed to read existing file: {}", e))
+            })?;
+            return Ok(StoredUpload::Conflict {
+                existing: Box::new(existing),
+            });
+        }
+        Err(e) => {
+            error!("Failed to create file: {}", e);
+            return Err(AppError::InternalError(format!(
+                "Failed to create file: {}",
+                e
+            )));
+        }
+    };
 
-    file.write_all(&form.file.contents).map_err(|e| {
+    file.write_all(contents).map_err(|e| {
         error!("Failed to write file: {}", e);
         AppError::InternalError(format!("Failed to write file: {}", e))
     })?;
@@ -662,21 +2177,93 @@ pub async fn upload_file(
         AppError::InternalError(format!("Failed to compute file hash: {}", e))
     })?;
 
-    info!("New file SHA-512: {}...", &new_file_hash[..16]);
+    info!("New file SHA-512: {}...", hash_prefix(&new_file_hash));
 
-    // Get the relative path from root_dir
-    let relative_path = file_path
-        .strip_prefix(&canonical_root)
-        .unwrap_or(&file_path)
-        .to_string_lossy()
-        .to_string();
-
-    Ok(Json(crate::models::UploadResponse {
-        message: "File uploaded successfully".to_string(),
+    Ok(StoredUpload::Uploaded {
+        location: relative_to(&file_path, canonical_root),
         filename,
-        location: relative_path,
-        uploaded_by: user.to_string(),
+        sha512: new_file_hash,
         skipped: false,
-        sha512: Some(new_file_hash),
-    }))
+    })
+}
+
+// Path relative to root_dir as reported to clients
+fn relative_to(path: &std::path::Path, canonical_root: &std::path::Path) -> String {
+    path.strip_prefix(canonical_root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ConflictPolicy {
+    /// Reject the upload with a `409`
+    Fail,
+    /// Replace the existing file
+    Overwrite,
+    /// Store the upload as `name (1).ext`, `name (2).ext`, ...
+    Rename,
+}
+
+// Gives up renaming after this many taken names
+const MAX_RENAME_ATTEMPTS: u32 = 10_000;
+
+// Opens the file an upload is written to and returns it with its path and
+// final name. Except when overwriting the file is created with `create_new`,
+// which makes the existence check and the creation one step so concurrent
+// uploads can't claim the same name.
+fn create_upload_target(
+    upload_dir: &std::path::Path,
+    filename: &str,
+    policy: ConflictPolicy,
+) -> std::io::Result<(fs::File, PathBuf, String)> {
+    let file_path = upload_dir.join(filename);
+    if policy == ConflictPolicy::Overwrite {
+        return Ok((
+            fs::File::create(&file_path)?,
+            file_path,
+            filename.to_string(),
+        ));
+    }
+
+    match create_new_file(&file_path) {
+        Ok(file) => return Ok((file, file_path, filename.to_string())),
+        Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(e),
+        Err(e) if policy == ConflictPolicy::Fail => return Err(e),
+        Err(_) => {}
+    }
+
+    // Like browsers, number before the extension. Dotfiles have no extension.
+    let (stem, extension) = match filename.rfind('.') {
+        Some(dot) if dot > 0 => filename.split_at(dot),
+        _ => (filename, ""),
+    };
+
+    for n in 1..=MAX_RENAME_ATTEMPTS {
+        let candidate = format!("{} ({}){}", stem, n, extension);
+        let candidate_path = upload_dir.join(&candidate);
+        match create_new_file(&candidate_path) {
+            Ok(file) => {
+                info!(
+                    "{} already exists, storing upload as {}",
+                    filename, candidate
+                );
+                return Ok((file, candidate_path, candidate));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(std::io::Error::new(
+        std::io::ErrorKind::AlreadyExists,
+        format!("No free name found for {}", filename),
+    ))
+}
+
+fn create_new_file(path: &std::path::Path) -> std::io::Result<fs::File> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
 }
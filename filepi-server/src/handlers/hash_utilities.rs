@@ -9,3 +9,9 @@ pub fn compute_file_sha512(path: &PathBuf) -> Result<String, std::io::Error> {
     let result = hasher.finalize();
     Ok(format!("{:x}", result))
 }
+
+/// First 16 characters of a hash for logging, the whole string when shorter.
+/// Client supplied hashes can be any length, so slicing directly could panic.
+pub fn hash_prefix(hash: &str) -> &str {
+    hash.get(..16).unwrap_or(hash)
+}
@@ -2,6 +2,13 @@ use serde::Serialize;
 
 use axum::Json;
 
+#[derive(Serialize)]
+pub struct VersionResponse {
+    version: String,
+    git_commit: String,
+    build_timestamp: String,
+}
+
 #[derive(Serialize)]
 pub struct HealthResponse {
     status: String,
@@ -14,3 +21,19 @@ pub async fn health_handler() -> Json<HealthResponse> {
         message: "FilePi Rust server is running".to_string(),
     })
 }
+
+pub async fn version_handler() -> Json<VersionResponse> {
+    // Set by build.rs as seconds since the epoch
+    let build_timestamp = env!("FILEPI_BUILD_TIMESTAMP")
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_default();
+
+    Json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: env!("FILEPI_GIT_COMMIT").to_string(),
+        build_timestamp,
+    })
+}
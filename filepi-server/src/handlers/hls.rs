@@ -0,0 +1,220 @@
+use axum::{
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use std::fmt::Write;
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::Arc;
+use tokio::process::Command;
+use tracing::{debug, error, info};
+
+use crate::config::Config;
+use crate::handlers::app_error::AppError;
+use crate::handlers::extractors::WildcardPath;
+use crate::handlers::media::probe_media;
+use crate::handlers::thumbnail_manager::{get_md5_hash, modified_millis};
+use crate::models::file_info::guess_mime_with_fallback;
+
+const SEGMENT_SECONDS: f64 = 6.0;
+const PLAYLIST_NAME: &str = "playlist.m3u8";
+
+enum HlsResource {
+    Playlist,
+    Segment(u64),
+}
+
+// Handler for GET /api/v1/hls/{*path}/playlist.m3u8 and
+// GET /api/v1/hls/{*path}/segment_N.ts
+// Segments are transcoded lazily on first request and cached per source mtime.
+pub async fn get_hls(
+    State(config): State<Arc<Config>>,
+    WildcardPath(request_path): WildcardPath,
+) -> Result<Response, AppError> {
+    let request_path = request_path.trim_start_matches('/');
+    let (file_path, resource) = request_path
+        .rsplit_once('/')
+        .ok_or_else(|| AppError::NotFound("Not found".to_string()))?;
+    let resource =
+        parse_resource(resource).ok_or_else(|| AppError::NotFound("Not found".to_string()))?;
+
+    let root_dir = PathBuf::from(&config.root_dir);
+    let abs_path = syncfusion_fm_backend::validate_path(&root_dir, file_path)?;
+
+    if !abs_path.is_file() {
+        return Err(AppError::NotFound("File not found".to_string()));
+    }
+
+    if !guess_mime_with_fallback(&abs_path, &config.mime).starts_with("video/") {
+        return Err(AppError::BadRequest("Not a video file".to_string()));
+    }
+
+    let hls_dir = config
+        .cache_dir
+        .join(get_md5_hash(&abs_path.to_string_lossy()))
+        .join(format!("hls_{}", modified_millis(&abs_path)));
+
+    tokio::fs::create_dir_all(&hls_dir).await.map_err(|e| {
+        error!("Failed to create HLS cache directory: {}", e);
+        AppError::InternalError(format!("Failed to create cache directory: {}", e))
+    })?;
+
+    match resource {
+        HlsResource::Playlist => {
+            let playlist_path = hls_dir.join(PLAYLIST_NAME);
+            if !playlist_path.exists() {
+                let duration = video_duration(&config, &abs_path).await?;
+                write_atomically(&playlist_path, build_playlist(duration).as_bytes()).await?;
+            }
+
+            info!("Serving HLS playlist for {:?}", abs_path);
+            serve_cached(&playlist_path, "application/vnd.apple.mpegurl").await
+        }
+        HlsResource::Segment(index) => {
+            let segment_path = hls_dir.join(format!("segment_{}.ts", index));
+            if !segment_path.exists() {
+                let duration = video_duration(&config, &abs_path).await?;
+                let start = index as f64 * SEGMENT_SECONDS;
+                if start >= duration {
+                    return Err(AppError::NotFound(format!("Segment {} not found", index)));
+                }
+
+                let length = SEGMENT_SECONDS.min(duration - start);
+                transcode_segment(&config, &abs_path, start, length, &segment_path).await?;
+            }
+
+            debug!("Serving HLS segment {} of {:?}", index, abs_path);
+            serve_cached(&segment_path, "video/mp2t").await
+        }
+    }
+}
+
+fn parse_resource(name: &str) -> Option<HlsResource> {
+    if name == PLAYLIST_NAME {
+        return Some(HlsResource::Playlist);
+    }
+
+    name.strip_prefix("segment_")
+        .and_then(|rest| rest.strip_suffix(".ts"))
+        .and_then(|index| index.parse().ok())
+        .map(HlsResource::Segment)
+}
+
+async fn video_duration(config: &Config, path: &FsPath) -> Result<f64, AppError> {
+    probe_media(config, path)
+        .await?
+        .duration
+        .filter(|duration| *duration > 0.0)
+        .ok_or_else(|| AppError::BadRequest("Unable to determine video duration".to_string()))
+}
+
+// Fixed length VOD playlist, segment URIs are relative to the playlist
+fn build_playlist(duration: f64) -> String {
+    let segment_count = (duration / SEGMENT_SECONDS).ceil() as u64;
+
+    let mut playlist = String::new();
+    playlist.push_str("#EXTM3U\n");
+    playlist.push_str("#EXT-X-VERSION:3\n");
+    let _ = writeln!(playlist, "#EXT-X-TARGETDURATION:{}", SEGMENT_SECONDS.ceil());
+    playlist.push_str("#EXT-X-MEDIA-SEQUENCE:0\n");
+    playlist.push_str("#EXT-X-PLAYLIST-TYPE:VOD\n");
+
+    for index in 0..segment_count {
+        let start = index as f64 * SEGMENT_SECONDS;
+        let length = SEGMENT_SECONDS.min(duration - start);
+        let _ = writeln!(playlist, "#EXTINF:{:.3},", length);
+        let _ = writeln!(playlist, "segment_{}.ts", index);
+    }
+
+    playlist.push_str("#EXT-X-ENDLIST\n");
+    playlist
+}
+
+async fn transcode_segment(
+    config: &Config,
+    source: &FsPath,
+    start: f64,
+    length: f64,
+    target: &FsPath,
+) -> Result<(), AppError> {
+    // Write next to the final path and rename, so concurrent requests never
+    // serve a half written segment
+    let partial = target.with_extension("partial");
+    let start = format!("{:.3}", start);
+
+    // Segments are cut independently, so they are re-encoded to start on a
+    // keyframe. The timestamp offset keeps them continuous for the player.
+    let output = Command::new(&config.ffmpeg_path)
+        .args(["-v", "error", "-ss", &start, "-i"])
+        .arg(source)
+        .args([
+            "-t",
+            &format!("{:.3}", length),
+            "-map",
+            "0:v:0",
+            "-map",
+            "0:a:0?",
+            "-c:v",
+            "libx264",
+            "-preset",
+            "veryfast",
+            "-c:a",
+            "aac",
+            "-output_ts_offset",
+            &start,
+            "-f",
+            "mpegts",
+            "-y",
+        ])
+        .arg(&partial)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| {
+            error!("Failed to run FFmpeg: {}", e);
+            AppError::InternalError(format!("Failed to generate segment: {}", e))
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        error!("FFmpeg error: {}", stderr);
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(AppError::Detailed {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            code: "ffmpeg_failed",
+            message: "Failed to generate segment".to_string(),
+            detail: Some(stderr),
+        });
+    }
+
+    tokio::fs::rename(&partial, target).await.map_err(|e| {
+        error!("Failed to store segment: {}", e);
+        AppError::InternalError(format!("Failed to store segment: {}", e))
+    })
+}
+
+async fn write_atomically(target: &FsPath, contents: &[u8]) -> Result<(), AppError> {
+    let partial = target.with_extension("partial");
+    tokio::fs::write(&partial, contents).await.map_err(|e| {
+        error!("Failed to write {:?}: {}", partial, e);
+        AppError::InternalError(format!("Failed to write playlist: {}", e))
+    })?;
+    tokio::fs::rename(&partial, target).await.map_err(|e| {
+        error!("Failed to store {:?}: {}", target, e);
+        AppError::InternalError(format!("Failed to store playlist: {}", e))
+    })
+}
+
+async fn serve_cached(path: &FsPath, content_type: &'static str) -> Result<Response, AppError> {
+    let contents = tokio::fs::read(path).await.map_err(|e| {
+        error!("Failed to read {:?}: {}", path, e);
+        AppError::InternalError(format!("Failed to read cached file: {}", e))
+    })?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, content_type)],
+        contents,
+    )
+        .into_response())
+}
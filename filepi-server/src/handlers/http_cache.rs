@@ -0,0 +1,177 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use mime_guess::mime::Mime;
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+use tracing::error;
+
+use crate::handlers::app_error::AppError;
+
+/// Version tag of a file built from its mtime and size. Used for HTTP
+/// caching, and sent back by clients as `if_match` to detect concurrent edits.
+pub fn etag(metadata: &fs::Metadata) -> String {
+    format!(
+        "\"{:x}-{:x}\"",
+        modified_millis(metadata).unwrap_or_default(),
+        metadata.len()
+    )
+}
+
+/// Whether the request's `If-None-Match` lists `etag`, so the client's copy is
+/// current and a `304` will do
+pub fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag == etag)
+        })
+}
+
+/// Whether a Range may be answered with part of the file. Without If-Range it
+/// may; with it only while it still names this version of the file, by its
+/// ETag or its Last-Modified date. Weak ETags never match, as RFC 9110 asks.
+pub fn if_range_matches(headers: &HeaderMap, etag: &str, metadata: &fs::Metadata) -> bool {
+    let Some(if_range) = headers.get(header::IF_RANGE) else {
+        return true;
+    };
+    let Ok(if_range) = if_range.to_str() else {
+        return false;
+    };
+
+    if if_range.starts_with('"') || if_range.starts_with("W/") {
+        return if_range == etag;
+    }
+
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|since_epoch| since_epoch.as_secs());
+    chrono::DateTime::parse_from_rfc2822(if_range)
+        .ok()
+        .and_then(|date| u64::try_from(date.timestamp()).ok())
+        .is_some_and(|date| Some(date) == modified)
+}
+
+/// Serves the file at `path` through tower-http's ServeFile, which answers
+/// Range requests so downloads can resume. If-Range keeps a resumed download
+/// from mixing two versions of a file. Only the request's `headers` are
+/// needed, so downloads requested with a POSTed form are served the same way.
+pub async fn serve_ranged_file(
+    path: &Path,
+    mime: &Mime,
+    headers: &HeaderMap,
+    disposition: &str,
+) -> Result<Response, AppError> {
+    let metadata = tokio::fs::metadata(path).await.map_err(|e| {
+        error!("Failed to read file metadata: {}", e);
+        AppError::InternalError(format!("Failed to read metadata: {}", e))
+    })?;
+    let etag = etag(&metadata);
+
+    if etag_matches(headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+
+    // ServeFile only answers GET and HEAD, so it always gets a GET
+    let mut request = Request::new(Body::empty());
+    *request.headers_mut() = headers.clone();
+    if !if_range_matches(headers, &etag, &metadata) {
+        request.headers_mut().remove(header::RANGE);
+    }
+
+    let mut response = ServeFile::new_with_mime(path, mime)
+        .oneshot(request)
+        .await
+        .unwrap_or_else(|e| match e {})
+        .map(Body::new);
+
+    let headers = response.headers_mut();
+    headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        headers.insert(header::ETAG, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(disposition) {
+        headers.insert(header::CONTENT_DISPOSITION, value);
+    }
+
+    Ok(response)
+}
+
+/// Modification time of a file in millis, `None` when the platform can't
+/// tell
+pub fn modified_millis(metadata: &fs::Metadata) -> Option<u128> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(name: header::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn matches_listed_or_any_etag() {
+        let etag = "\"1-2\"";
+        assert!(etag_matches(
+            &headers(header::IF_NONE_MATCH, "\"0-1\", \"1-2\""),
+            etag
+        ));
+        assert!(etag_matches(&headers(header::IF_NONE_MATCH, "*"), etag));
+        assert!(!etag_matches(
+            &headers(header::IF_NONE_MATCH, "\"0-1\""),
+            etag
+        ));
+        assert!(!etag_matches(&HeaderMap::new(), etag));
+    }
+
+    #[test]
+    fn if_range_needs_current_strong_etag_or_date() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let metadata = file.as_file().metadata().unwrap();
+        let etag = etag(&metadata);
+        let modified = chrono::DateTime::<chrono::Utc>::from(metadata.modified().unwrap());
+
+        assert!(if_range_matches(&HeaderMap::new(), &etag, &metadata));
+        assert!(if_range_matches(
+            &headers(header::IF_RANGE, &etag),
+            &etag,
+            &metadata
+        ));
+        assert!(!if_range_matches(
+            &headers(header::IF_RANGE, &format!("W/{}", etag)),
+            &etag,
+            &metadata
+        ));
+        assert!(if_range_matches(
+            &headers(header::IF_RANGE, &modified.to_rfc2822()),
+            &etag,
+            &metadata
+        ));
+        assert!(!if_range_matches(
+            &headers(header::IF_RANGE, "Thu, 01 Jan 1970 00:00:00 +0000"),
+            &etag,
+            &metadata
+        ));
+    }
+}
@@ -0,0 +1,247 @@
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use mime_guess::from_path;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, error, info};
+
+use crate::config::Config;
+use crate::handlers::thumbnail_manager::{ThumbnailError, get_md5_hash, modified_millis};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fit {
+    /// Scale to fit inside the requested box, keeping the aspect ratio
+    Contain,
+    /// Scale and crop to fill the requested box exactly
+    Cover,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Jpeg,
+    WebP,
+}
+
+impl OutputFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::WebP => "image/webp",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::WebP => "webp",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ResizeRequest {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fit: Fit,
+    pub format: OutputFormat,
+}
+
+/// Returns the path of a resized copy of the image at `path`, generating it
+/// in `cache_dir` if needed. Cached copies are keyed on the source path, its
+/// mtime and the resize parameters.
+pub async fn get_resized_image(
+    config: Arc<Config>,
+    path: &Path,
+    request: ResizeRequest,
+) -> Result<PathBuf, ThumbnailError> {
+    if !path.is_file() {
+        return Err(ThumbnailError::InvalidInput);
+    }
+
+    let mime_type = from_path(path);
+    if !mime_type
+        .first_or_octet_stream()
+        .essence_str()
+        .starts_with("image/")
+    {
+        return Err(ThumbnailError::InvalidInput);
+    }
+
+    if request.width.is_none() && request.height.is_none() {
+        return Err(ThumbnailError::InvalidInput);
+    }
+
+    let modified = modified_millis(path);
+
+    let cache_dir = config.cache_dir.join(get_md5_hash(&path.to_string_lossy()));
+    let cached_path = cache_dir.join(format!(
+        "image_{}_{}x{}_{:?}.{}",
+        modified,
+        request.width.unwrap_or(0),
+        request.height.unwrap_or(0),
+        request.fit,
+        request.format.extension()
+    ));
+
+    if cached_path.exists() {
+        debug!("Resized image already cached at {:?}", cached_path);
+        return Ok(cached_path);
+    }
+
+    tokio::fs::create_dir_all(&cache_dir).await.map_err(|e| {
+        error!("Failed to create image cache directory: {}", e);
+        ThumbnailError::InternalError(format!("Failed to create image cache directory: {}", e))
+    })?;
+
+    debug!("Resizing {:?} with {:?}", path, request);
+
+    let source = path.to_path_buf();
+    let target = cached_path.clone();
+    tokio::task::spawn_blocking(move || resize_image(&source, &target, request))
+        .await
+        .map_err(|e| {
+            error!("Image resize task failed: {}", e);
+            ThumbnailError::InternalError(format!("Failed to resize image: {}", e))
+        })??;
+
+    info!("Resized image generated successfully");
+
+    Ok(cached_path)
+}
+
+fn resize_image(
+    source: &Path,
+    target: &Path,
+    request: ResizeRequest,
+) -> Result<(), ThumbnailError> {
+    let image = decode_oriented(source)?;
+    let resized = resize(&image, request);
+    save_atomically(&resized, target, request.format)
+}
+
+/// Writes a JPEG thumbnail of the image at `source`, scaled to `width` pixels
+/// wide (never upscaled) like the ffmpeg generated video thumbnails.
+pub fn generate_thumbnail(source: &Path, target: &Path, width: u32) -> Result<(), ThumbnailError> {
+    let image = decode_oriented(source)?;
+    let request = ResizeRequest {
+        width: Some(width),
+        height: None,
+        fit: Fit::Contain,
+        format: OutputFormat::Jpeg,
+    };
+    let resized = resize(&image, request);
+    save_atomically(&resized, target, OutputFormat::Jpeg)
+}
+
+/// Like [`generate_thumbnail`] for an encoded image held in memory, such as
+/// cover art embedded in an audio file.
+pub fn generate_thumbnail_from_bytes(
+    data: &[u8],
+    target: &Path,
+    width: u32,
+) -> Result<(), ThumbnailError> {
+    let image = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| {
+            error!("Failed to read embedded image: {}", e);
+            ThumbnailError::InternalError(format!("Failed to read image: {}", e))
+        })?
+        .decode()
+        .map_err(|e| {
+            error!("Failed to decode embedded image: {}", e);
+            ThumbnailError::InvalidInput
+        })?;
+    let request = ResizeRequest {
+        width: Some(width),
+        height: None,
+        fit: Fit::Contain,
+        format: OutputFormat::Jpeg,
+    };
+    let resized = resize(&image, request);
+    save_atomically(&resized, target, OutputFormat::Jpeg)
+}
+
+// Decodes the image and applies its EXIF orientation, so portrait phone photos
+// come out upright. Images without the tag are left as they are.
+fn decode_oriented(source: &Path) -> Result<DynamicImage, ThumbnailError> {
+    let mut decoder = ImageReader::open(source)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| {
+            error!("Failed to open image {:?}: {}", source, e);
+            ThumbnailError::InternalError(format!("Failed to open image: {}", e))
+        })?
+        .into_decoder()
+        .map_err(|e| {
+            error!("Failed to decode image {:?}: {}", source, e);
+            ThumbnailError::InvalidInput
+        })?;
+
+    let orientation = decoder.orientation().unwrap_or_else(|e| {
+        debug!("Failed to read orientation of {:?}: {}", source, e);
+        Orientation::NoTransforms
+    });
+
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| {
+        error!("Failed to decode image {:?}: {}", source, e);
+        ThumbnailError::InvalidInput
+    })?;
+    image.apply_orientation(orientation);
+
+    Ok(image)
+}
+
+fn save_atomically(
+    image: &DynamicImage,
+    target: &Path,
+    format: OutputFormat,
+) -> Result<(), ThumbnailError> {
+    // JPEG has no alpha channel
+    let image = match format {
+        OutputFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()),
+        OutputFormat::WebP => DynamicImage::ImageRgba8(image.to_rgba8()),
+    };
+
+    let image_format = match format {
+        OutputFormat::Jpeg => ImageFormat::Jpeg,
+        OutputFormat::WebP => ImageFormat::WebP,
+    };
+
+    // Write next to the final path and rename, so concurrent requests never
+    // serve a half written file
+    let partial = target.with_extension("partial");
+    image
+        .save_with_format(&partial, image_format)
+        .map_err(|e| {
+            error!("Failed to encode image: {}", e);
+            ThumbnailError::InternalError(format!("Failed to encode image: {}", e))
+        })?;
+    std::fs::rename(&partial, target).map_err(|e| {
+        error!("Failed to store image: {}", e);
+        ThumbnailError::InternalError(format!("Failed to store image: {}", e))
+    })
+}
+
+// Never upscales: requested dimensions beyond the source are clamped
+fn resize(image: &DynamicImage, request: ResizeRequest) -> DynamicImage {
+    let (src_width, src_height) = (image.width(), image.height());
+
+    match (request.fit, request.width, request.height) {
+        (Fit::Cover, Some(width), Some(height)) => {
+            // Shrink the requested box uniformly until it fits in the source so
+            // the crop keeps the requested aspect ratio
+            let scale = (src_width as f64 / width as f64)
+                .min(src_height as f64 / height as f64)
+                .min(1.0);
+            let width = ((width as f64 * scale).round() as u32).max(1);
+            let height = ((height as f64 * scale).round() as u32).max(1);
+            image.resize_to_fill(width, height, FilterType::Lanczos3)
+        }
+        (_, width, height) => {
+            let width = width.unwrap_or(src_width).clamp(1, src_width);
+            let height = height.unwrap_or(src_height).clamp(1, src_height);
+            image.resize(width, height, FilterType::Lanczos3)
+        }
+    }
+}
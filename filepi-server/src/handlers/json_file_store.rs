@@ -0,0 +1,86 @@
+use serde::{Serialize, de::DeserializeOwned};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use tempfile::NamedTempFile;
+use tracing::warn;
+
+/// A value kept in a JSON file, read once at startup and rewritten whole on
+/// every change. Shared by the stores under `cache_dir`.
+pub struct JsonFileStore<T> {
+    file_path: PathBuf,
+    value: Mutex<T>,
+}
+
+impl<T: Serialize + DeserializeOwned + Default> JsonFileStore<T> {
+    /// Loads `file_path`, starting from the default when it is absent or
+    /// unreadable
+    pub fn load(file_path: PathBuf) -> Self {
+        let value = match std::fs::read(&file_path) {
+            Ok(contents) => serde_json::from_slice(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring corrupt file {:?}: {}", file_path, e);
+                T::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => T::default(),
+            Err(e) => {
+                warn!("Failed to read {:?}: {}", file_path, e);
+                T::default()
+            }
+        };
+
+        JsonFileStore {
+            file_path,
+            value: Mutex::new(value),
+        }
+    }
+
+    /// Locks the value, changes are only stored by a following `save`
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        self.value.lock().unwrap()
+    }
+
+    /// Writes `value` to a temporary file renamed over the store, so a crash
+    /// never leaves a truncated file behind
+    pub fn save(&self, value: &T) -> std::io::Result<()> {
+        let dir = self.file_path.parent().unwrap_or(Path::new("."));
+        let mut temp = NamedTempFile::new_in(dir)?;
+        temp.write_all(&serde_json::to_vec_pretty(value)?)?;
+        temp.persist(&self.file_path).map_err(|e| e.error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn reloads_saved_value() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("store.json");
+
+        let store = JsonFileStore::<Vec<String>>::load(file_path.clone());
+        let mut value = store.lock();
+        value.push("docs".to_string());
+        store.save(&value).unwrap();
+        drop(value);
+
+        let reloaded = JsonFileStore::<Vec<String>>::load(file_path);
+        assert_eq!(*reloaded.lock(), ["docs"]);
+    }
+
+    #[test]
+    fn starts_empty_without_a_valid_file() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("store.json");
+
+        let missing = JsonFileStore::<Vec<String>>::load(file_path.clone());
+        assert!(missing.lock().is_empty());
+
+        fs::write(&file_path, "{ not json").unwrap();
+        let corrupt = JsonFileStore::<Vec<String>>::load(file_path);
+        assert!(corrupt.lock().is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::config::Config;
+use crate::models::file_info::FileInfo;
+
+struct CachedListing {
+    files: Vec<FileInfo>,
+    cached_at: Instant,
+}
+
+/// Short lived LRU cache of directory listings, keyed by canonical path.
+///
+/// Entries hold every child of the directory (hidden ones included) and
+/// expire after `FILE_PI_LISTING_CACHE_TTL_SECS`. Handlers that write into a
+/// directory must call `invalidate` for it.
+pub struct ListingCache {
+    entries: Option<Mutex<LruCache<PathBuf, CachedListing>>>,
+    ttl: Duration,
+}
+
+impl ListingCache {
+    pub fn new(config: &Config) -> Self {
+        let ttl = Duration::from_secs(config.listing_cache_ttl_secs);
+        let entries = NonZeroUsize::new(config.listing_cache_size)
+            .filter(|_| !ttl.is_zero())
+            .map(|size| Mutex::new(LruCache::new(size)));
+
+        ListingCache { entries, ttl }
+    }
+
+    pub fn get(&self, dir: &Path) -> Option<Vec<FileInfo>> {
+        let mut entries = self.entries.as_ref()?.lock().unwrap();
+
+        let expired = entries.peek(dir)?.cached_at.elapsed() > self.ttl;
+        if expired {
+            entries.pop(dir);
+            return None;
+        }
+
+        debug!("Listing cache hit for {:?}", dir);
+        entries.get(dir).map(|cached| cached.files.clone())
+    }
+
+    pub fn insert(&self, dir: PathBuf, files: Vec<FileInfo>) {
+        if let Some(entries) = &self.entries {
+            entries.lock().unwrap().put(
+                dir,
+                CachedListing {
+                    files,
+                    cached_at: Instant::now(),
+                },
+            );
+        }
+    }
+
+    /// Evicts the listing of `dir`, call after anything inside it changed
+    pub fn invalidate(&self, dir: &Path) {
+        if let Some(entries) = &self.entries {
+            let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+            debug!("Invalidating listing cache for {:?}", dir);
+            entries.lock().unwrap().pop(&dir);
+        }
+    }
+
+    /// Evicts the listings of `dir` and everything below it
+    pub fn invalidate_tree(&self, dir: &Path) {
+        if let Some(entries) = &self.entries {
+            let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+            debug!("Invalidating listing cache below {:?}", dir);
+            let mut entries = entries.lock().unwrap();
+            let stale: Vec<PathBuf> = entries
+                .iter()
+                .filter(|(cached, _)| cached.starts_with(&dir))
+                .map(|(cached, _)| cached.clone())
+                .collect();
+            for cached in stale {
+                entries.pop(&cached);
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::Arc;
+use tokio::process::Command;
+use tracing::{error, info};
+
+use crate::config::Config;
+use crate::handlers::app_error::AppError;
+use crate::handlers::extractors::WildcardPath;
+use crate::handlers::thumbnail_manager::{get_md5_hash, modified_millis};
+use crate::models::file_info::{MimeSettings, guess_mime_with_fallback};
+use crate::models::media_info::{FfprobeOutput, MediaInfo};
+
+#[derive(Debug)]
+pub enum ProbeError {
+    /// ffprobe ran but rejected the file, carries its stderr
+    Failed(String),
+    InternalError(String),
+}
+
+impl From<ProbeError> for AppError {
+    fn from(err: ProbeError) -> Self {
+        match err {
+            ProbeError::Failed(stderr) => AppError::Detailed {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                code: "ffprobe_failed",
+                message: "Failed to probe media file".to_string(),
+                detail: Some(stderr),
+            },
+            ProbeError::InternalError(msg) => AppError::InternalError(msg),
+        }
+    }
+}
+
+// Handler for GET /api/v1/mediainfo/{*path}
+pub async fn get_media_info(
+    State(config): State<Arc<Config>>,
+    WildcardPath(file_path): WildcardPath,
+) -> Result<Json<MediaInfo>, AppError> {
+    let root_dir = PathBuf::from(&config.root_dir);
+    let abs_path =
+        syncfusion_fm_backend::validate_path(&root_dir, file_path.trim_start_matches('/'))?;
+
+    if !abs_path.is_file() {
+        return Err(AppError::NotFound("File not found".to_string()));
+    }
+
+    if !is_media_file(&abs_path, &config.mime) {
+        return Err(AppError::BadRequest("Not a media file".to_string()));
+    }
+
+    info!("Probing media file: {:?}", abs_path);
+
+    Ok(Json(probe_media(&config, &abs_path).await?))
+}
+
+#[derive(Deserialize)]
+pub struct SubtitleParams {
+    /// 0-based index among the subtitle streams, omit to list the tracks
+    pub track: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct SubtitleTrack {
+    pub track: usize,
+    pub stream_index: u32,
+    pub codec_name: Option<String>,
+    pub language: Option<String>,
+    pub title: Option<String>,
+}
+
+// Bitmap subtitle formats ffmpeg can't turn into text
+const IMAGE_SUBTITLE_CODECS: [&str; 3] = ["hdmv_pgs_subtitle", "dvd_subtitle", "dvb_subtitle"];
+
+// Handler for GET /api/v1/subtitles/{*path}?track=N
+// Without `track` the available subtitle tracks are listed as JSON, with it
+// the track is extracted as WebVTT.
+pub async fn get_subtitles(
+    State(config): State<Arc<Config>>,
+    WildcardPath(file_path): WildcardPath,
+    Query(params): Query<SubtitleParams>,
+) -> Result<Response, AppError> {
+    let root_dir = PathBuf::from(&config.root_dir);
+    let abs_path =
+        syncfusion_fm_backend::validate_path(&root_dir, file_path.trim_start_matches('/'))?;
+
+    if !abs_path.is_file() {
+        return Err(AppError::NotFound("File not found".to_string()));
+    }
+
+    if !is_media_file(&abs_path, &config.mime) {
+        return Err(AppError::BadRequest("Not a media file".to_string()));
+    }
+
+    let cache_dir = config
+        .cache_dir
+        .join(get_md5_hash(&abs_path.to_string_lossy()));
+
+    if let Some(track) = params.track {
+        let subtitle_path = cache_dir.join(format!(
+            "subtitle_{}_{}.vtt",
+            modified_millis(&abs_path),
+            track
+        ));
+
+        if !subtitle_path.exists() {
+            let tracks = subtitle_tracks(&probe_media(&config, &abs_path).await?);
+            let Some(subtitle) = tracks.get(track) else {
+                return Err(AppError::NotFound(format!(
+                    "Subtitle track {} not found",
+                    track
+                )));
+            };
+
+            if subtitle
+                .codec_name
+                .as_deref()
+                .is_some_and(|codec| IMAGE_SUBTITLE_CODECS.contains(&codec))
+            {
+                return Err(AppError::BadRequest(
+                    "Subtitle track is image based and can't be converted to WebVTT".to_string(),
+                ));
+            }
+
+            extract_subtitle(&config, &abs_path, track, &cache_dir, &subtitle_path).await?;
+        }
+
+        info!("Serving subtitle track {} of {:?}", track, abs_path);
+
+        let contents = tokio::fs::read(&subtitle_path).await.map_err(|e| {
+            error!("Failed to read subtitle file: {}", e);
+            AppError::InternalError(format!("Failed to read subtitle file: {}", e))
+        })?;
+
+        return Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "text/vtt; charset=utf-8")],
+            contents,
+        )
+            .into_response());
+    }
+
+    let tracks = subtitle_tracks(&probe_media(&config, &abs_path).await?);
+    if tracks.is_empty() {
+        return Err(AppError::NotFound("No subtitle tracks found".to_string()));
+    }
+
+    Ok(Json(tracks).into_response())
+}
+
+fn subtitle_tracks(info: &MediaInfo) -> Vec<SubtitleTrack> {
+    info.streams
+        .iter()
+        .filter(|stream| stream.codec_type == "subtitle")
+        .enumerate()
+        .map(|(track, stream)| SubtitleTrack {
+            track,
+            stream_index: stream.index,
+            codec_name: stream.codec_name.clone(),
+            language: stream.language.clone(),
+            title: stream.title.clone(),
+        })
+        .collect()
+}
+
+async fn extract_subtitle(
+    config: &Config,
+    source: &FsPath,
+    track: usize,
+    cache_dir: &FsPath,
+    target: &FsPath,
+) -> Result<(), AppError> {
+    tokio::fs::create_dir_all(cache_dir).await.map_err(|e| {
+        error!("Failed to create subtitle cache directory: {}", e);
+        AppError::InternalError(format!("Failed to create cache directory: {}", e))
+    })?;
+
+    // Extract next to the final path and rename, so concurrent requests never
+    // serve a half written file
+    let partial = target.with_extension("partial");
+
+    let output = Command::new(&config.ffmpeg_path)
+        .args(["-v", "error", "-i"])
+        .arg(source)
+        .args(["-map", &format!("0:s:{}", track), "-f", "webvtt", "-y"])
+        .arg(&partial)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| {
+            error!("Failed to run FFmpeg: {}", e);
+            AppError::InternalError(format!("Failed to extract subtitles: {}", e))
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        error!("FFmpeg error: {}", stderr);
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(AppError::Detailed {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            code: "ffmpeg_failed",
+            message: "Failed to extract subtitles".to_string(),
+            detail: Some(stderr),
+        });
+    }
+
+    tokio::fs::rename(&partial, target).await.map_err(|e| {
+        error!("Failed to store subtitle file: {}", e);
+        AppError::InternalError(format!("Failed to store subtitle file: {}", e))
+    })
+}
+
+pub fn is_media_file(path: &FsPath, mime: &MimeSettings) -> bool {
+    let mime_type = guess_mime_with_fallback(path, mime);
+    mime_type.starts_with("video/") || mime_type.starts_with("audio/")
+}
+
+/// Runs ffprobe on `path` and returns its format and stream details
+pub async fn probe_media(config: &Config, path: &FsPath) -> Result<MediaInfo, ProbeError> {
+    let output = Command::new(&config.ffprobe_path)
+        .args([
+            "-v",
+            "error",
+            "-print_format",
+            "json",
+            "-show_format",
+            "-show_streams",
+        ])
+        .arg(path)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| {
+            error!("Failed to run ffprobe: {}", e);
+            ProbeError::InternalError(format!("Failed to run ffprobe: {}", e))
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        error!("ffprobe error: {}", stderr);
+        return Err(ProbeError::Failed(stderr));
+    }
+
+    let parsed: FfprobeOutput = serde_json::from_slice(&output.stdout).map_err(|e| {
+        error!("Failed to parse ffprobe output: {}", e);
+        ProbeError::InternalError(format!("Failed to parse ffprobe output: {}", e))
+    })?;
+
+    Ok(parsed.into())
+}
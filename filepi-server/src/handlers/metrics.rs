@@ -0,0 +1,44 @@
+use axum::{Json, extract::State};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::models::{DedupSummary, MetricsResponse};
+
+/// Running totals of the SHA-512 upload deduplication since the server
+/// started, so clients can see whether identical files are being skipped.
+#[derive(Default)]
+pub struct UploadStats {
+    files_written: AtomicU64,
+    files_skipped: AtomicU64,
+    bytes_written: AtomicU64,
+    bytes_skipped: AtomicU64,
+}
+
+impl UploadStats {
+    pub fn add(&self, summary: &DedupSummary) {
+        self.files_written
+            .fetch_add(summary.files_written, Ordering::Relaxed);
+        self.files_skipped
+            .fetch_add(summary.files_skipped, Ordering::Relaxed);
+        self.bytes_written
+            .fetch_add(summary.bytes_written, Ordering::Relaxed);
+        self.bytes_skipped
+            .fetch_add(summary.bytes_skipped, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> DedupSummary {
+        DedupSummary {
+            files_written: self.files_written.load(Ordering::Relaxed),
+            files_skipped: self.files_skipped.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            bytes_skipped: self.bytes_skipped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Handler for GET /api/v1/metrics
+pub async fn get_metrics(State(upload_stats): State<Arc<UploadStats>>) -> Json<MetricsResponse> {
+    Json(MetricsResponse {
+        uploads: upload_stats.snapshot(),
+    })
+}
@@ -1,7 +1,24 @@
+pub mod admin;
 pub mod app_error;
+pub mod audio;
+pub mod capabilities;
+pub mod copy;
+pub mod extractors;
+pub mod favorites;
 pub mod files;
 pub mod hash_utilities;
 pub mod health;
+pub mod hls;
+pub mod http_cache;
+pub mod image_resizer;
+pub mod json_file_store;
+pub mod listing_cache;
+pub mod media;
+pub mod metrics;
 pub mod result_handler;
 pub mod syncfusion;
+pub mod tag_store;
+pub mod tags;
+pub mod text_files;
 pub mod thumbnail_manager;
+pub mod tree;
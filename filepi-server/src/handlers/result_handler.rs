@@ -1,26 +1,42 @@
 use axum::Json;
+use std::cmp::Ordering;
 
 use crate::handlers::app_error::AppError;
 use crate::models::file_info::FileInfo;
 
 use crate::models::{FileQuery, FilesResponse};
 
+/// Entries per page when a request leaves out `limit`
+pub const DEFAULT_PAGE_SIZE: usize = 25;
+
 pub fn format_result(
     files: &mut Vec<FileInfo>,
     params: &FileQuery,
 ) -> Result<Json<FilesResponse>, AppError> {
     let skip = params.skip.unwrap_or(0);
-    let limit = params.limit.unwrap_or(25);
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
     let total = files.len();
+    let total_size = files
+        .iter()
+        .filter(|file| !file.is_directory)
+        .map(|file| file.size)
+        .sum();
 
     // Sorting modifies the vector → that's why it's &mut
     let sort_by = params.sort_by.as_deref();
-    let order = params.order.as_deref().unwrap_or("asc");
-    let is_desc = order == "desc";
+    let is_desc = is_descending(params)?;
+    let group_dirs = params.group_dirs;
 
     if let Some(sort_field) = sort_by {
         // Validate sort field
-        let valid_fields = ["name", "size", "modified_time", "created_time", "file_type"];
+        let valid_fields = [
+            "name",
+            "size",
+            "modified_time",
+            "created_time",
+            "file_type",
+            "score",
+        ];
         if !valid_fields.contains(&sort_field) {
             return Err(AppError::BadRequest(format!(
                 "Invalid sort field: {}",
@@ -28,14 +44,15 @@ pub fn format_result(
             )));
         }
 
-        // Sort with directories always first
+        // Sort with directories first unless group_dirs is turned off
         files.sort_by(|a, b| {
-            // ALWAYS sort directories first, then files - for ALL sort criteria
-            if a.is_directory != b.is_directory {
+            // Directories go before files for ALL sort criteria
+            if group_dirs && a.is_directory != b.is_directory {
                 return b.is_directory.cmp(&a.is_directory);
             }
 
-            // If both are directories or both are files, then sort by the specified field
+            // Entries of the same kind, or any two without grouping, are
+            // sorted by the specified field
             match sort_field {
                 "name" => {
                     if is_desc {
@@ -72,28 +89,290 @@ pub fn format_result(
                         a.file_type.cmp(&b.file_type)
                     }
                 }
-                _ => std::cmp::Ordering::Equal,
+                "score" => {
+                    if is_desc {
+                        b.score.cmp(&a.score)
+                    } else {
+                        a.score.cmp(&b.score)
+                    }
+                }
+                _ => Ordering::Equal,
             }
         });
     } else {
-        // Default sorting: directories first, then by name (ascending)
         files.sort_by(|a, b| {
-            // Always sort directories first, then files
-            if a.is_directory != b.is_directory {
-                return b.is_directory.cmp(&a.is_directory);
-            }
-            // If both are directories or both are files, sort by name
-            a.name.cmp(&b.name)
+            default_order(
+                (a.is_directory, &a.name),
+                (b.is_directory, &b.name),
+                group_dirs,
+            )
         });
     }
 
     // Pagination
     let paginated_files: Vec<FileInfo> = files.iter().skip(skip).take(limit).cloned().collect();
 
+    format_page(paginated_files, total, total_size, params)
+}
+
+/// Response for a page already cut from a listing of `total` entries, whose
+/// files add up to `total_size` bytes
+pub fn format_page(
+    mut files: Vec<FileInfo>,
+    total: usize,
+    total_size: u64,
+    params: &FileQuery,
+) -> Result<Json<FilesResponse>, AppError> {
+    is_descending(params)?;
+
+    if params.iso_dates {
+        files.iter_mut().for_each(FileInfo::add_iso_dates);
+    }
+
     Ok(Json(FilesResponse {
-        files: paginated_files,
+        files,
         total_files: total,
-        skip,
-        limit,
+        total_size,
+        skip: params.skip.unwrap_or(0),
+        limit: params.limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        cwd: None,
+        disk_free: None,
+        disk_total: None,
+        truncated: false,
+        timed_out: false,
     }))
 }
+
+/// Order used without `sort_by`: directories first unless `group_dirs` is
+/// off, then by name ascending. Takes `(is_directory, name)` so listings can
+/// sort before building FileInfo.
+pub fn default_order(a: (bool, &str), b: (bool, &str), group_dirs: bool) -> Ordering {
+    let kind = if group_dirs {
+        b.0.cmp(&a.0)
+    } else {
+        Ordering::Equal
+    };
+    kind.then_with(|| a.1.cmp(b.1))
+}
+
+fn is_descending(params: &FileQuery) -> Result<bool, AppError> {
+    let order = params.order.as_deref().unwrap_or("asc");
+    if !["asc", "desc"].contains(&order) {
+        return Err(AppError::BadRequest(format!(
+            "Invalid sort order: {}",
+            order
+        )));
+    }
+    Ok(order == "desc")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const FIELDS: [&str; 6] = [
+        "name",
+        "size",
+        "modified_time",
+        "created_time",
+        "file_type",
+        "score",
+    ];
+
+    fn entry(
+        name: &str,
+        is_directory: bool,
+        size: u64,
+        modified: u128,
+        created: u128,
+        file_type: &str,
+        score: i64,
+    ) -> FileInfo {
+        FileInfo {
+            name: name.to_string(),
+            full_name: format!("/{}", name),
+            size,
+            is_directory,
+            created_time: Some(created),
+            modified_time: Some(modified),
+            file_type: file_type.to_string(),
+            owner: None,
+            parent_dir: None,
+            rel_path: Some(name.to_string()),
+            is_symlink: false,
+            symlink_target: None,
+            mode: None,
+            has_children: None,
+            child_count: None,
+            tags: None,
+            score: Some(score),
+            created_iso: None,
+            modified_iso: None,
+        }
+    }
+
+    // Every field orders the three files differently. The folders sort
+    // after the files by each field, so only directories-first puts them on top.
+    fn listing() -> Vec<FileInfo> {
+        vec![
+            entry("a.txt", false, 300, 1, 3, "text/plain", 20),
+            entry("z-dir", true, 900, 9, 9, "x-directory", 90),
+            entry("b.png", false, 100, 3, 2, "image/png", 10),
+            entry("c.mp4", false, 200, 2, 1, "video/mp4", 30),
+            entry("y-dir", true, 800, 8, 8, "x-directory", 80),
+        ]
+    }
+
+    fn query(params: serde_json::Value) -> FileQuery {
+        serde_json::from_value(params).unwrap()
+    }
+
+    fn names(response: &FilesResponse) -> Vec<&str> {
+        response
+            .files
+            .iter()
+            .map(|file| file.name.as_str())
+            .collect()
+    }
+
+    fn sorted(params: serde_json::Value) -> FilesResponse {
+        let Ok(Json(response)) = format_result(&mut listing(), &query(params)) else {
+            panic!("sorting failed");
+        };
+        response
+    }
+
+    #[test]
+    fn sorts_directories_first_for_every_field_and_order() {
+        for field in FIELDS {
+            for order in ["asc", "desc"] {
+                let response = sorted(json!({ "sort_by": field, "order": order }));
+                let directories: Vec<bool> = response
+                    .files
+                    .iter()
+                    .map(|file| file.is_directory)
+                    .collect();
+                assert_eq!(
+                    directories,
+                    [true, true, false, false, false],
+                    "sort_by={} order={}",
+                    field,
+                    order
+                );
+            }
+        }
+
+        let response = sorted(json!({}));
+        assert_eq!(
+            names(&response),
+            ["y-dir", "z-dir", "a.txt", "b.png", "c.mp4"]
+        );
+    }
+
+    #[test]
+    fn sorts_descending_by_each_field() {
+        let expected = [
+            ("name", ["c.mp4", "b.png", "a.txt"]),
+            ("size", ["a.txt", "c.mp4", "b.png"]),
+            ("modified_time", ["b.png", "c.mp4", "a.txt"]),
+            ("created_time", ["a.txt", "b.png", "c.mp4"]),
+            ("file_type", ["c.mp4", "a.txt", "b.png"]),
+            ("score", ["c.mp4", "a.txt", "b.png"]),
+        ];
+
+        for (field, files) in expected {
+            let response = sorted(json!({ "sort_by": field, "order": "desc" }));
+            assert_eq!(
+                names(&response)[..2],
+                ["z-dir", "y-dir"],
+                "sort_by={}",
+                field
+            );
+            assert_eq!(names(&response)[2..], files, "sort_by={}", field);
+
+            let response = sorted(json!({ "sort_by": field, "order": "asc" }));
+            let mut ascending = files;
+            ascending.reverse();
+            assert_eq!(names(&response)[2..], ascending, "sort_by={}", field);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_sort_field_and_order() {
+        let result = format_result(&mut listing(), &query(json!({ "sort_by": "owner" })));
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+
+        let result = format_result(
+            &mut listing(),
+            &query(json!({ "sort_by": "name", "order": "up" })),
+        );
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    #[test]
+    fn sorts_directories_with_files_without_grouping() {
+        let response = sorted(json!({ "sort_by": "modified_time", "group_dirs": false }));
+        assert_eq!(
+            names(&response),
+            ["a.txt", "c.mp4", "b.png", "y-dir", "z-dir"]
+        );
+
+        let response = sorted(json!({ "group_dirs": false }));
+        assert_eq!(
+            names(&response),
+            ["a.txt", "b.png", "c.mp4", "y-dir", "z-dir"]
+        );
+    }
+
+    #[test]
+    fn paginates_with_skip_and_limit() {
+        let response = sorted(json!({ "skip": 1, "limit": 2 }));
+
+        assert_eq!(names(&response), ["z-dir", "a.txt"]);
+        assert_eq!(response.total_files, 5);
+        assert_eq!(response.total_size, 600);
+        assert_eq!((response.skip, response.limit), (1, 2));
+    }
+
+    #[test]
+    fn returns_empty_page_past_the_end() {
+        for skip in [5, 50] {
+            let response = sorted(json!({ "skip": skip, "limit": 10 }));
+
+            assert!(response.files.is_empty(), "skip={}", skip);
+            assert_eq!(response.total_files, 5);
+            assert_eq!(response.skip, skip);
+        }
+    }
+
+    #[test]
+    fn adds_iso_dates_only_when_requested() {
+        let response = sorted(json!({ "limit": 1 }));
+        let file = serde_json::to_value(&response.files[0]).unwrap();
+        assert!(file.get("modified_iso").is_none());
+
+        let mut files = vec![entry(
+            "a.txt",
+            false,
+            1,
+            1_700_000_000_123,
+            0,
+            "text/plain",
+            0,
+        )];
+        let Ok(Json(response)) = format_result(&mut files, &query(json!({ "iso_dates": true })))
+        else {
+            panic!("formatting failed");
+        };
+        let file = &response.files[0];
+        assert_eq!(
+            file.modified_iso.as_deref(),
+            Some("2023-11-14T22:13:20.123Z")
+        );
+        assert_eq!(
+            file.created_iso.as_deref(),
+            Some("1970-01-01T00:00:00.000Z")
+        );
+    }
+}
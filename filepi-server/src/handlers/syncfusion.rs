@@ -1,57 +1,124 @@
 use axum::{
     Json,
     body::Body,
-    extract::{Form, Multipart, Query, State},
-    http::{StatusCode, header},
-    response::IntoResponse,
+    extract::{Form, Multipart, Query, Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
 };
 use axum_typed_multipart::{FieldData, TryFromMultipart, TypedMultipart};
 use bytes::Bytes;
-use mime_guess::from_path;
 use serde::Deserialize;
 use std::io::Write;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
-use tokio_util::io::ReaderStream;
-use tracing::{debug, error, info};
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+use tracing::{debug, error, info, warn};
 
 use crate::config::Config;
 use crate::handlers::app_error::AppError;
+use crate::handlers::extractors::AppJson;
+use crate::handlers::http_cache;
+use crate::handlers::listing_cache::ListingCache;
+use crate::handlers::tag_store::TagStore;
+use crate::handlers::thumbnail_manager;
+use crate::models::file_info::guess_mime;
 
-use syncfusion_fm_backend::{FileManagerDirectoryContent, FileManagerResponse};
+use syncfusion_fm_backend::{ErrorDetails, FileManagerDirectoryContent, FileManagerResponse};
 
 pub async fn file_operations(
     State(config): State<Arc<Config>>,
-    Json(args): Json<FileManagerDirectoryContent>,
+    State(listing_cache): State<Arc<ListingCache>>,
+    State(tag_store): State<Arc<TagStore>>,
+    AppJson(args): AppJson<FileManagerDirectoryContent>,
 ) -> Result<Json<FileManagerResponse>, AppError> {
     debug!("Syncfusion FileManager action: {:?}", args);
 
-    // call the process_file_manager_request function from syncfusion-fm-backend
+    // call the process_file_manager_request_excluding function from syncfusion-fm-backend,
+    // which leaves the server's own folders out of listings
     let root_dir = PathBuf::from(&config.root_dir);
-    let response = syncfusion_fm_backend::process_file_manager_request(&args, &root_dir);
+    let response = syncfusion_fm_backend::process_file_manager_request_excluding(
+        &args,
+        &root_dir,
+        &config.system_dirs(&root_dir),
+    );
+
+    // Evict the listings this action may have changed
+    let action = args.action.as_deref().unwrap_or("");
+    if matches!(action, "create" | "delete" | "rename" | "copy" | "move") {
+        for path in [&args.path, &args.target_path].into_iter().flatten() {
+            listing_cache.invalidate(&root_dir.join(path.trim_start_matches('/')));
+        }
+    }
+
+    if let Err(e) = update_tags(&tag_store, &args, &response) {
+        warn!("Failed to update tags after {}: {}", action, e);
+    }
+
     Ok(Json(response))
 }
 
+// Lets tags follow renamed and moved entries and drops those of deleted ones
+fn update_tags(
+    tag_store: &TagStore,
+    args: &FileManagerDirectoryContent,
+    response: &FileManagerResponse,
+) -> std::io::Result<()> {
+    let dir = args.path.as_deref().unwrap_or("").trim_matches('/');
+    let join = |dir: &str, name: &str| format!("{}/{}", dir.trim_matches('/'), name);
+    let done = response.files.as_deref().unwrap_or_default();
+
+    match args.action.as_deref() {
+        Some("rename") if response.error.is_none() => {
+            if let (Some(name), Some(new_name)) = (&args.name, &args.new_name) {
+                tag_store.rename(&join(dir, name), &join(dir, new_name))?;
+            }
+        }
+        Some("move") => {
+            let target = args.target_path.as_deref().unwrap_or("");
+            for file in done {
+                if let (Some(from), Some(to)) = (&file.previous_name, &file.name) {
+                    tag_store.rename(&join(dir, from), &join(target, to))?;
+                }
+            }
+        }
+        Some("delete") => {
+            for file in done {
+                if let Some(name) = &file.name {
+                    tag_store.remove(&join(dir, name))?;
+                }
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
 #[derive(Deserialize)]
 pub struct GetImageParams {
     #[serde(alias = "Path")]
     pub path: String,
 }
 
+// Served through tower-http's ServeFile, which answers Range requests and
+// If-Modified-Since with Last-Modified, so previews of large images can resume
+// and revalidate instead of downloading again. An ETag built like the text
+// endpoints' lets clients revalidate with If-None-Match too.
 pub async fn get_image(
     State(config): State<Arc<Config>>,
     Query(params): Query<GetImageParams>,
-) -> Result<impl IntoResponse, AppError> {
+    request: Request,
+) -> Result<Response, AppError> {
     let path = params.path;
     info!("Syncfusion GetImage: {}", path);
 
     let relative_path = path.trim_start_matches('/');
     let root_dir = PathBuf::from(&config.root_dir);
 
-    let full_path = syncfusion_fm_backend::validate_path(&root_dir, relative_path)
-        .map_err(|_| AppError::BadRequest("Invalid path".to_string()))?;
+    let full_path = syncfusion_fm_backend::validate_path(&root_dir, relative_path)?;
 
     if !full_path.exists() {
         return Err(AppError::NotFound("File not found".to_string()));
@@ -61,29 +128,41 @@ pub async fn get_image(
         return Err(AppError::BadRequest("Path is not a file".to_string()));
     }
 
-    let file = File::open(&full_path).await.map_err(|e| {
-        error!("Failed to open file: {}", e);
-        AppError::InternalError(format!("Failed to open file: {}", e))
-    })?;
-
-    let metadata = file.metadata().await.map_err(|e| {
-        error!("Failed to read metadata: {}", e);
+    let metadata = std::fs::metadata(&full_path).map_err(|e| {
+        error!("Failed to read image metadata: {}", e);
         AppError::InternalError(format!("Failed to read metadata: {}", e))
     })?;
+    let etag = http_cache::etag(&metadata);
+    let cache_control = format!("public, max-age={}", config.image_cache_max_age_secs);
+
+    if http_cache::etag_matches(request.headers(), &etag) {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, etag), (header::CACHE_CONTROL, cache_control)],
+        )
+            .into_response());
+    }
+
+    let mime_type = guess_mime(&full_path, &config.mime)
+        .parse()
+        .unwrap_or(mime_guess::mime::APPLICATION_OCTET_STREAM);
+
+    let mut response = ServeFile::new_with_mime(&full_path, &mime_type)
+        .oneshot(request)
+        .await
+        .unwrap_or_else(|e| match e {})
+        .map(Body::new);
+
+    let headers = response.headers_mut();
+    headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        headers.insert(header::ETAG, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&cache_control) {
+        headers.insert(header::CACHE_CONTROL, value);
+    }
 
-    let mime_type = "application/octet-stream".to_string();
-    let stream = ReaderStream::new(file);
-    let body = Body::from_stream(stream);
-
-    Ok((
-        StatusCode::OK,
-        [
-            (header::CONTENT_TYPE, mime_type),
-            (header::CONTENT_LENGTH, metadata.len().to_string()),
-            (header::CACHE_CONTROL, "public, max-age=3600".to_string()),
-        ],
-        body,
-    ))
+    Ok(response)
 }
 
 #[derive(Deserialize)]
@@ -92,10 +171,13 @@ pub struct DownloadForm {
     pub download_input: String,
 }
 
+// A single file is served like GET /file, so interrupted downloads can resume
+// with a Range, guarded by If-Range
 pub async fn download(
     State(config): State<Arc<Config>>,
+    headers: HeaderMap,
     Form(form): Form<DownloadForm>,
-) -> Result<impl IntoResponse, AppError> {
+) -> Result<Response, AppError> {
     info!("Syncfusion Download");
 
     let args: FileManagerDirectoryContent =
@@ -125,8 +207,7 @@ pub async fn download(
     };
 
     let root_dir = PathBuf::from(&config.root_dir);
-    let full_path = syncfusion_fm_backend::validate_path(&root_dir, &relative_path)
-        .map_err(|_| AppError::BadRequest("Invalid path".to_string()))?;
+    let full_path = syncfusion_fm_backend::validate_path(&root_dir, &relative_path)?;
 
     if !full_path.exists() {
         return Err(AppError::NotFound("File not found".to_string()));
@@ -136,31 +217,12 @@ pub async fn download(
         return Err(AppError::BadRequest("Path is not a file".to_string()));
     }
 
-    let file = File::open(&full_path).await.map_err(|e| {
-        error!("Failed to open file: {}", e);
-        AppError::InternalError(format!("Failed to open file: {}", e))
-    })?;
-
-    let metadata = file.metadata().await.map_err(|e| {
-        error!("Failed to read metadata: {}", e);
-        AppError::InternalError(format!("Failed to read metadata: {}", e))
-    })?;
+    let mime_type = guess_mime(&full_path, &config.mime)
+        .parse()
+        .unwrap_or(mime_guess::mime::APPLICATION_OCTET_STREAM);
+    let disposition = format!("attachment; filename=\"{}\"", file_name);
 
-    let mime_type = from_path(&full_path).first_or_octet_stream().to_string();
-    let stream = ReaderStream::new(file);
-    let body = Body::from_stream(stream);
-
-    let filename_header = format!("attachment; filename=\"{}\"", file_name);
-
-    Ok((
-        StatusCode::OK,
-        [
-            (header::CONTENT_TYPE, mime_type),
-            (header::CONTENT_LENGTH, metadata.len().to_string()),
-            (header::CONTENT_DISPOSITION, filename_header),
-        ],
-        body,
-    ))
+    http_cache::serve_ranged_file(&full_path, &mime_type, &headers, &disposition).await
 }
 
 #[derive(Deserialize)]
@@ -173,14 +235,18 @@ pub struct UploadParams {
 
 pub async fn upload(
     State(config): State<Arc<Config>>,
+    State(listing_cache): State<Arc<ListingCache>>,
     Query(params): Query<UploadParams>,
     mut multipart: Multipart,
-) -> Result<impl IntoResponse, AppError> {
+) -> Result<Response, AppError> {
     info!("Syncfusion Upload2 (Streaming)");
     info!("Query params - path: {:?}, action: {:?}", params.path, params.action);
 
     let root_dir = PathBuf::from(&config.root_dir);
     let mut current_path = params.path.unwrap_or_else(|| String::from("/"));
+    let mut upload_action = params.action.unwrap_or_else(|| String::from("save"));
+    let mut uploaded = Vec::new();
+    let mut existing = Vec::new();
 
     while let Some(field) = multipart.next_field().await.map_err(|e| {
         error!("Failed to get next field: {}", e);
@@ -198,31 +264,58 @@ pub async fn upload(
         } else if name == "action" {
              if let Ok(val) = field.text().await {
                 info!("Multipart action: '{}'", val);
+                upload_action = val;
             }
         } else if name == "uploadFiles" {
             let file_name = field.file_name().unwrap_or("uploaded_file").to_string();
+
+            // Only plain names, like the Syncfusion create, the client sends
+            // the folder in `path`
+            if file_name.contains('/')
+                || file_name.contains('\\')
+                || file_name.is_empty()
+                || file_name == "."
+                || file_name == ".."
+            {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid file name '{}': names cannot contain path separators",
+                    file_name
+                )));
+            }
             info!("Processing file field: '{}'. Current path context: '{}'", file_name, current_path);
 
             let relative_path = current_path.trim_start_matches('/');
             info!("Root dir: {:?}, Relative path: '{}'", root_dir, relative_path);
 
-            let canonical_upload_dir = syncfusion_fm_backend::validate_path(&root_dir, relative_path)
-                .map_err(|_| {
-                    error!("Path validation failed for relative path: '{}'", relative_path);
-                    AppError::BadRequest("Invalid upload path".to_string())
-                })?;
-            
+            // Creates missing folders and re-checks the result, so the final
+            // directory can't have escaped root_dir
+            let canonical_upload_dir =
+                syncfusion_fm_backend::create_dir_within_root(&root_dir, relative_path)?;
+
             info!("Canonical upload dir: {:?}", canonical_upload_dir);
 
-            if !canonical_upload_dir.exists() {
-                info!("Creating directory: {:?}", canonical_upload_dir);
-                tokio::fs::create_dir_all(&canonical_upload_dir).await.map_err(|e| {
-                    error!("Failed to create upload directory: {}", e);
-                    AppError::InternalError(format!("Failed to create directory: {}", e))
-                })?;
+            // "save" leaves existing files alone and reports them so the
+            // client can ask whether to "replace" them or "keepboth"
+            // Re-checked like the directory, so a link in its place can't
+            // send the contents outside root_dir
+            let mut file_path =
+                syncfusion_fm_backend::validate_path(&canonical_upload_dir, &file_name)?;
+            if file_path.symlink_metadata().is_ok() {
+                match upload_action.as_str() {
+                    "replace" => info!("Replacing existing file: {:?}", file_path),
+                    "keepboth" => {
+                        file_path = syncfusion_fm_backend::next_free_path(
+                            &canonical_upload_dir,
+                            &file_name,
+                        );
+                    }
+                    _ => {
+                        info!("File already exists: {:?}", file_path);
+                        existing.push(file_name);
+                        continue;
+                    }
+                }
             }
-
-            let file_path = canonical_upload_dir.join(&file_name);
             info!("Target file path: {:?}", file_path);
 
             info!("Saving file to: {:?}", file_path);
@@ -250,10 +343,56 @@ pub async fn upload(
                  AppError::InternalError(format!("Failed to flush file: {}", e))
             })?;
             info!("File saved successfully. Total bytes: {}", total_bytes);
+
+            let filter_path = match relative_path.trim_end_matches('/') {
+                "" => "/".to_string(),
+                relative => format!("/{}/", relative),
+            };
+            match syncfusion_fm_backend::file_entry(&file_path, &filter_path) {
+                Ok(entry) => uploaded.push(entry),
+                Err(e) => warn!("Failed to read uploaded file {:?}: {}", file_path, e),
+            }
+
+            listing_cache.invalidate(&canonical_upload_dir);
+            if let Some(parent) = canonical_upload_dir.parent() {
+                // The upload directory may have just been created
+                listing_cache.invalidate(parent);
+            }
+
+            // Warm the thumbnail under the same path the thumbnail route uses
+            let thumbnail_source = root_dir
+                .join(relative_path)
+                .join(file_path.file_name().unwrap_or_default());
+            if thumbnail_manager::is_thumbnailable(&thumbnail_source, &config.mime) {
+                thumbnail_manager::queue_thumbnail(config.clone(), thumbnail_source);
+            }
         } else {
             info!("Ignoring field: name='{}'", name);
         }
     }
 
-    Ok(StatusCode::OK)
+    // The Syncfusion client expects existing files as a 400 listing them in
+    // `fileExists`, next to whatever did get uploaded
+    let (status, error) = if existing.is_empty() {
+        (StatusCode::OK, None)
+    } else {
+        (
+            StatusCode::BAD_REQUEST,
+            Some(ErrorDetails {
+                code: Some("400".to_string()),
+                message: Some("File already exists.".to_string()),
+                file_exists: Some(existing),
+                failed_items: None,
+            }),
+        )
+    };
+
+    let response = FileManagerResponse {
+        cwd: None,
+        files: Some(uploaded),
+        error,
+        details: None,
+    };
+
+    Ok((status, Json(response)).into_response())
 }
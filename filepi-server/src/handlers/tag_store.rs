@@ -0,0 +1,122 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use tracing::debug;
+
+use crate::config::Config;
+use crate::handlers::json_file_store::JsonFileStore;
+use crate::models::file_info::FileInfo;
+
+const INDEX_FILE: &str = "tags.json";
+
+type TagIndex = BTreeMap<String, BTreeSet<String>>;
+
+/// Tags attached to files and directories without touching them.
+///
+/// The index lives in `tags.json` under `cache_dir`, keyed by the path
+/// relative to `root_dir`. It is read once at startup and rewritten on every
+/// change. Handlers that rename, move or delete entries must call
+/// `rename`/`remove` so the tags follow.
+pub struct TagStore {
+    tags: JsonFileStore<TagIndex>,
+}
+
+impl TagStore {
+    /// Loads the index, starting empty when it is absent or unreadable
+    pub fn load(config: &Config) -> Self {
+        TagStore {
+            tags: JsonFileStore::load(config.cache_dir.join(INDEX_FILE)),
+        }
+    }
+
+    pub fn get(&self, path: &str) -> Vec<String> {
+        self.tags
+            .lock()
+            .get(&key(path))
+            .map(|tags| tags.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Replaces the tags of `path`, an empty set removes them
+    pub fn set(&self, path: &str, tags: BTreeSet<String>) -> std::io::Result<()> {
+        let mut index = self.tags.lock();
+        if tags.is_empty() {
+            index.remove(&key(path));
+        } else {
+            index.insert(key(path), tags);
+        }
+        self.tags.save(&index)
+    }
+
+    /// Relative paths carrying `tag`
+    pub fn paths_with_tag(&self, tag: &str) -> Vec<String> {
+        self.tags
+            .lock()
+            .iter()
+            .filter(|(_, tags)| tags.contains(tag))
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    /// Moves the tags of `from` and everything below it to `to`
+    pub fn rename(&self, from: &str, to: &str) -> std::io::Result<()> {
+        let (from, to) = (key(from), key(to));
+        let mut index = self.tags.lock();
+
+        let moved: Vec<String> = index
+            .keys()
+            .filter(|path| is_within(path, &from))
+            .cloned()
+            .collect();
+        if moved.is_empty() {
+            return Ok(());
+        }
+
+        for path in moved {
+            if let Some(tags) = index.remove(&path) {
+                index.insert(format!("{}{}", to, &path[from.len()..]), tags);
+            }
+        }
+        debug!("Moved tags from {} to {}", from, to);
+        self.tags.save(&index)
+    }
+
+    /// Drops the tags of `path` and everything below it
+    pub fn remove(&self, path: &str) -> std::io::Result<()> {
+        let path = key(path);
+        let mut index = self.tags.lock();
+        let before = index.len();
+        index.retain(|tagged, _| !is_within(tagged, &path));
+        if index.len() == before {
+            return Ok(());
+        }
+        self.tags.save(&index)
+    }
+
+    /// Fills in `tags` of every entry, `root` is the canonical root_dir the
+    /// entries' `full_name` starts with
+    pub fn annotate(&self, files: &mut [FileInfo], root: &Path) {
+        let index = self.tags.lock();
+        for file in files {
+            let tags = Path::new(&file.full_name)
+                .strip_prefix(root)
+                .ok()
+                .and_then(|relative| index.get(&key(&relative.to_string_lossy())));
+            file.tags = Some(
+                tags.map(|tags| tags.iter().cloned().collect())
+                    .unwrap_or_default(),
+            );
+        }
+    }
+}
+
+fn key(path: &str) -> String {
+    path.trim_matches('/').to_string()
+}
+
+fn is_within(path: &str, ancestor: &str) -> bool {
+    path == ancestor
+        || ancestor.is_empty()
+        || path
+            .strip_prefix(ancestor)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
@@ -0,0 +1,105 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::config::Config;
+use crate::handlers::app_error::{AppError, display_path};
+use crate::handlers::extractors::AppJson;
+use crate::handlers::tag_store::TagStore;
+use crate::models::{TagsQuery, TagsRequest, TagsResponse};
+
+const MAX_TAG_LENGTH: usize = 64;
+const MAX_TAGS: usize = 100;
+
+// Handler for GET /api/v1/tags?path=...
+pub async fn get_tags(
+    State(config): State<Arc<Config>>,
+    State(tag_store): State<Arc<TagStore>>,
+    Query(params): Query<TagsQuery>,
+) -> Result<Json<TagsResponse>, AppError> {
+    let path = params.path.trim_matches('/');
+    let root_dir = PathBuf::from(&config.root_dir);
+    let full_path = syncfusion_fm_backend::validate_path(&root_dir, path)?;
+    if !full_path.exists() {
+        return Err(AppError::NotFound(format!(
+            "Path not found: {}",
+            display_path(path)
+        )));
+    }
+
+    Ok(Json(TagsResponse {
+        path: path.to_string(),
+        tags: tag_store.get(path),
+    }))
+}
+
+// Handler for POST /api/v1/tags
+// Replaces the tags of `path`, an empty list removes them all.
+pub async fn set_tags(
+    State(config): State<Arc<Config>>,
+    State(tag_store): State<Arc<TagStore>>,
+    AppJson(params): AppJson<TagsRequest>,
+) -> Result<Json<TagsResponse>, AppError> {
+    let path = params.path.trim_matches('/');
+    if path.is_empty() {
+        return Err(AppError::BadRequest(
+            "The root directory can't be tagged".to_string(),
+        ));
+    }
+
+    let root_dir = PathBuf::from(&config.root_dir);
+    let full_path = syncfusion_fm_backend::validate_path(&root_dir, path)?;
+    if !full_path.exists() {
+        return Err(AppError::NotFound(format!(
+            "Path not found: {}",
+            display_path(path)
+        )));
+    }
+
+    let tags = normalize_tags(params.tags)?;
+
+    tag_store.set(path, tags).map_err(|e| {
+        error!("Failed to store tags: {}", e);
+        AppError::InternalError(format!("Failed to store tags: {}", e))
+    })?;
+
+    info!("Updated tags of {}", path);
+
+    Ok(Json(TagsResponse {
+        path: path.to_string(),
+        tags: tag_store.get(path),
+    }))
+}
+
+// Trims and deduplicates, rejecting empty, overlong or control characters
+fn normalize_tags(tags: Vec<String>) -> Result<BTreeSet<String>, AppError> {
+    let invalid = |message: String| AppError::Validation {
+        message,
+        field: Some("tags".to_string()),
+    };
+
+    if tags.len() > MAX_TAGS {
+        return Err(invalid(format!("At most {} tags are allowed", MAX_TAGS)));
+    }
+
+    tags.into_iter()
+        .map(|tag| {
+            let tag = tag.trim();
+            if tag.is_empty() || tag.chars().count() > MAX_TAG_LENGTH {
+                return Err(invalid(format!(
+                    "Tags must be 1 to {} characters long",
+                    MAX_TAG_LENGTH
+                )));
+            }
+            if tag.chars().any(char::is_control) {
+                return Err(invalid(format!("Invalid tag: {:?}", tag)));
+            }
+            Ok(tag.to_string())
+        })
+        .collect()
+}
@@ -0,0 +1,635 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::{StatusCode, header},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::fs;
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::NamedTempFile;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, error, info};
+
+use crate::config::Config;
+use crate::handlers::app_error::{AppError, display_path};
+use crate::handlers::extractors::AppJson;
+use crate::handlers::http_cache::{etag, modified_millis};
+use crate::handlers::listing_cache::ListingCache;
+use crate::models::{FileLinesResponse, SaveContentRequest, SaveContentResponse};
+
+// Bytes inspected when deciding whether a file is text
+const SNIFF_LEN: usize = 8192;
+
+const DEFAULT_TAIL_LINES: usize = 10;
+const MAX_TAIL_LINES: usize = 10_000;
+const TAIL_CHUNK: u64 = 8192;
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const DEFAULT_LINE_SPAN: usize = 200;
+const MAX_LINE_SPAN: usize = 5000;
+
+#[derive(Deserialize)]
+pub struct ContentQuery {
+    pub path: String,
+}
+
+#[derive(Deserialize)]
+pub struct LinesQuery {
+    pub path: String,
+    /// 1-indexed, inclusive
+    pub start: Option<usize>,
+    /// 1-indexed, inclusive
+    pub end: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct TailQuery {
+    pub path: String,
+    pub lines: Option<usize>,
+    /// Keep the connection open and stream appended lines as server-sent events
+    #[serde(default)]
+    pub follow: bool,
+}
+
+// Handler for GET /api/v1/file/content
+// Returns the file decoded to UTF-8, the source encoding is reported in the
+// X-Detected-Encoding header and the ETag can be passed back when saving.
+pub async fn read_content(
+    State(config): State<Arc<Config>>,
+    Query(params): Query<ContentQuery>,
+) -> Result<Response, AppError> {
+    let path = params.path.trim_start_matches('/');
+    let root_dir = PathBuf::from(&config.root_dir);
+    let file_path = syncfusion_fm_backend::validate_path(&root_dir, path)?;
+
+    if !file_path.is_file() {
+        return Err(AppError::NotFound(format!(
+            "File not found: {}",
+            display_path(path)
+        )));
+    }
+
+    let metadata = fs::metadata(&file_path).map_err(|e| {
+        error!("Failed to read metadata of {:?}: {}", file_path, e);
+        AppError::InternalError(format!("Failed to read metadata: {}", e))
+    })?;
+
+    if metadata.len() > config.max_text_file_size {
+        return Err(AppError::Detailed {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            code: "file_too_large",
+            message: format!("File exceeds the {} byte limit", config.max_text_file_size),
+            detail: None,
+        });
+    }
+
+    let bytes = tokio::fs::read(&file_path).await.map_err(|e| {
+        error!("Failed to read {:?}: {}", file_path, e);
+        AppError::InternalError(format!("Failed to read file: {}", e))
+    })?;
+
+    let encoding = detect_encoding(&bytes).ok_or_else(|| AppError::Detailed {
+        status: StatusCode::BAD_REQUEST,
+        code: "binary_file",
+        message: "File is not a text file".to_string(),
+        detail: None,
+    })?;
+
+    // decode() still honors a BOM, which then wins over the detection
+    let (text, encoding, _) = encoding.decode(&bytes);
+
+    info!("Serving {:?} decoded from {}", file_path, encoding.name());
+
+    Ok((
+        StatusCode::OK,
+        [
+            (
+                header::CONTENT_TYPE,
+                "text/plain; charset=utf-8".to_string(),
+            ),
+            (header::ETAG, etag(&metadata)),
+            (
+                header::HeaderName::from_static("x-detected-encoding"),
+                encoding.name().to_string(),
+            ),
+        ],
+        text.into_owned(),
+    )
+        .into_response())
+}
+
+// Handler for GET /api/v1/file/lines
+// Returns lines start..=end, clamped to the file and to MAX_LINE_SPAN lines.
+// The file is only read up to the last requested line, and never past
+// max_text_file_size bytes.
+pub async fn get_lines(
+    State(config): State<Arc<Config>>,
+    Query(params): Query<LinesQuery>,
+) -> Result<Json<FileLinesResponse>, AppError> {
+    let path = params.path.trim_start_matches('/');
+    let root_dir = PathBuf::from(&config.root_dir);
+    let file_path = syncfusion_fm_backend::validate_path(&root_dir, path)?;
+
+    if !file_path.is_file() {
+        return Err(AppError::NotFound(format!(
+            "File not found: {}",
+            display_path(path)
+        )));
+    }
+
+    let start = params.start.unwrap_or(1).max(1);
+    let end = params
+        .end
+        .unwrap_or(start.saturating_add(DEFAULT_LINE_SPAN - 1))
+        .clamp(start, start.saturating_add(MAX_LINE_SPAN - 1));
+
+    let max_size = config.max_text_file_size;
+    tokio::task::spawn_blocking(move || read_lines(&file_path, start, end, max_size))
+        .await
+        .map_err(|e| {
+            error!("Line read task failed: {}", e);
+            AppError::InternalError(format!("Failed to read file: {}", e))
+        })?
+        .map(Json)
+}
+
+fn read_lines(
+    path: &Path,
+    start: usize,
+    end: usize,
+    max_size: u64,
+) -> Result<FileLinesResponse, AppError> {
+    let read_error = |e: std::io::Error| {
+        error!("Failed to read {:?}: {}", path, e);
+        AppError::InternalError(format!("Failed to read file: {}", e))
+    };
+
+    let mut file = fs::File::open(path).map_err(read_error)?;
+    let mut sample = Vec::with_capacity(SNIFF_LEN);
+    (&mut file)
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut sample)
+        .map_err(read_error)?;
+
+    let encoding = detect_encoding(&sample).ok_or_else(|| AppError::Detailed {
+        status: StatusCode::BAD_REQUEST,
+        code: "binary_file",
+        message: "File is not a text file".to_string(),
+        detail: None,
+    })?;
+    file.seek(SeekFrom::Start(0)).map_err(read_error)?;
+
+    let mut lines = Vec::new();
+    let mut has_more = false;
+
+    if encoding.is_ascii_compatible() {
+        // Newlines can be found on the raw bytes, only the wanted lines get
+        // decoded. Reading stops at max_size, like UTF-16 files are refused
+        // past it, so a huge file without newlines isn't held in memory.
+        let capped = file.metadata().map_err(read_error)?.len() > max_size;
+        let mut reader = std::io::BufReader::new(file.take(max_size));
+        let mut line = Vec::new();
+        let mut number = 0;
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line).map_err(read_error)? == 0 {
+                break;
+            }
+            if capped && !line.ends_with(b"\n") && reader.get_ref().limit() == 0 {
+                // Cut off by the limit, the rest of the line is unread
+                has_more = true;
+                break;
+            }
+
+            number += 1;
+            if number > end {
+                has_more = true;
+                break;
+            }
+            if number >= start {
+                let line = line.strip_suffix(b"\n").unwrap_or(&line);
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                let (text, _) = encoding.decode_without_bom_handling(line);
+                let text = if number == 1 {
+                    text.trim_start_matches('\u{feff}').to_string()
+                } else {
+                    text.into_owned()
+                };
+                lines.push(text);
+            }
+        }
+
+        if lines.is_empty() && has_more && number < start {
+            return Err(AppError::Detailed {
+                status: StatusCode::PAYLOAD_TOO_LARGE,
+                code: "file_too_large",
+                message: format!("Lines past the first {} bytes can't be read", max_size),
+                detail: None,
+            });
+        }
+    } else {
+        // UTF-16 has to be decoded as a whole
+        if file.metadata().map_err(read_error)?.len() > max_size {
+            return Err(AppError::Detailed {
+                status: StatusCode::PAYLOAD_TOO_LARGE,
+                code: "file_too_large",
+                message: format!("File exceeds the {} byte limit", max_size),
+                detail: None,
+            });
+        }
+
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes).map_err(read_error)?;
+        let (text, _, _) = encoding.decode(&bytes);
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            if number > end {
+                has_more = true;
+                break;
+            }
+            if number >= start {
+                lines.push(line.to_string());
+            }
+        }
+    }
+
+    Ok(FileLinesResponse {
+        start,
+        end: start - 1 + lines.len(),
+        lines,
+        has_more,
+    })
+}
+
+// Guesses the encoding of `bytes`, None when they look binary
+fn detect_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return Some(encoding);
+    }
+
+    let sample = &bytes[..bytes.len().min(SNIFF_LEN)];
+    if sample.contains(&0) {
+        // BOM-less UTF-16 shows up as NULs in every other byte of ASCII text,
+        // anything else with NULs is treated as binary
+        return detect_utf16(sample);
+    }
+
+    // A sequence cut off at the end of a sample still counts as UTF-8
+    if std::str::from_utf8(bytes).map_or_else(|e| e.error_len().is_none(), |_| true) {
+        return Some(UTF_8);
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    Some(detector.guess(None, true))
+}
+
+fn detect_utf16(sample: &[u8]) -> Option<&'static Encoding> {
+    let pairs = sample.len() / 2;
+    if pairs == 0 {
+        return None;
+    }
+
+    let even_zeros = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_zeros = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|b| **b == 0)
+        .count();
+
+    // Mostly-ASCII text has the high byte zero for nearly every code unit
+    let threshold = pairs * 3 / 10;
+    if odd_zeros > threshold && even_zeros == 0 {
+        Some(UTF_16LE)
+    } else if even_zeros > threshold && odd_zeros == 0 {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+// Handler for POST /api/v1/file/content
+// Replaces the file atomically, readers see either the old or the new content.
+pub async fn save_content(
+    State(config): State<Arc<Config>>,
+    State(listing_cache): State<Arc<ListingCache>>,
+    AppJson(params): AppJson<SaveContentRequest>,
+) -> Result<Json<SaveContentResponse>, AppError> {
+    let path = params.path.trim_start_matches('/');
+    if path.is_empty() {
+        return Err(AppError::Validation {
+            message: "Path should not be empty".to_string(),
+            field: Some("path".to_string()),
+        });
+    }
+
+    if params.content.len() as u64 > config.max_text_file_size {
+        return Err(AppError::Detailed {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            code: "file_too_large",
+            message: format!(
+                "Content exceeds the {} byte limit",
+                config.max_text_file_size
+            ),
+            detail: None,
+        });
+    }
+
+    let root_dir = PathBuf::from(&config.root_dir);
+    let file_path = syncfusion_fm_backend::validate_path(&root_dir, path)?;
+
+    if file_path.is_dir() {
+        return Err(AppError::BadRequest("Path is a directory".to_string()));
+    }
+
+    let parent = file_path
+        .parent()
+        .filter(|parent| parent.is_dir())
+        .ok_or_else(|| AppError::NotFound(format!("Path not found: {}", display_path(path))))?
+        .to_path_buf();
+
+    let target = file_path.clone();
+    let response = tokio::task::spawn_blocking(move || {
+        write_content(
+            &target,
+            &parent,
+            &params.content,
+            params.if_match.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| {
+        error!("Save task failed: {}", e);
+        AppError::InternalError(format!("Failed to save file: {}", e))
+    })??;
+
+    if let Some(parent) = file_path.parent() {
+        listing_cache.invalidate(parent);
+    }
+
+    info!("Saved {} bytes to {:?}", response.size, file_path);
+
+    Ok(Json(response))
+}
+
+fn write_content(
+    target: &Path,
+    parent: &Path,
+    content: &str,
+    if_match: Option<&str>,
+) -> Result<SaveContentResponse, AppError> {
+    if let Some(expected) = if_match {
+        let current = fs::metadata(target).ok().map(|metadata| etag(&metadata));
+        if current.as_deref() != Some(expected) {
+            return Err(AppError::Detailed {
+                status: StatusCode::CONFLICT,
+                code: "etag_mismatch",
+                message: "File was modified since it was loaded".to_string(),
+                detail: current,
+            });
+        }
+    }
+
+    // The temp file lives in the target directory so the rename can't cross
+    // file systems
+    let mut temp = NamedTempFile::new_in(parent).map_err(|e| {
+        error!("Failed to create temp file in {:?}: {}", parent, e);
+        AppError::InternalError(format!("Failed to save file: {}", e))
+    })?;
+
+    // Temp files are private, keep the permissions of the file being replaced
+    let permissions = match fs::metadata(target) {
+        Ok(metadata) => Some(metadata.permissions()),
+        Err(_) => default_permissions(),
+    };
+    if let Some(permissions) = permissions {
+        temp.as_file().set_permissions(permissions).map_err(|e| {
+            error!("Failed to set permissions of temp file: {}", e);
+            AppError::InternalError(format!("Failed to save file: {}", e))
+        })?;
+    }
+
+    temp.write_all(content.as_bytes())
+        .and_then(|_| temp.as_file().sync_all())
+        .map_err(|e| {
+            error!("Failed to write temp file: {}", e);
+            AppError::InternalError(format!("Failed to save file: {}", e))
+        })?;
+
+    temp.persist(target).map_err(|e| {
+        error!("Failed to replace {:?}: {}", target, e);
+        AppError::InternalError(format!("Failed to save file: {}", e))
+    })?;
+
+    let metadata = fs::metadata(target).map_err(|e| {
+        error!("Failed to read metadata of {:?}: {}", target, e);
+        AppError::InternalError(format!("Failed to read metadata: {}", e))
+    })?;
+
+    Ok(SaveContentResponse {
+        size: metadata.len(),
+        modified_time: modified_millis(&metadata),
+        etag: etag(&metadata),
+    })
+}
+
+#[cfg(unix)]
+fn default_permissions() -> Option<fs::Permissions> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(fs::Permissions::from_mode(0o644))
+}
+
+#[cfg(not(unix))]
+fn default_permissions() -> Option<fs::Permissions> {
+    None
+}
+
+// Handler for GET /api/v1/file/tail
+// Returns the last `lines` lines as plain text, or with `follow=true` sends
+// them as server-sent events followed by every line appended afterwards.
+pub async fn tail(
+    State(config): State<Arc<Config>>,
+    Query(params): Query<TailQuery>,
+) -> Result<Response, AppError> {
+    let path = params.path.trim_start_matches('/');
+    let root_dir = PathBuf::from(&config.root_dir);
+    let file_path = syncfusion_fm_backend::validate_path(&root_dir, path)?;
+
+    if !file_path.is_file() {
+        return Err(AppError::NotFound(format!(
+            "File not found: {}",
+            display_path(path)
+        )));
+    }
+
+    let lines = params.lines.unwrap_or(DEFAULT_TAIL_LINES);
+    if lines == 0 || lines > MAX_TAIL_LINES {
+        return Err(AppError::BadRequest(format!(
+            "lines must be between 1 and {}",
+            MAX_TAIL_LINES
+        )));
+    }
+
+    let source = file_path.clone();
+    let max_bytes = config.max_text_file_size;
+    let (text, offset) = tokio::task::spawn_blocking(move || read_tail(&source, lines, max_bytes))
+        .await
+        .map_err(|e| {
+            error!("Tail task failed: {}", e);
+            AppError::InternalError(format!("Failed to read file: {}", e))
+        })?
+        .map_err(|e| {
+            error!("Failed to tail {:?}: {}", file_path, e);
+            AppError::InternalError(format!("Failed to read file: {}", e))
+        })?;
+
+    if !params.follow {
+        return Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            text,
+        )
+            .into_response());
+    }
+
+    info!("Following {:?} from offset {}", file_path, offset);
+
+    let (tx, rx) = mpsc::channel::<Result<Event, Infallible>>(64);
+    tokio::spawn(follow_file(file_path, text, offset, tx));
+
+    Ok(Sse::new(ReceiverStream::new(rx))
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+// Reads backwards from the end until `lines` complete lines are found, so the
+// cost doesn't depend on the file size. Gives up after `max_bytes`, dropping
+// the partial line it stopped in, so a file with few newlines isn't read in
+// full. Also returns the offset the text ends at.
+fn read_tail(path: &Path, lines: usize, max_bytes: u64) -> std::io::Result<(String, u64)> {
+    let mut file = fs::File::open(path)?;
+    let len = file.metadata()?.len();
+
+    // Chunks from the end backwards, joined once enough lines are found
+    let mut chunks: Vec<Vec<u8>> = Vec::new();
+    let mut newlines = 0;
+    let mut pos = len;
+    while pos > 0 && len - pos < max_bytes {
+        let chunk = TAIL_CHUNK.min(pos).min(max_bytes - (len - pos));
+        pos -= chunk;
+
+        let mut block = vec![0; chunk as usize];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut block)?;
+
+        // A trailing newline ends the last line rather than starting a new one
+        let counted = if chunks.is_empty() {
+            block.strip_suffix(b"\n").unwrap_or(&block)
+        } else {
+            &block
+        };
+        newlines += counted.iter().filter(|b| **b == b'\n').count();
+        chunks.push(block);
+
+        if newlines >= lines {
+            break;
+        }
+    }
+
+    chunks.reverse();
+    let buf = chunks.concat();
+    let text = String::from_utf8_lossy(&buf);
+    let mut all: Vec<&str> = text.lines().collect();
+    if pos > 0 && newlines < lines && all.len() > 1 {
+        all.remove(0);
+    }
+    let start = all.len().saturating_sub(lines);
+
+    Ok((all[start..].join("\n"), len))
+}
+
+// Polls the file for appended data until the client disconnects. Only whole
+// lines are sent, a truncated file is followed again from its start.
+async fn follow_file(
+    path: PathBuf,
+    initial: String,
+    mut offset: u64,
+    tx: mpsc::Sender<Result<Event, Infallible>>,
+) {
+    for line in initial.lines() {
+        if tx.send(Ok(Event::default().data(line))).await.is_err() {
+            return;
+        }
+    }
+
+    let mut pending: Vec<u8> = Vec::new();
+    loop {
+        tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+        if tx.is_closed() {
+            break;
+        }
+
+        let len = match tokio::fs::metadata(&path).await {
+            Ok(metadata) => metadata.len(),
+            Err(e) => {
+                debug!("Stopped following {:?}: {}", path, e);
+                break;
+            }
+        };
+
+        if len < offset {
+            debug!("{:?} was truncated, following from the start", path);
+            offset = 0;
+            pending.clear();
+        }
+        if len == offset {
+            continue;
+        }
+
+        let source = path.clone();
+        let start = offset;
+        let appended = tokio::task::spawn_blocking(move || read_range(&source, start, len)).await;
+        let appended = match appended {
+            Ok(Ok(appended)) => appended,
+            Ok(Err(e)) => {
+                error!("Failed to read appended data of {:?}: {}", path, e);
+                break;
+            }
+            Err(e) => {
+                error!("Follow task failed: {}", e);
+                break;
+            }
+        };
+        offset = len;
+        pending.extend_from_slice(&appended);
+
+        while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line[..end]);
+            let line = line.strip_suffix('\r').unwrap_or(&line);
+            if tx.send(Ok(Event::default().data(line))).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn read_range(path: &Path, start: u64, end: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut buf = Vec::new();
+    file.take(end - start).read_to_end(&mut buf)?;
+    Ok(buf)
+}
@@ -1,33 +1,67 @@
 use axum::extract::State;
 use md5;
-use mime_guess::from_path;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::UNIX_EPOCH;
 use tokio::process::Command;
-use tracing::{debug, error, info};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::{debug, error, info, warn};
 
 use crate::config::Config;
+use crate::handlers::media::probe_media;
+use crate::handlers::{audio, image_resizer};
+use crate::models::file_info::{MimeSettings, guess_mime_with_fallback};
+
+const THUMBNAIL_WIDTH: u32 = 320;
+const PREVIEW_SNIPPETS: u32 = 5;
+const PREVIEW_FPS: u32 = 10;
+// How close to its end a video thumbnail frame may be taken
+const SEEK_END_MARGIN_SECS: f64 = 0.5;
+
+// Bounds how many thumbnails are generated at once, so opening a big folder
+// doesn't start an ffmpeg process per file. Sized from the config on first use.
+static GENERATION_PERMITS: OnceLock<Semaphore> = OnceLock::new();
 
 #[derive(Debug)]
 pub enum ThumbnailError {
     InvalidInput,
+    /// The file can't have a thumbnail, e.g. audio without embedded cover art
+    NotFound,
     InternalError(String),
 }
 
 pub async fn get_thumbnail(
     State(config): State<Arc<Config>>,
     path: &PathBuf,
+) -> Result<PathBuf, ThumbnailError> {
+    generate_thumbnail(config, path, false, None).await
+}
+
+/// Replaces the cached thumbnail of `path` with a freshly generated one. For
+/// videos `seek` picks the frame in seconds instead of the configured
+/// percentage, the result is still cached where [`get_thumbnail`] finds it.
+pub async fn regenerate_thumbnail(
+    config: Arc<Config>,
+    path: &PathBuf,
+    seek: Option<f64>,
+) -> Result<PathBuf, ThumbnailError> {
+    generate_thumbnail(config, path, true, seek).await
+}
+
+async fn generate_thumbnail(
+    config: Arc<Config>,
+    path: &PathBuf,
+    regenerate: bool,
+    seek: Option<f64>,
 ) -> Result<PathBuf, ThumbnailError> {
     if !path.exists() || path.is_dir() {
         return Err(ThumbnailError::InvalidInput);
     }
 
-    let mime_type = from_path(path);
-    if !mime_type
-        .first_or_octet_stream()
-        .essence_str()
-        .starts_with("video/")
-    {
+    let mime_type = guess_mime_with_fallback(path, &config.mime);
+    let is_image = mime_type.starts_with("image/");
+    let is_audio = mime_type.starts_with("audio/");
+    if !is_image && !is_audio && !mime_type.starts_with("video/") {
         return Err(ThumbnailError::InvalidInput);
     }
 
@@ -46,29 +80,114 @@ pub async fn get_thumbnail(
             })?;
     }
 
-    let thumbnail_path = thumbnail_dir.join("thumbnail.jpg");
+    // Keyed by the source's modification time, so overwriting a file gives it
+    // a new thumbnail. Video thumbnails are also keyed by where their frame is
+    // taken, so changing FILE_PI_THUMB_SEEK_PERCENT regenerates them.
+    let modified = modified_millis(path);
+    let thumbnail_path = if is_image || is_audio {
+        thumbnail_dir.join(format!("thumbnail_{}.jpg", modified))
+    } else {
+        thumbnail_dir.join(format!(
+            "thumbnail_{}pct_{}.jpg",
+            config.thumb_seek_percent, modified
+        ))
+    };
     debug!("Thumbnail path is {:?}", thumbnail_path);
 
-    if thumbnail_path.exists() {
+    if thumbnail_path.exists() && !regenerate {
         debug!("thumbnail path already exist");
         return Ok(thumbnail_path);
     }
 
+    let _permit = acquire_permit(&config).await?;
+
+    if regenerate {
+        match tokio::fs::remove_file(&thumbnail_path).await {
+            Ok(()) => info!("Removed cached thumbnail {:?}", thumbnail_path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                error!("Failed to remove cached thumbnail: {}", e);
+                return Err(ThumbnailError::InternalError(format!(
+                    "Failed to remove cached thumbnail: {}",
+                    e
+                )));
+            }
+        }
+    } else if thumbnail_path.exists() {
+        // Another request may have generated it while this one waited
+        return Ok(thumbnail_path);
+    }
+
     debug!("Generating thumbnail for {:?}", path);
 
-    let output = Command::new("ffmpeg")
+    if is_image {
+        let source = path.clone();
+        let target = thumbnail_path.clone();
+        tokio::task::spawn_blocking(move || {
+            image_resizer::generate_thumbnail(&source, &target, THUMBNAIL_WIDTH)
+        })
+        .await
+        .map_err(|e| {
+            error!("Image thumbnail task failed: {}", e);
+            ThumbnailError::InternalError(format!("Failed to generate thumbnail: {}", e))
+        })??;
+
+        info!("Thumbnail generated successfully");
+        remove_stale_thumbnails(thumbnail_dir, &thumbnail_path).await;
+        return Ok(thumbnail_path);
+    }
+
+    if is_audio {
+        let source = path.clone();
+        let target = thumbnail_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let cover = audio::cover_art(&source).ok_or(ThumbnailError::NotFound)?;
+            image_resizer::generate_thumbnail_from_bytes(&cover, &target, THUMBNAIL_WIDTH)
+        })
+        .await
+        .map_err(|e| {
+            error!("Cover art thumbnail task failed: {}", e);
+            ThumbnailError::InternalError(format!("Failed to generate thumbnail: {}", e))
+        })??;
+
+        info!("Thumbnail generated from embedded cover art");
+        remove_stale_thumbnails(thumbnail_dir, &thumbnail_path).await;
+        return Ok(thumbnail_path);
+    }
+
+    // Without a known duration the first frame is better than seeking past
+    // the end of a short clip
+    let duration = match probe_media(&config, path).await {
+        Ok(info) => info.duration,
+        Err(e) => {
+            warn!("Failed to probe {:?}, using its first frame: {:?}", path, e);
+            None
+        }
+    };
+    let seek = seek_position(duration, config.thumb_seek_percent, seek);
+
+    // Write next to the final path and rename, so concurrent requests never
+    // serve a half written file and a failed run leaves nothing behind
+    let partial = thumbnail_path.with_extension("partial");
+
+    // Killed when the request is dropped, e.g. on timeout, so a hung ffmpeg
+    // doesn't keep running once its permit is released
+    let output = Command::new(&config.ffmpeg_path)
         .args([
+            "-ss",
+            &format!("{:.3}", seek),
             "-i",
             path.to_str().unwrap(),
-            "-ss",
-            "00:00:05",
             "-vframes",
             "1", // Extract 1 frame
             "-vf",
-            "scale=320:-1", // Scale to width 320, keep aspect ratio
-            thumbnail_path.to_str().unwrap(),
+            &format!("scale={}:-1", THUMBNAIL_WIDTH), // Scale to width, keep aspect ratio
+            "-f",
+            "mjpeg",
             "-y",
         ])
+        .arg(&partial)
+        .kill_on_drop(true)
         .output()
         .await
         .map_err(|e| {
@@ -78,17 +197,266 @@ pub async fn get_thumbnail(
     if !output.status.success() {
         let error_msg = String::from_utf8_lossy(&output.stderr);
         error!("FFmpeg error: {}", error_msg);
+        let _ = tokio::fs::remove_file(&partial).await;
         return Err(ThumbnailError::InternalError(
             "Failed to generate thumbnail with FFmpeg".to_string(),
         ));
     }
 
+    tokio::fs::rename(&partial, &thumbnail_path)
+        .await
+        .map_err(|e| {
+            error!("Failed to store thumbnail: {}", e);
+            ThumbnailError::InternalError(format!("Failed to store thumbnail: {}", e))
+        })?;
+
     info!("Thumbnail generated successfully");
+    remove_stale_thumbnails(thumbnail_dir, &thumbnail_path).await;
 
     return Ok(thumbnail_path);
 }
 
-fn get_md5_hash(input: &str) -> String {
+// Removes the thumbnails in `dir` other than `current`, made from an earlier
+// version of the source, including the unversioned `thumbnail.jpg` of older
+// releases. A file that can't be removed is only logged.
+async fn remove_stale_thumbnails(dir: &Path, current: &Path) {
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if path == current || !name.starts_with("thumbnail") || !name.ends_with(".jpg") {
+            continue;
+        }
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => debug!("Removed stale thumbnail {:?}", path),
+            Err(e) => warn!("Failed to remove stale thumbnail {:?}: {}", path, e),
+        }
+    }
+}
+
+// Seconds into a clip of `duration` where its thumbnail frame is taken,
+// `seek` when given or else `percent` of the duration. Kept clear of the last
+// frames so very short clips still have a frame to decode.
+fn seek_position(duration: Option<f64>, percent: u32, seek: Option<f64>) -> f64 {
+    match duration.filter(|duration| *duration > 0.0) {
+        Some(duration) => {
+            let latest = (duration - SEEK_END_MARGIN_SECS).max(0.0);
+            seek.unwrap_or(duration * f64::from(percent) / 100.0)
+                .min(latest)
+        }
+        None => seek.unwrap_or(0.0),
+    }
+}
+
+/// Whether a thumbnail can be generated for `path`, judged by its extension
+/// or, without a known one, its first bytes
+pub fn is_thumbnailable(path: &Path, mime: &MimeSettings) -> bool {
+    let mime_type = guess_mime_with_fallback(path, mime);
+    mime_type.starts_with("image/")
+        || mime_type.starts_with("video/")
+        || mime_type.starts_with("audio/")
+}
+
+/// SVG icon standing in for a thumbnail that can't be generated, labelled
+/// with the extension of `path` and coloured by the kind of file
+pub fn placeholder_svg(path: &Path, mime: &MimeSettings) -> String {
+    let color = match guess_mime_with_fallback(path, mime).split('/').next() {
+        Some("image") => "#43a047",
+        Some("video") => "#e53935",
+        Some("audio") => "#8e24aa",
+        Some("text") => "#1e88e5",
+        _ => "#757575",
+    };
+    let label: String = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .take(4)
+        .collect::<String>()
+        .to_uppercase();
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="256" height="256" viewBox="0 0 256 256"><path d="M64 24h88l56 56v152H64z" fill="#eceff1"/><path d="M152 24v56h56z" fill="#cfd8dc"/><rect x="48" y="140" width="144" height="48" rx="6" fill="{}"/><text x="120" y="173" font-family="sans-serif" font-size="26" font-weight="bold" fill="#fff" text-anchor="middle">{}</text></svg>"##,
+        color, label
+    )
+}
+
+/// Generates the thumbnail of `path` in the background so a later request
+/// finds it cached. Failures are only logged.
+pub fn queue_thumbnail(config: Arc<Config>, path: PathBuf) {
+    tokio::spawn(async move {
+        match get_thumbnail(State(config), &path).await {
+            Ok(_) => {}
+            Err(ThumbnailError::NotFound) => debug!("No thumbnail available for {:?}", path),
+            Err(e) => warn!(
+                "Background thumbnail generation failed for {:?}: {:?}",
+                path, e
+            ),
+        }
+    });
+}
+
+/// Returns the path of a short animated WebP preview of the video at `path`,
+/// made of one second snippets sampled evenly across its duration.
+pub async fn get_animated_preview(
+    config: Arc<Config>,
+    path: &Path,
+) -> Result<PathBuf, ThumbnailError> {
+    if !path.is_file() {
+        return Err(ThumbnailError::InvalidInput);
+    }
+
+    if !guess_mime_with_fallback(path, &config.mime).starts_with("video/") {
+        return Err(ThumbnailError::InvalidInput);
+    }
+
+    let thumbnail_dir = config.cache_dir.join(get_md5_hash(&path.to_string_lossy()));
+    let preview_path = thumbnail_dir.join(format!("preview_{}.webp", modified_millis(path)));
+
+    if preview_path.exists() {
+        debug!("Animated preview already cached at {:?}", preview_path);
+        return Ok(preview_path);
+    }
+
+    tokio::fs::create_dir_all(&thumbnail_dir)
+        .await
+        .map_err(|e| {
+            error!("Failed to create thumbnail directory: {}", e);
+            ThumbnailError::InternalError(format!("Failed to create thumbnail directory: {}", e))
+        })?;
+
+    let _permit = acquire_permit(&config).await?;
+
+    if preview_path.exists() {
+        return Ok(preview_path);
+    }
+
+    let duration = probe_media(&config, path)
+        .await
+        .map_err(|e| {
+            error!("Failed to probe {:?}: {:?}", path, e);
+            ThumbnailError::InternalError("Failed to read video duration".to_string())
+        })?
+        .duration
+        .filter(|duration| *duration > 0.0)
+        .ok_or(ThumbnailError::InvalidInput)?;
+
+    debug!("Generating animated preview for {:?}", path);
+
+    // Short clips get fewer snippets so they don't overlap
+    let snippets = PREVIEW_SNIPPETS.min(duration.floor() as u32).max(1);
+    let snippet_length = duration.min(1.0);
+    let spacing = duration / snippets as f64;
+
+    // Every snippet is its own input so ffmpeg seeks instead of decoding the
+    // whole file, the inputs are then scaled and concatenated
+    let mut command = Command::new(&config.ffmpeg_path);
+    command.args(["-v", "error"]);
+    let mut filter = String::new();
+    for i in 0..snippets {
+        let start = (i as f64 * spacing).min(duration - snippet_length);
+        command
+            .args(["-ss", &format!("{:.3}", start)])
+            .args(["-t", &format!("{:.3}", snippet_length)])
+            .arg("-i")
+            .arg(path);
+        filter.push_str(&format!(
+            "[{i}:v:0]fps={},scale={}:-2,setsar=1[v{i}];",
+            PREVIEW_FPS, THUMBNAIL_WIDTH
+        ));
+    }
+    for i in 0..snippets {
+        filter.push_str(&format!("[v{i}]"));
+    }
+    filter.push_str(&format!("concat=n={}:v=1:a=0[out]", snippets));
+
+    // Write next to the final path and rename, so concurrent requests never
+    // serve a half written file
+    let partial = preview_path.with_extension("partial");
+    let output = command
+        .args(["-filter_complex", &filter, "-map", "[out]", "-an"])
+        .args(["-loop", "0", "-f", "webp", "-y"])
+        .arg(&partial)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| {
+            error!("Failed to run FFmpeg: {}", e);
+            ThumbnailError::InternalError(format!("Failed to generate preview: {}", e))
+        })?;
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        error!("FFmpeg error: {}", error_msg);
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(ThumbnailError::InternalError(
+            "Failed to generate preview with FFmpeg".to_string(),
+        ));
+    }
+
+    tokio::fs::rename(&partial, &preview_path)
+        .await
+        .map_err(|e| {
+            error!("Failed to store preview: {}", e);
+            ThumbnailError::InternalError(format!("Failed to store preview: {}", e))
+        })?;
+
+    info!("Animated preview generated successfully");
+
+    Ok(preview_path)
+}
+
+async fn acquire_permit(config: &Config) -> Result<SemaphorePermit<'static>, ThumbnailError> {
+    GENERATION_PERMITS
+        .get_or_init(|| Semaphore::new(config.thumb_concurrency))
+        .acquire()
+        .await
+        .map_err(|e| {
+            error!("Thumbnail semaphore closed: {}", e);
+            ThumbnailError::InternalError("Thumbnail generation unavailable".to_string())
+        })
+}
+
+/// Modification time of `path` in millis, used to key cache entries so they
+/// are regenerated when the source changes. 0 when it can't be read.
+pub fn modified_millis(path: &Path) -> u128 {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis())
+        .unwrap_or_default()
+}
+
+pub fn get_md5_hash(input: &str) -> String {
     let hash = md5::compute(input.as_bytes());
     format!("{:x}", hash)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeks_to_start_without_duration() {
+        assert_eq!(seek_position(None, 25, None), 0.0);
+        assert_eq!(seek_position(Some(0.0), 25, None), 0.0);
+        assert_eq!(seek_position(None, 25, Some(3.0)), 3.0);
+    }
+
+    #[test]
+    fn seeks_to_start_of_clip_shorter_than_end_margin() {
+        assert_eq!(seek_position(Some(0.3), 25, None), 0.0);
+        assert_eq!(seek_position(Some(0.3), 100, Some(10.0)), 0.0);
+    }
+
+    #[test]
+    fn keeps_full_percentage_clear_of_the_end() {
+        assert_eq!(seek_position(Some(10.0), 100, None), 9.5);
+        assert_eq!(seek_position(Some(10.0), 25, None), 2.5);
+        assert_eq!(seek_position(Some(10.0), 25, Some(20.0)), 9.5);
+    }
+}
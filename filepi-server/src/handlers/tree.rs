@@ -0,0 +1,140 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::config::Config;
+use crate::handlers::app_error::{AppError, display_path};
+use crate::models::TreeNode;
+
+const DEFAULT_DEPTH: usize = 1;
+const MAX_DEPTH: usize = 8;
+
+#[derive(Deserialize)]
+pub struct TreeQuery {
+    pub path: Option<String>,
+    pub depth: Option<usize>,
+}
+
+// Handler for GET /api/v1/tree
+// Returns the directories below `path` nested up to `depth` levels. Every node
+// carries the number of files and subdirectories directly inside it, so nodes
+// at the depth limit still show whether they can be expanded.
+pub async fn get_tree(
+    State(config): State<Arc<Config>>,
+    Query(params): Query<TreeQuery>,
+) -> Result<Json<TreeNode>, AppError> {
+    let path = params
+        .path
+        .as_deref()
+        .unwrap_or_default()
+        .trim_matches('/')
+        .to_string();
+
+    let depth = params.depth.unwrap_or(DEFAULT_DEPTH);
+    if depth > MAX_DEPTH {
+        return Err(AppError::BadRequest(format!(
+            "depth must be at most {}",
+            MAX_DEPTH
+        )));
+    }
+
+    let root_dir = PathBuf::from(&config.root_dir);
+    let full_path = syncfusion_fm_backend::validate_path(&root_dir, &path)?;
+    if !full_path.is_dir() {
+        return Err(AppError::BadRequest(format!(
+            "Not a directory: {}",
+            display_path(&path)
+        )));
+    }
+
+    info!("Building tree of {:?} with depth {}", full_path, depth);
+
+    let system_dirs = config.system_dirs(&root_dir);
+    tokio::task::spawn_blocking(move || build_node(&full_path, path, depth, &system_dirs))
+        .await
+        .map_err(|e| {
+            error!("Tree task failed: {}", e);
+            AppError::InternalError(format!("Failed to build tree: {}", e))
+        })?
+        .map(Json)
+}
+
+// Symlinked directories are counted but not descended into, so link cycles
+// can't make the walk loop
+fn build_node(
+    dir: &Path,
+    rel_path: String,
+    depth: usize,
+    system_dirs: &[PathBuf],
+) -> Result<TreeNode, AppError> {
+    let entries = fs::read_dir(dir).map_err(|e| {
+        error!("Error reading directory {:?}: {}", dir, e);
+        AppError::InternalError(format!("Failed to read directory: {}", e))
+    })?;
+
+    let mut file_count = 0;
+    let mut subdirs = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Hidden directories are shown, the server managed ones never
+        if !syncfusion_fm_backend::is_listed(&entry.path(), true, system_dirs) {
+            continue;
+        }
+
+        let is_real_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+        if is_real_dir || entry.path().is_dir() {
+            subdirs.push((name, entry.path(), is_real_dir));
+        } else {
+            file_count += 1;
+        }
+    }
+    subdirs.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let dir_count = subdirs.len();
+    let mut children = Vec::new();
+    if depth > 0 {
+        for (name, path, is_real_dir) in subdirs {
+            let child_rel = if rel_path.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", rel_path, name)
+            };
+
+            if !is_real_dir {
+                children.push(TreeNode {
+                    name,
+                    path: child_rel,
+                    file_count: 0,
+                    dir_count: 0,
+                    children: Vec::new(),
+                });
+                continue;
+            }
+
+            match build_node(&path, child_rel, depth - 1, system_dirs) {
+                Ok(node) => children.push(node),
+                // An unreadable subdirectory shouldn't fail the whole tree
+                Err(_) => error!("Skipping unreadable directory {:?}", path),
+            }
+        }
+    }
+
+    let name = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    Ok(TreeNode {
+        name,
+        path: rel_path,
+        file_count,
+        dir_count,
+        children,
+    })
+}
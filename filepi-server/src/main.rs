@@ -2,29 +2,41 @@ mod config;
 mod handlers;
 mod middleware;
 mod models;
+mod routes;
+mod state;
 
 use axum::{
     Router,
-    http::StatusCode,
+    http::{HeaderName, StatusCode},
     middleware as axum_middleware,
     response::IntoResponse,
-    routing::{get, post},
-    extract::DefaultBodyLimit,
+    routing::get,
 };
 
 use axum::body::Body;
+use axum_server::tls_rustls::RustlsConfig;
+use bytes::Bytes;
 use std::convert::Infallible;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio_util::sync::CancellationToken;
 use tower::ServiceBuilder;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::ServeDir;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use config::Config;
-use handlers::files;
 use handlers::health;
 use middleware::logging::logging_middleware;
+use middleware::request_id::{REQUEST_ID_HEADER, request_id_middleware};
+use state::AppState;
+
+// How long in-flight requests may take to finish once shutdown starts
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(30);
+// How often the SPA index is checked for changes when FILE_PI_SPA_WATCH is set
+const SPA_WATCH_INTERVAL: Duration = Duration::from_secs(2);
 
 #[tokio::main]
 async fn main() {
@@ -51,6 +63,9 @@ async fn main() {
     tracing::info!("📁 Root directory: {}", config.root_dir);
     tracing::info!("🔧 Log level: {}", config.log_level);
 
+    warn_if_missing("ffmpeg", &config.ffmpeg_path);
+    warn_if_missing("ffprobe", &config.ffprobe_path);
+
     // Wrap config in Arc for sharing across threads
     let shared_config = Arc::new(config.clone());
 
@@ -58,88 +73,257 @@ async fn main() {
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
-        .allow_headers(Any);
-
-    // Build API routes
-    let api_routes = Router::new()
-        .route("/files", get(files::get_files))
-        .route("/videos", get(files::get_videos))
-        .route("/search", get(files::search))
-        .route("/file/{*wildcard}", get(files::serve_file))
-        .route("/stream/{*wildcard}", get(files::stream_file))
-        .route("/thumbnail/{*wildcard}", get(files::get_thumbnail))
-        .route("/createfolder", post(files::create_folder))
-        .route("/uploadfile", post(files::upload_file))
-        .route(
-            "/syncfusion/fileoperations",
-            post(handlers::syncfusion::file_operations),
-        )
-        .route("/syncfusion/download", post(handlers::syncfusion::download))
-        .route("/syncfusion/upload", post(handlers::syncfusion::upload))
-        .route("/syncfusion/getimage", get(handlers::syncfusion::get_image))
-        .with_state(shared_config.clone());
+        .allow_headers(Any)
+        .expose_headers([HeaderName::from_static(REQUEST_ID_HEADER)]);
+
+    // Cancelled by SIGTERM, Ctrl+C or the admin API to stop the server
+    let shutdown = CancellationToken::new();
+    tokio::spawn(watch_shutdown_signals(shutdown.clone()));
+
+    let api_routes = routes::api_routes(AppState::new(shared_config.clone(), shutdown.clone()));
 
-    // Check if webdeploy directory exists
-    let serve_static = std::path::Path::new("./webdeploy").exists();
+    // Check if the web root exists
+    let serve_static = config.web_root.exists();
+    let spa_index = Arc::new(SpaIndex::load(config.web_root.join(&config.spa_index)).await);
 
     if serve_static {
-        tracing::info!("✅ Serving Blazor WebAssembly files from ./webdeploy");
+        tracing::info!(
+            "✅ Serving frontend files from {}",
+            config.web_root.display()
+        );
+        if !spa_index.is_loaded() {
+            tracing::warn!(
+                "⚠️  SPA index {} not found, client-side routes will answer 404",
+                spa_index.path.display()
+            );
+        }
+        if config.spa_watch {
+            tokio::spawn(watch_spa_index(spa_index.clone(), shutdown.clone()));
+        }
     } else {
-        tracing::warn!("⚠️  webdeploy directory not found, Blazor UI will not be available");
+        tracing::warn!(
+            "⚠️  Web root {} not found, the UI will not be available",
+            config.web_root.display()
+        );
     }
 
+    let spa_fallback = tower::service_fn(move |_req| spa_handler(spa_index.clone()));
+
     // Build main app with all routes and middleware
     let app = if serve_static {
         // Serve static files and handle SPA routing
         Router::new()
             .route("/health", get(health::health_handler))
+            .route("/api/v1/version", get(health::version_handler))
             .nest("/api/v1", api_routes)
-            .fallback_service(
-                ServeDir::new("webdeploy").not_found_service(tower::service_fn(spa_handler)),
-            )
+            .fallback_service(ServeDir::new(&config.web_root).not_found_service(spa_fallback))
             .layer(
                 ServiceBuilder::new()
-                    .layer(axum_middleware::from_fn(logging_middleware))
+                    .layer(axum_middleware::from_fn(request_id_middleware))
+                    .layer(axum_middleware::from_fn_with_state(
+                        shared_config.clone(),
+                        logging_middleware,
+                    ))
                     .layer(cors),
             )
     } else {
         // No static files, just API
         Router::new()
             .route("/health", get(health::health_handler))
+            .route("/api/v1/version", get(health::version_handler))
             .nest("/api/v1", api_routes)
-            .layer(axum_middleware::from_fn(logging_middleware))
+            .layer(axum_middleware::from_fn_with_state(
+                shared_config.clone(),
+                logging_middleware,
+            ))
+            .layer(axum_middleware::from_fn(request_id_middleware))
             .layer(cors)
     };
 
     // Define the server address
-    let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
+    let addr = SocketAddr::new(config.bind_addr, config.port);
+
+    let scheme = if config.tls_cert.is_some() {
+        "https"
+    } else {
+        "http"
+    };
 
-    tracing::info!("🚀 Server starting on http://{}", addr);
+    tracing::info!("🚀 Server starting on {}://{}", scheme, addr);
 
     if serve_static {
         tracing::info!(
-            "🌐 Access the web interface at: http://localhost:{}",
+            "🌐 Access the web interface at: {}://localhost:{}",
+            scheme,
             config.port
         );
     }
     tracing::info!(
-        "📡 API available at: http://localhost:{}/api/v1",
+        "📡 API available at: {}://localhost:{}/api/v1",
+        scheme,
         config.port
     );
 
     // Start the server
-    let listener = tokio::net::TcpListener::bind(addr)
-        .await
-        .expect("Failed to bind to address");
+    if let (Some(cert), Some(key)) = (&config.tls_cert, &config.tls_key) {
+        tracing::info!("🔒 TLS enabled with certificate {:?}", cert);
+
+        let tls_config = RustlsConfig::from_pem_file(cert, key)
+            .await
+            .expect("Failed to load TLS certificate or key");
+
+        let handle = axum_server::Handle::new();
+        let shutdown_handle = handle.clone();
+        let token = shutdown.clone();
+        tokio::spawn(async move {
+            token.cancelled().await;
+            shutdown_handle.graceful_shutdown(Some(SHUTDOWN_GRACE_PERIOD));
+        });
+
+        // axum-server negotiates HTTP/2 over ALPN
+        axum_server::bind_rustls(addr, tls_config)
+            .handle(handle)
+            .serve(app.into_make_service())
+            .await
+            .expect("Server error");
+    } else {
+        tracing::info!("🔓 TLS disabled, serving plain HTTP");
+
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .expect("Failed to bind to address");
+
+        let server =
+            axum::serve(listener, app).with_graceful_shutdown(shutdown.clone().cancelled_owned());
+
+        // Long lived connections such as followed log tails would otherwise
+        // keep the server from ever stopping
+        tokio::select! {
+            result = server => result.expect("Server error"),
+            _ = async {
+                shutdown.cancelled().await;
+                tokio::time::sleep(SHUTDOWN_GRACE_PERIOD).await;
+            } => tracing::warn!("⚠️  Connections still open after the grace period, stopping anyway"),
+        }
+    }
+
+    tracing::info!("👋 Server stopped");
+}
+
+// Cancels `shutdown` on Ctrl+C or, on Unix, SIGTERM
+async fn watch_shutdown_signals(shutdown: CancellationToken) {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
-    axum::serve(listener, app).await.expect("Server error");
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received Ctrl+C, shutting down"),
+        _ = terminate => tracing::info!("Received SIGTERM, shutting down"),
+        _ = shutdown.cancelled() => return,
+    }
+
+    shutdown.cancel();
 }
 
-// Handler for SPA fallback - serves index.html for client-side routing
-async fn spa_handler(
-    _req: axum::http::Request<Body>,
-) -> Result<axum::response::Response, Infallible> {
-    match tokio::fs::read_to_string("webdeploy/index.html").await {
+// The SPA index answers every client-side route, so it is read once and
+// served from memory. A missing index is retried on the next request.
+struct SpaIndex {
+    path: PathBuf,
+    cached: RwLock<Option<CachedIndex>>,
+}
+
+struct CachedIndex {
+    contents: Bytes,
+    modified: Option<SystemTime>,
+}
+
+impl SpaIndex {
+    async fn load(path: PathBuf) -> Self {
+        let index = SpaIndex {
+            path,
+            cached: RwLock::new(None),
+        };
+        let _ = index.reload().await;
+        index
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.cached.read().unwrap().is_some()
+    }
+
+    async fn contents(&self) -> std::io::Result<Bytes> {
+        if let Some(cached) = self.cached.read().unwrap().as_ref() {
+            return Ok(cached.contents.clone());
+        }
+        self.reload().await
+    }
+
+    async fn reload(&self) -> std::io::Result<Bytes> {
+        let contents = Bytes::from(tokio::fs::read(&self.path).await?);
+        let modified = modified_time(&self.path).await;
+        *self.cached.write().unwrap() = Some(CachedIndex {
+            contents: contents.clone(),
+            modified,
+        });
+        Ok(contents)
+    }
+
+    fn invalidate(&self) {
+        *self.cached.write().unwrap() = None;
+    }
+
+    fn cached_modified(&self) -> Option<SystemTime> {
+        self.cached
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|cached| cached.modified)
+    }
+}
+
+async fn modified_time(path: &std::path::Path) -> Option<SystemTime> {
+    tokio::fs::metadata(path).await.ok()?.modified().ok()
+}
+
+// Polls the SPA index and drops the cached copy once the file changes or
+// disappears, the next request then reads it again
+async fn watch_spa_index(index: Arc<SpaIndex>, shutdown: CancellationToken) {
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(SPA_WATCH_INTERVAL) => {}
+            _ = shutdown.cancelled() => return,
+        }
+
+        let modified = modified_time(&index.path).await;
+        if index.is_loaded() && modified != index.cached_modified() {
+            tracing::info!("🔄 SPA index {} changed, reloading", index.path.display());
+            index.invalidate();
+        }
+    }
+}
+
+// Handler for SPA fallback - serves the index for client-side routing
+async fn spa_handler(index: Arc<SpaIndex>) -> Result<axum::response::Response, Infallible> {
+    match index.contents().await {
         Ok(contents) => {
             let body = Body::from(contents);
             let response = axum::response::Response::builder()
@@ -150,9 +334,32 @@ async fn spa_handler(
             Ok(response)
         }
         Err(e) => {
-            tracing::error!("Failed to read index.html: {}", e);
-            let response = (StatusCode::NOT_FOUND, "404 - index.html not found").into_response();
+            tracing::error!("Failed to read SPA index {}: {}", index.path.display(), e);
+            let message = format!(
+                "404 - SPA index {} not found, check FILE_PI_WEBROOT and FILE_PI_SPA_INDEX",
+                index.path.display()
+            );
+            let response = (StatusCode::NOT_FOUND, message).into_response();
             Ok(response)
         }
     }
 }
+
+// Media features degrade to errors without these binaries, so make a
+// misconfigured path obvious at startup instead of on the first request
+fn warn_if_missing(name: &str, path: &str) {
+    let found = std::process::Command::new(path)
+        .arg("-version")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok();
+
+    if !found {
+        tracing::warn!(
+            "⚠️  {} not found at '{}', media features will not be available",
+            name,
+            path
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use axum::extract::{Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::config::Config;
+use crate::handlers::app_error::AppError;
+
+/// Only lets requests through that carry `Authorization: Bearer <token>` with
+/// the configured `FILE_PI_ADMIN_TOKEN`. Without a token the admin routes
+/// answer `404` as if they didn't exist.
+pub async fn require_admin_token(
+    State(config): State<Arc<Config>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(expected) = config.admin_token.as_deref() else {
+        return AppError::NotFound("Not found".to_string()).into_response();
+    };
+
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => {
+            warn!("Rejected admin request to {}", request.uri().path());
+            AppError::Detailed {
+                status: StatusCode::UNAUTHORIZED,
+                code: "unauthorized",
+                message: "Missing or invalid admin token".to_string(),
+                detail: None,
+            }
+            .into_response()
+        }
+    }
+}
+
+// Compares without short-circuiting so the token can't be guessed byte by
+// byte from response times
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -1,15 +1,44 @@
 use axum::body::Body;
+use axum::extract::State;
 use axum::{extract::Request, middleware::Next, response::Response};
 use http_body_util::BodyExt;
+use std::sync::Arc;
 use std::time::Instant;
-use tracing::{debug, info};
+use tracing::{debug, info, trace};
 
-pub async fn logging_middleware(request: Request, next: Next) -> Response {
+use crate::config::Config;
+use crate::middleware::request_id::RequestId;
+
+pub async fn logging_middleware(
+    State(config): State<Arc<Config>>,
+    request: Request,
+    next: Next,
+) -> Response {
     let start = Instant::now();
     let method = request.method().clone();
     let uri = request.uri().clone();
     let path = uri.path().to_string();
     let query = uri.query().unwrap_or("").to_string();
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .map(|id| id.0.clone())
+        .unwrap_or_default();
+
+    // Probes like /health are polled constantly, keep them out of the logs
+    // and skip buffering their bodies
+    if config.log_skip_paths.iter().any(|skip| skip == &path) {
+        let response = next.run(request).await;
+        trace!(
+            request_id = %request_id,
+            method = %method,
+            path = %path,
+            status = %response.status().as_u16(),
+            latency = ?start.elapsed(),
+            "HTTP request"
+        );
+        return response;
+    }
 
     // Extract and read the body
     let (parts, body) = request.into_parts();
@@ -25,22 +54,32 @@ pub async fn logging_middleware(request: Request, next: Next) -> Response {
         || content_type.starts_with("application/octet-stream")
         || content_type.starts_with("multipart/form-data");
 
-    let (body_str, request) = if is_binary {
+    let body_limit = config.log_body_limit;
+
+    let (body_str, request) = if body_limit == 0 {
+        // Body logging is disabled, don't buffer it either
+        (None, Request::from_parts(parts, body))
+    } else if is_binary {
         (
-            format!("<binary data: {}>", content_type),
+            Some(format!("<binary data: {}>", content_type)),
             Request::from_parts(parts, body),
         )
     } else {
         let bytes = body.collect().await.unwrap_or_default().to_bytes();
         let mut body_str = String::from_utf8_lossy(&bytes).to_string();
-        
-        if body_str.len() > 100 {
-            body_str.truncate(100);
+
+        if body_str.len() > body_limit {
+            // Cutting inside a multibyte character would panic
+            let mut end = body_limit;
+            while !body_str.is_char_boundary(end) {
+                end -= 1;
+            }
+            body_str.truncate(end);
             body_str.push_str("... (truncated)");
         }
 
         (
-            body_str,
+            Some(body_str),
             Request::from_parts(parts, Body::from(bytes)),
         )
     };
@@ -54,6 +93,7 @@ pub async fn logging_middleware(request: Request, next: Next) -> Response {
 
     // Log the request
     info!(
+        request_id = %request_id,
         method = %method,
         path = %path,
         query = %query,
@@ -62,10 +102,13 @@ pub async fn logging_middleware(request: Request, next: Next) -> Response {
         "HTTP request"
     );
 
-    debug!(
-        body = %body_str,
-        "Detailed HTTP request"
-    );
+    if let Some(body_str) = body_str {
+        debug!(
+            request_id = %request_id,
+            body = %body_str,
+            "Detailed HTTP request"
+        );
+    }
 
     response
 }
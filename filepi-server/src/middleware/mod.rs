@@ -1 +1,4 @@
+pub mod admin;
 pub mod logging;
+pub mod request_id;
+pub mod timeout;
@@ -0,0 +1,42 @@
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::config::Config;
+use crate::handlers::app_error::AppError;
+
+/// Fails requests that take longer than `FILE_PI_REQUEST_TIMEOUT_SECS` with a
+/// `504`. Only applied to routes that don't stream large bodies.
+pub async fn timeout_middleware(
+    State(config): State<Arc<Config>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if config.request_timeout_secs == 0 {
+        return next.run(request).await;
+    }
+
+    let path = request.uri().path().to_string();
+    let timeout = Duration::from_secs(config.request_timeout_secs);
+
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!("Request to {} timed out after {:?}", path, timeout);
+            AppError::Detailed {
+                status: StatusCode::GATEWAY_TIMEOUT,
+                code: "request_timeout",
+                message: format!(
+                    "Request timed out after {} seconds",
+                    config.request_timeout_secs
+                ),
+                detail: None,
+            }
+            .into_response()
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use serde::Serialize;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AudioInfo {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub genre: Option<String>,
+    pub track: Option<u32>,
+    pub track_total: Option<u32>,
+    pub disc: Option<u32>,
+    pub disc_total: Option<u32>,
+    pub duration: f64,         // seconds
+    pub bit_rate: Option<u32>, // kbps
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u8>,
+    pub has_cover: bool,
+}
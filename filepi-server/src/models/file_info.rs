@@ -1,9 +1,22 @@
+use chrono::{DateTime, SecondsFormat};
 use mime_guess::from_path;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use std::time::UNIX_EPOCH;
 
+/// How MIME types are guessed, part of the config
+#[derive(Clone, Debug, Default)]
+pub struct MimeSettings {
+    /// Check the first bytes of files for a known signature,
+    /// `FILE_PI_MIME_SNIFF`
+    pub sniff: bool,
+    /// MIME types replacing the guessed ones, keyed by lowercase extension,
+    /// `FILE_PI_MIME_OVERRIDES`
+    pub overrides: HashMap<String, String>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FileInfo {
     pub name: String,
@@ -16,15 +29,48 @@ pub struct FileInfo {
     pub owner: Option<String>,
     pub parent_dir: Option<String>,
     pub rel_path: Option<String>, // relative path w.r.t currrent dir
+    #[serde(default)]
+    pub is_symlink: bool,
+    #[serde(default)]
+    pub symlink_target: Option<String>, // as stored in the link, may be relative
+    #[serde(default)]
+    pub mode: Option<u32>, // unix permission bits, None elsewhere
+    #[serde(default)]
+    pub has_children: Option<bool>, // directories only, None for files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub child_count: Option<usize>, // directories only, when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>, // only filled in when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<i64>, // fuzzy search match score, higher is better
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_iso: Option<String>, // RFC 3339 in UTC, only filled in when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_iso: Option<String>, // RFC 3339 in UTC, only filled in when requested
 }
 
 impl FileInfo {
     pub fn from_path<P: AsRef<Path>, T: AsRef<Path>>(
         absolute_path: P,
         current_dir: T,
+        mime: &MimeSettings,
     ) -> std::io::Result<Self> {
         let path = absolute_path.as_ref();
-        let metadata = fs::metadata(path)?;
+
+        // Links are described by their target. Broken links have none, they
+        // are listed with a zero size and zero times.
+        let link_metadata = fs::symlink_metadata(path)?;
+        let is_symlink = link_metadata.file_type().is_symlink();
+        let symlink_target = if is_symlink {
+            fs::read_link(path)
+                .ok()
+                .map(|target| target.to_string_lossy().to_string())
+        } else {
+            None
+        };
+        let target_metadata = fs::metadata(path);
+        let broken = is_symlink && target_metadata.is_err();
+        let metadata = target_metadata.unwrap_or(link_metadata);
 
         // Basic info
         let name = path
@@ -32,7 +78,7 @@ impl FileInfo {
             .map(|n| n.to_string_lossy().to_string())
             .unwrap_or_default();
 
-        let full_name = String::from(path.to_str().unwrap());
+        let full_name = path.to_string_lossy().to_string();
 
         let size = match get_size(path) {
             Ok(size) => size,
@@ -44,23 +90,45 @@ impl FileInfo {
 
         let is_directory = metadata.is_dir();
 
+        // Only the first entry is read, unreadable directories stay None
+        let has_children = if is_directory {
+            fs::read_dir(path)
+                .ok()
+                .map(|mut entries| entries.next().is_some())
+        } else {
+            None
+        };
+
         // Timestamps
-        let created_time = metadata
-            .created()
-            .ok()
-            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
-            .map(|d| d.as_millis());
+        let created_time = if broken {
+            Some(0)
+        } else {
+            metadata
+                .created()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis())
+        };
 
-        let modified_time = metadata
-            .modified()
-            .ok()
-            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
-            .map(|d| d.as_millis());
+        let modified_time = if broken {
+            Some(0)
+        } else {
+            metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis())
+        };
 
-        let file_type = from_path(&path).first_or_octet_stream().to_string();
+        let file_type = if is_directory {
+            from_path(&path).first_or_octet_stream().to_string()
+        } else {
+            guess_mime(path, mime)
+        };
 
         // Owner info (Unix/Linux only)
         let owner = get_file_owner(path);
+        let mode = get_file_mode(&metadata);
 
         // Parent directory
         let parent_dir = path.parent().map(|p| p.to_string_lossy().to_string());
@@ -82,8 +150,71 @@ impl FileInfo {
             owner,
             parent_dir,
             rel_path,
+            is_symlink,
+            symlink_target,
+            mode,
+            has_children,
+            child_count: None,
+            tags: None,
+            score: None,
+            created_iso: None,
+            modified_iso: None,
         })
     }
+
+    /// Fills in `created_iso` and `modified_iso` from the millisecond
+    /// timestamps, left out when a timestamp is unknown
+    pub fn add_iso_dates(&mut self) {
+        self.created_iso = self.created_time.and_then(iso_date);
+        self.modified_iso = self.modified_time.and_then(iso_date);
+    }
+}
+
+fn iso_date(millis: u128) -> Option<String> {
+    let millis = i64::try_from(millis).ok()?;
+    DateTime::from_timestamp_millis(millis)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+/// MIME type of the file at `path`. Configured overrides win, then with
+/// sniffing enabled the first bytes of the file are checked for a known
+/// signature before falling back to [`guess_mime_with_fallback`].
+pub fn guess_mime(path: &Path, settings: &MimeSettings) -> String {
+    if let Some(mime) = mime_override(path, settings) {
+        return mime.to_string();
+    }
+
+    if settings.sniff
+        && let Ok(Some(kind)) = infer::get_from_path(path)
+    {
+        return kind.mime_type().to_string();
+    }
+
+    guess_mime_with_fallback(path, settings)
+}
+
+/// MIME type of the file at `path` from its extension. Only a file the
+/// extension says nothing about (`application/octet-stream`), such as an
+/// extensionless camera file, is opened to check its first bytes for a
+/// known signature, so this stays cheap for walks over many files.
+pub fn guess_mime_with_fallback(path: &Path, settings: &MimeSettings) -> String {
+    if let Some(mime) = mime_override(path, settings) {
+        return mime.to_string();
+    }
+
+    let mime = from_path(path).first_or_octet_stream();
+    if mime == mime_guess::mime::APPLICATION_OCTET_STREAM
+        && let Ok(Some(kind)) = infer::get_from_path(path)
+    {
+        return kind.mime_type().to_string();
+    }
+
+    mime.to_string()
+}
+
+fn mime_override<'a>(path: &Path, settings: &'a MimeSettings) -> Option<&'a str> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    settings.overrides.get(&extension).map(String::as_str)
 }
 
 // Helper function to get file owner (Unix only)
@@ -99,6 +230,18 @@ fn get_file_owner(_path: &Path) -> Option<String> {
     None
 }
 
+// Permission bits without the file type (Unix only)
+#[cfg(unix)]
+fn get_file_mode(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn get_file_mode(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
 fn get_size<P: AsRef<Path>>(path: P) -> std::io::Result<u64> {
     let total_size = 0;
     let path = path.as_ref();
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MediaInfo {
+    pub format_name: Option<String>,
+    pub duration: Option<f64>, // seconds
+    pub bit_rate: Option<u64>,
+    pub size: Option<u64>,
+    pub streams: Vec<MediaStream>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MediaStream {
+    pub index: u32,
+    pub codec_type: String, // video, audio, subtitle, ...
+    pub codec_name: Option<String>,
+    pub codec_long_name: Option<String>,
+    pub duration: Option<f64>, // seconds
+    pub bit_rate: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub channels: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub language: Option<String>,
+    pub title: Option<String>,
+}
+
+// Raw `ffprobe -print_format json` output. ffprobe reports most numbers as
+// strings, they are parsed when converting into MediaInfo.
+#[derive(Debug, Deserialize)]
+pub struct FfprobeOutput {
+    #[serde(default)]
+    pub streams: Vec<FfprobeStream>,
+    pub format: Option<FfprobeFormat>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FfprobeStream {
+    pub index: u32,
+    pub codec_type: Option<String>,
+    pub codec_name: Option<String>,
+    pub codec_long_name: Option<String>,
+    pub duration: Option<String>,
+    pub bit_rate: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub channels: Option<u32>,
+    pub sample_rate: Option<String>,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FfprobeFormat {
+    pub format_name: Option<String>,
+    pub duration: Option<String>,
+    pub bit_rate: Option<String>,
+    pub size: Option<String>,
+}
+
+impl From<FfprobeOutput> for MediaInfo {
+    fn from(output: FfprobeOutput) -> Self {
+        let format = output.format;
+
+        MediaInfo {
+            format_name: format.as_ref().and_then(|f| f.format_name.clone()),
+            duration: format
+                .as_ref()
+                .and_then(|f| f.duration.as_deref())
+                .and_then(|d| d.parse().ok()),
+            bit_rate: format
+                .as_ref()
+                .and_then(|f| f.bit_rate.as_deref())
+                .and_then(|b| b.parse().ok()),
+            size: format
+                .as_ref()
+                .and_then(|f| f.size.as_deref())
+                .and_then(|s| s.parse().ok()),
+            streams: output
+                .streams
+                .into_iter()
+                .map(|stream| MediaStream {
+                    index: stream.index,
+                    codec_type: stream.codec_type.unwrap_or_else(|| "unknown".to_string()),
+                    codec_name: stream.codec_name,
+                    codec_long_name: stream.codec_long_name,
+                    duration: stream.duration.and_then(|d| d.parse().ok()),
+                    bit_rate: stream.bit_rate.and_then(|b| b.parse().ok()),
+                    width: stream.width,
+                    height: stream.height,
+                    channels: stream.channels,
+                    sample_rate: stream.sample_rate.and_then(|s| s.parse().ok()),
+                    language: stream.tags.get("language").cloned(),
+                    title: stream.tags.get("title").cloned(),
+                })
+                .collect(),
+        }
+    }
+}
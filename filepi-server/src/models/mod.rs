@@ -1,4 +1,6 @@
+pub mod audio_info;
 pub mod file_info;
+pub mod media_info;
 use crate::models::file_info::FileInfo;
 use axum_typed_multipart::{FieldData, TryFromMultipart};
 use serde::{Deserialize, Serialize};
@@ -7,8 +9,29 @@ use serde::{Deserialize, Serialize};
 pub struct FilesResponse {
     pub files: Vec<FileInfo>,
     pub total_files: usize,
+    /// Combined size in bytes of the files across all pages. Directories add
+    /// nothing, their contents aren't walked.
+    pub total_size: u64,
     pub skip: usize,
     pub limit: usize,
+    /// The listed directory itself, only for plain listings. `total_files`
+    /// is its item count.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<FileInfo>,
+    /// Bytes available to the server on the root's filesystem, with `with_disk`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_free: Option<u64>,
+    /// Size in bytes of the root's filesystem, with `with_disk`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_total: Option<u64>,
+    /// Set when a search stopped early at `max_search_results` matches, so
+    /// `total_files` only counts those
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Set, together with `truncated`, when a search ran out of
+    /// `search_timeout_secs`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -21,11 +44,65 @@ pub struct FileQuery {
     pub query: Option<String>,
     #[serde(default)]
     pub skip_hidden: bool,
+    /// Stream search results as NDJSON instead of a paginated response
+    #[serde(default)]
+    pub stream: bool,
+    /// Include the tags of every entry
+    #[serde(default)]
+    pub with_tags: bool,
+    /// Only search entries carrying this tag
+    pub tag: Option<String>,
+    /// Count the immediate entries of every listed directory, leaving out
+    /// hidden ones with `skip_hidden`. Costs a directory read each.
+    #[serde(default)]
+    pub with_child_count: bool,
+    /// Include the free and total space of the filesystem holding the root
+    #[serde(default)]
+    pub with_disk: bool,
+    /// Rank search matches by a fuzzy score instead of requiring the query
+    /// as a substring. Sorted by score unless `sort_by` is given, and the
+    /// best matches are kept when there are more than `max_search_results`.
+    /// Streamed results are not ranked.
+    #[serde(default)]
+    pub fuzzy: bool,
+    /// How many levels below `path` search and video walks descend, 1 only
+    /// looks at its direct entries. Unlimited when unset.
+    pub max_depth: Option<usize>,
+    /// Add `created_iso` and `modified_iso` RFC 3339 timestamps next to the
+    /// millisecond ones
+    #[serde(default)]
+    pub iso_dates: bool,
+    /// List directories before files, the default. `false` sorts them
+    /// together by the chosen field.
+    #[serde(default = "default_true")]
+    pub group_dirs: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MediaQuery {
+    /// MIME type prefix such as `image/`, or a full type like `audio/flac`
+    #[serde(rename = "type")]
+    pub mime_prefix: String,
 }
 
+/// Error envelope shared by all REST handlers: `{ "error": { "code", "message" } }`
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
-    pub error: String,
+    pub error: ErrorBody,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -39,6 +116,201 @@ pub struct CreateFolderResponse {
     pub message: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SaveContentRequest {
+    pub path: String,
+    pub content: String,
+    /// ETag the client loaded, the save is rejected if the file changed since
+    pub if_match: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SaveContentResponse {
+    pub size: u64,
+    pub modified_time: Option<u128>,
+    pub etag: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TreeNode {
+    pub name: String,
+    /// Relative to the root directory
+    pub path: String,
+    pub file_count: usize,
+    pub dir_count: usize,
+    /// Empty below the requested depth even when `dir_count` isn't 0
+    pub children: Vec<TreeNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileLinesResponse {
+    pub start: usize,
+    /// Last line returned, lines past the end of the file are left out
+    pub end: usize,
+    pub lines: Vec<String>,
+    /// Whether the file continues after `end`
+    pub has_more: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FavoriteAction {
+    Add,
+    Remove,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FavoritesRequest {
+    pub path: String,
+    /// `add` (default) or `remove`
+    pub action: Option<FavoriteAction>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FavoritesResponse {
+    /// Relative paths in the order they were pinned
+    pub favorites: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TagsQuery {
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TagsRequest {
+    pub path: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagsResponse {
+    pub path: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TouchRequest {
+    pub path: String,
+    /// Milliseconds since the epoch, now when omitted
+    pub modified_time: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkType {
+    Hard,
+    Symbolic,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinkRequest {
+    /// Existing file or directory the link points to
+    pub source: String,
+    /// Path of the link to create
+    pub target: String,
+    #[serde(rename = "type")]
+    pub link_type: LinkType,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CopyRequest {
+    /// File or folder to copy
+    pub source: String,
+    /// Folder to copy into, the root when empty
+    #[serde(default)]
+    pub target: String,
+    /// Give the copy a numbered name like `name (1)` when `target` already
+    /// holds one with the same name, instead of failing
+    #[serde(default)]
+    pub rename: bool,
+    /// Answer with server-sent events reporting the progress of the copy
+    #[serde(default)]
+    pub progress: bool,
+}
+
+/// Sent as `progress` events while a copy streams its progress, totals are
+/// counted before the copy starts
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct CopyProgress {
+    pub files_copied: u64,
+    pub files_total: u64,
+    pub bytes_copied: u64,
+    pub bytes_total: u64,
+}
+
+/// The finished copy, also sent as the final `complete` event of a stream
+#[derive(Serialize)]
+pub struct CopyResponse {
+    pub message: String,
+    pub file: FileInfo,
+    pub files_copied: u64,
+    pub bytes_copied: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChmodRequest {
+    pub path: String,
+    /// Permission bits, e.g. 420 (0o644)
+    pub mode: u32,
+    /// Also apply to everything below a directory
+    #[serde(default)]
+    pub recursive: bool,
+    /// Overrides `mode` for directories, e.g. 493 (0o755)
+    pub dir_mode: Option<u32>,
+    /// Overrides `mode` for files
+    pub file_mode: Option<u32>,
+}
+
+/// The changed entry, plus how many entries changed in total
+#[derive(Serialize)]
+pub struct ChmodResponse {
+    #[serde(flatten)]
+    pub file: FileInfo,
+    pub changed: usize,
+    /// A recursive change stopped at the entry or depth limit
+    pub truncated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmptyRequest {
+    pub path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmptyResponse {
+    /// Direct children of the directory that were deleted
+    pub removed: usize,
+    /// Size of the files deleted, including those in subfolders
+    pub bytes: u64,
+    /// Children that couldn't be deleted, the rest are removed regardless
+    pub failed: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateFileRequest {
+    pub path: Option<String>,
+    pub filename: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ThumbnailPrefetchRequest {
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ThumbnailPrefetchResponse {
+    pub queued: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ThumbnailRegenerateRequest {
+    pub path: String,
+    /// Seconds into a video to take the frame from, instead of the
+    /// configured percentage of its duration
+    pub seek: Option<f64>,
+}
+
 #[derive(Serialize)]
 pub struct UploadResponse {
     pub message: String,
@@ -50,11 +322,110 @@ pub struct UploadResponse {
     pub skipped: bool,
 }
 
+/// Files and bytes an upload wrote, or skipped because an identical file was
+/// already there
+#[derive(Debug, Default, Serialize)]
+pub struct DedupSummary {
+    pub files_written: u64,
+    pub files_skipped: u64,
+    pub bytes_written: u64,
+    pub bytes_skipped: u64,
+}
+
+impl DedupSummary {
+    pub fn record(&mut self, bytes: u64, skipped: bool) {
+        if skipped {
+            self.files_skipped += 1;
+            self.bytes_skipped += bytes;
+        } else {
+            self.files_written += 1;
+            self.bytes_written += bytes;
+        }
+    }
+}
+
+/// Answer of a multi-file upload
+#[derive(Serialize)]
+pub struct UploadBatchResponse {
+    pub results: Vec<UploadResult>,
+    pub dedup: DedupSummary,
+}
+
+#[derive(Serialize)]
+pub struct MetricsResponse {
+    pub uploads: DedupSummary,
+}
+
+/// Outcome of one file of a multi-file upload. A single file upload answers
+/// with the bare [`UploadResponse`], or an [`UploadFailure`] and a `409`.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum UploadResult {
+    Uploaded(UploadResponse),
+    Failed(Box<UploadFailure>),
+}
+
+#[derive(Serialize)]
+pub struct UploadFailure {
+    pub filename: String,
+    pub error: ErrorBody,
+    /// The file the upload would have replaced, for conflicts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub existing: Option<FileInfo>,
+}
+
+/// Largest upload request body, the limit on the upload routes that
+/// capabilities advertise
+pub const MAX_UPLOAD_SIZE: u64 = 10 * 1024 * 1024 * 1024;
+
 #[derive(TryFromMultipart)]
 pub struct UploadForm {
     pub location: String,
     pub user: String,
-    #[form_data(limit = "10GiB")]
-    pub file: FieldData<bytes::Bytes>,
-    pub sha512: Option<String>,
+    /// One or more files, all stored in `location`. Bounded by the request
+    /// body limit, [`MAX_UPLOAD_SIZE`].
+    #[form_data(limit = "unlimited")]
+    pub file: Vec<FieldData<bytes::Bytes>>,
+    /// Expected SHA-512 of each file, in the same order as `file`
+    pub sha512: Vec<String>,
+    /// Replace an existing file with different content, defaults to false
+    pub overwrite: Option<bool>,
+    /// `fail` (default), `overwrite` or `rename` when the file already exists
+    /// with different content. Takes precedence over `overwrite`.
+    pub on_conflict: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CapabilitiesResponse {
+    pub version: String,
+    pub features: CapabilityFeatures,
+    pub limits: CapabilityLimits,
+    /// Operations this deployment answers, e.g. `upload` or `copy`
+    pub actions: Vec<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CapabilityFeatures {
+    pub tls: bool,
+    /// The admin API is enabled by `FILE_PI_ADMIN_TOKEN`
+    pub admin: bool,
+    /// Thumbnails that can't be generated are answered with a placeholder
+    pub thumbnail_fallback: bool,
+    pub mime_sniff: bool,
+    /// Entries report their mode and owner and `chmod` is available
+    pub permissions: bool,
+    /// Listings can include free and total disk space
+    pub disk_space: bool,
+}
+
+/// Limits of this deployment, `None` where there is none
+#[derive(Debug, Serialize)]
+pub struct CapabilityLimits {
+    pub max_upload_size: u64,
+    pub default_page_size: usize,
+    pub max_page_size: Option<usize>,
+    pub max_text_file_size: u64,
+    pub max_search_results: Option<usize>,
+    pub search_timeout_secs: Option<u64>,
+    pub request_timeout_secs: Option<u64>,
 }
@@ -0,0 +1,1512 @@
+use axum::{
+    Router,
+    extract::DefaultBodyLimit,
+    middleware as axum_middleware,
+    routing::{get, post},
+};
+
+use crate::handlers::{
+    self, admin, audio, capabilities, copy, favorites, files, hls, media, metrics, tags,
+    text_files, tree,
+};
+use crate::middleware::admin::require_admin_token;
+use crate::middleware::timeout::timeout_middleware;
+use crate::models::MAX_UPLOAD_SIZE;
+use crate::state::AppState;
+
+/// Routes served under `/api/v1`. Everything except the routes that move file
+/// contents is bounded by the request timeout, large transfers legitimately
+/// take long.
+pub fn api_routes(state: AppState) -> Router {
+    let timed_routes = Router::new()
+        .route("/capabilities", get(capabilities::get_capabilities))
+        .route("/metrics", get(metrics::get_metrics))
+        .route("/files", get(files::get_files))
+        .route("/videos", get(files::get_videos))
+        .route("/audios", get(files::get_audios))
+        .route("/images", get(files::get_images))
+        .route("/media", get(files::get_media))
+        .route("/search", get(files::search))
+        .route("/tree", get(tree::get_tree))
+        .route("/thumbnail/{*wildcard}", get(files::get_thumbnail))
+        .route("/thumbnails/prefetch", post(files::prefetch_thumbnails))
+        .route("/thumbnails/regenerate", post(files::regenerate_thumbnail))
+        .route("/image/{*wildcard}", get(files::get_resized_image))
+        .route("/mediainfo/{*wildcard}", get(media::get_media_info))
+        .route("/audioinfo/{*wildcard}", get(audio::get_audio_info))
+        .route("/subtitles/{*wildcard}", get(media::get_subtitles))
+        .route("/hls/{*wildcard}", get(hls::get_hls))
+        .route("/file/lines", get(text_files::get_lines))
+        .route("/createfolder", post(files::create_folder))
+        .route("/createfile", post(files::create_file))
+        .route("/chmod", post(files::chmod))
+        .route("/link", post(files::create_link))
+        .route("/touch", post(files::touch))
+        .route("/tags", get(tags::get_tags).post(tags::set_tags))
+        .route(
+            "/favorites",
+            get(favorites::get_favorites).post(favorites::update_favorites),
+        )
+        .route(
+            "/file/content",
+            // The save body is JSON, escaping can double the size of the text
+            get(text_files::read_content)
+                .post(text_files::save_content)
+                .layer(DefaultBodyLimit::max(
+                    (state.config.max_text_file_size as usize).saturating_mul(2),
+                )),
+        )
+        .route(
+            "/syncfusion/fileoperations",
+            post(handlers::syncfusion::file_operations),
+        )
+        .route_layer(axum_middleware::from_fn_with_state(
+            state.config.clone(),
+            timeout_middleware,
+        ));
+
+    let upload_limit = DefaultBodyLimit::max(MAX_UPLOAD_SIZE as usize);
+    let transfer_routes = Router::new()
+        .route("/file/{*wildcard}", get(files::serve_file))
+        .route("/stream/{*wildcard}", get(files::stream_file))
+        .route("/file/tail", get(text_files::tail))
+        .route("/uploadfile", post(files::upload_file).layer(upload_limit))
+        .route("/copy", post(copy::copy))
+        .route("/empty", post(files::empty_directory))
+        .route("/syncfusion/download", post(handlers::syncfusion::download))
+        .route(
+            "/syncfusion/upload",
+            post(handlers::syncfusion::upload).layer(upload_limit),
+        )
+        .route("/syncfusion/getimage", get(handlers::syncfusion::get_image));
+
+    let admin_routes = Router::new()
+        .route("/admin/shutdown", post(admin::shutdown))
+        .route_layer(axum_middleware::from_fn_with_state(
+            state.config.clone(),
+            require_admin_token,
+        ));
+
+    timed_routes
+        .merge(transfer_routes)
+        .merge(admin_routes)
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::models::file_info::MimeSettings;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode, header};
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use tokio_util::sync::CancellationToken;
+    use tower::ServiceExt;
+
+    // A root with a few files and folders, next to a file outside of it
+    fn fixture() -> (TempDir, Router) {
+        fixture_with(|_| {})
+    }
+
+    // Like `fixture`, with the config adjusted by `configure`
+    fn fixture_with(configure: impl FnOnce(&mut Config)) -> (TempDir, Router) {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("root");
+        fs::create_dir_all(root.join("docs/nested")).unwrap();
+        fs::create_dir(root.join("photos")).unwrap();
+        fs::write(root.join("alpha.txt"), "alpha").unwrap();
+        fs::write(root.join(".hidden"), "hidden").unwrap();
+        fs::write(root.join("docs/readme.txt"), "read me").unwrap();
+        fs::write(root.join("docs/nested/deep-readme.md"), "deep").unwrap();
+        fs::write(temp.path().join("secret.txt"), "secret").unwrap();
+
+        let mut config = Config {
+            root_dir: root.to_string_lossy().into_owned(),
+            bind_addr: [127, 0, 0, 1].into(),
+            port: 0,
+            log_level: "info".to_string(),
+            cache_dir: temp.path().join("cache"),
+            log_dir: temp.path().join("logs"),
+            log_skip_paths: Vec::new(),
+            log_body_limit: 0,
+            request_timeout_secs: 0,
+            listing_cache_ttl_secs: 0,
+            listing_cache_size: 0,
+            thumb_concurrency: 1,
+            thumb_cache_max_age_secs: 0,
+            thumb_fallback: true,
+            thumb_seek_percent: 25,
+            image_cache_max_age_secs: 0,
+            max_text_file_size: 1024,
+            max_search_results: 0,
+            search_timeout_secs: 0,
+            mime: MimeSettings::default(),
+            video_extensions: Vec::new(),
+            ffmpeg_path: "ffmpeg".to_string(),
+            ffprobe_path: "ffprobe".to_string(),
+            tls_cert: None,
+            tls_key: None,
+            admin_token: None,
+            web_root: temp.path().join("webdeploy"),
+            spa_index: "index.html".into(),
+            spa_watch: false,
+        };
+        configure(&mut config);
+
+        let state = AppState::new(Arc::new(config), CancellationToken::new());
+        (temp, api_routes(state))
+    }
+
+    fn root(temp: &TempDir) -> std::path::PathBuf {
+        temp.path().join("root")
+    }
+
+    async fn send(app: Router, request: Request<Body>) -> (StatusCode, Vec<u8>) {
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, body.to_vec())
+    }
+
+    async fn get_json(app: Router, uri: &str) -> (StatusCode, Value) {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        let (status, body) = send(app, request).await;
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    async fn post_json(app: Router, uri: &str, body: Value) -> (StatusCode, Value) {
+        let request = Request::post(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let (status, body) = send(app, request).await;
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn names(response: &Value) -> Vec<&str> {
+        response["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|file| file["name"].as_str().unwrap())
+            .collect()
+    }
+
+    fn multipart_upload(location: &str, file_name: &str, contents: &str) -> Request<Body> {
+        let boundary = "filepi-test-boundary";
+        let body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"location\"\r\n\r\n{location}\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"user\"\r\n\r\ntester\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\n\
+             Content-Type: text/plain\r\n\r\n{contents}\r\n\
+             --{b}--\r\n",
+            b = boundary
+        );
+        Request::post("/uploadfile")
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    fn assert_error(status: StatusCode, body: &Value, expected: StatusCode) {
+        assert_eq!(status, expected, "unexpected response {}", body);
+        assert!(body["error"]["message"].is_string());
+    }
+
+    #[tokio::test]
+    async fn describes_capabilities() {
+        let (_temp, app) = fixture();
+
+        let (status, body) = get_json(app, "/capabilities").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["features"]["admin"], false);
+        assert_eq!(body["limits"]["default_page_size"], 25);
+        let actions = body["actions"].as_array().unwrap();
+        assert!(actions.contains(&Value::from("upload")));
+        assert!(!actions.contains(&Value::from("shutdown")));
+    }
+
+    #[tokio::test]
+    async fn routes_every_advertised_action() {
+        let (_temp, app) = fixture();
+        let routes = [
+            ("list", "GET", "/files"),
+            ("search", "GET", "/search"),
+            ("media", "GET", "/media"),
+            ("tree", "GET", "/tree"),
+            ("tags", "POST", "/tags"),
+            ("favorites", "POST", "/favorites"),
+            ("thumbnails", "GET", "/thumbnail/alpha.txt"),
+            ("regenerate_thumbnail", "POST", "/thumbnails/regenerate"),
+            (
+                "animated_previews",
+                "GET",
+                "/thumbnail/alpha.txt?animated=true",
+            ),
+            ("resized_images", "GET", "/image/alpha.txt"),
+            ("media_info", "GET", "/mediainfo/alpha.txt"),
+            ("audio_info", "GET", "/audioinfo/alpha.txt"),
+            ("subtitles", "GET", "/subtitles/alpha.txt"),
+            ("hls", "GET", "/hls/alpha.txt/index.m3u8"),
+            ("read_text", "GET", "/file/content"),
+            ("save_text", "POST", "/file/content"),
+            ("tail", "GET", "/file/tail"),
+            ("create_folder", "POST", "/createfolder"),
+            ("create_file", "POST", "/createfile"),
+            ("link", "POST", "/link"),
+            ("touch", "POST", "/touch"),
+            ("empty", "POST", "/empty"),
+            ("upload", "POST", "/uploadfile"),
+            ("copy", "POST", "/copy"),
+            ("download", "GET", "/file/alpha.txt"),
+            ("syncfusion", "POST", "/syncfusion/fileoperations"),
+            ("metrics", "GET", "/metrics"),
+            ("chmod", "POST", "/chmod"),
+            ("shutdown", "POST", "/admin/shutdown"),
+        ];
+
+        let advertised = capabilities::ACTIONS.iter().chain(&["chmod", "shutdown"]);
+        for action in advertised {
+            let (_, method, uri) = routes
+                .iter()
+                .find(|(name, _, _)| name == action)
+                .unwrap_or_else(|| panic!("no route listed for action {}", action));
+            let request = Request::builder()
+                .method(*method)
+                .uri(*uri)
+                .body(Body::empty())
+                .unwrap();
+
+            // The router answers unknown routes with an empty 404 or a 405,
+            // handlers always explain their errors
+            let (status, body) = send(app.clone(), request).await;
+            assert_ne!(status, StatusCode::METHOD_NOT_ALLOWED, "{} {}", method, uri);
+            assert!(
+                status != StatusCode::NOT_FOUND || !body.is_empty(),
+                "{} {} is not routed",
+                method,
+                uri
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_directory_with_folders_first() {
+        let (_temp, app) = fixture();
+
+        let (status, body) = get_json(app, "/files?skip_hidden=true").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(names(&body), ["docs", "photos", "alpha.txt"]);
+        assert_eq!(body["total_files"], 3);
+        assert_eq!(body["cwd"]["name"], "root");
+    }
+
+    #[tokio::test]
+    async fn lists_subdirectory_page() {
+        let (_temp, app) = fixture();
+
+        let (status, body) = get_json(app, "/files?path=docs&skip=1&limit=1").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(names(&body), ["readme.txt"]);
+        assert_eq!(body["total_files"], 2);
+    }
+
+    #[tokio::test]
+    async fn caches_default_listing_of_small_directories() {
+        let (temp, app) = fixture_with(|config| {
+            config.listing_cache_ttl_secs = 60;
+            config.listing_cache_size = 8;
+        });
+        let large = root(&temp).join("large");
+        fs::create_dir(&large).unwrap();
+        for i in 0..1001 {
+            fs::write(large.join(format!("file-{:04}.txt", i)), "").unwrap();
+        }
+
+        // Files written behind the server's back only show up uncached
+        for (dir, expected_total) in [("docs", 2), ("large", 1002)] {
+            let uri = format!("/files?path={}&limit=1", dir);
+            let (status, _) = get_json(app.clone(), &uri).await;
+            assert_eq!(status, StatusCode::OK);
+
+            fs::write(root(&temp).join(dir).join("new.txt"), "").unwrap();
+            let (_, body) = get_json(app.clone(), &uri).await;
+            assert_eq!(body["total_files"], expected_total);
+        }
+    }
+
+    #[tokio::test]
+    async fn default_order_matches_sorted_listing() {
+        let (_temp, app) = fixture();
+
+        // Without sort_by only the page is read in full, with it every entry
+        for query in [
+            "skip=1&limit=2",
+            "skip=1&limit=2&skip_hidden=true",
+            "skip=1&limit=2&group_dirs=false",
+        ] {
+            let (_, fast) = get_json(app.clone(), &format!("/files?{}", query)).await;
+            let uri = format!("/files?{}&sort_by=name", query);
+            let (_, full) = get_json(app.clone(), &uri).await;
+
+            assert_eq!(names(&fast), names(&full), "{}", query);
+            assert_eq!(fast["total_files"], full["total_files"], "{}", query);
+            assert_eq!(fast["total_size"], full["total_size"], "{}", query);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn lists_broken_symlinks() {
+        let (temp, app) = fixture();
+        std::os::unix::fs::symlink(root(&temp).join("gone.txt"), root(&temp).join("dangling"))
+            .unwrap();
+
+        for uri in ["/files", "/files?sort_by=name"] {
+            let (status, body) = get_json(app.clone(), uri).await;
+
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            let files = body["files"].as_array().unwrap();
+            let link = files
+                .iter()
+                .find(|file| file["name"] == "dangling")
+                .unwrap();
+            assert_eq!(link["is_symlink"], true, "{}", uri);
+            assert_eq!(link["is_directory"], false, "{}", uri);
+            assert_eq!(link["size"], 0, "{}", uri);
+            assert_eq!(link["created_time"], 0, "{}", uri);
+            assert_eq!(link["modified_time"], 0, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_listing_outside_root() {
+        let (_temp, app) = fixture();
+
+        let (status, body) = get_json(app.clone(), "/files?path=..").await;
+        assert_error(status, &body, StatusCode::BAD_REQUEST);
+
+        let (status, body) = get_json(app, "/files?path=missing").await;
+        assert_error(status, &body, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn sniffs_files_without_a_known_extension() {
+        let (temp, app) = fixture();
+        let png_signature = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        fs::write(root(&temp).join("photos/IMG0001"), png_signature).unwrap();
+        fs::write(root(&temp).join("photos/notes"), "plain text").unwrap();
+
+        let (status, body) = get_json(app, "/images?path=photos").await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(names(&body), ["IMG0001"]);
+        assert_eq!(body["files"][0]["file_type"], "image/png");
+    }
+
+    #[tokio::test]
+    async fn sniffs_every_file_when_the_config_asks() {
+        let (temp, app) = fixture_with(|config| config.mime.sniff = true);
+        let png_signature = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        fs::write(root(&temp).join("photos/scan.txt"), png_signature).unwrap();
+
+        let (status, body) = get_json(app, "/files?path=photos").await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(names(&body), ["scan.txt"]);
+        assert_eq!(body["files"][0]["file_type"], "image/png");
+    }
+
+    #[tokio::test]
+    async fn applies_configured_mime_overrides() {
+        let (temp, app) = fixture_with(|config| {
+            config
+                .mime
+                .overrides
+                .insert("cr9".to_string(), "image/x-camera-raw".to_string());
+        });
+        fs::write(root(&temp).join("photos/IMG0002.CR9"), "raw").unwrap();
+
+        let (status, body) = get_json(app, "/images?path=photos").await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(names(&body), ["IMG0002.CR9"]);
+        assert_eq!(body["files"][0]["file_type"], "image/x-camera-raw");
+    }
+
+    // Spreads the letters of `query` apart with `gap` filler characters
+    fn scattered(query: &str, gap: usize) -> String {
+        let filler = "x".repeat(gap);
+        let letters: Vec<String> = query.chars().map(String::from).collect();
+        format!("{}.txt", letters.join(&filler))
+    }
+
+    #[tokio::test]
+    async fn fuzzy_search_ranks_and_drops_scattered_matches() {
+        let (temp, app) = fixture();
+        for name in [
+            "monthlyreport.txt".to_string(),
+            "report.txt".to_string(),
+            scattered("report", 3),
+            "monthly-report.txt".to_string(),
+            // Matches every letter, but too far apart to pass the threshold
+            scattered("report", 15),
+        ] {
+            fs::write(root(&temp).join("photos").join(name), "").unwrap();
+        }
+
+        let (status, body) = get_json(app, "/search?path=photos&query=report&fuzzy=true").await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(
+            names(&body),
+            [
+                "report.txt",
+                "monthly-report.txt",
+                "monthlyreport.txt",
+                &scattered("report", 3)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn fuzzy_search_keeps_best_matches_when_truncating() {
+        let (temp, app) = fixture_with(|config| config.max_search_results = 2);
+        for gap in 1..=8 {
+            fs::write(
+                root(&temp).join("photos").join(scattered("report", gap)),
+                "",
+            )
+            .unwrap();
+        }
+        fs::create_dir(root(&temp).join("photos/deep")).unwrap();
+        fs::write(root(&temp).join("photos/deep/report.txt"), "").unwrap();
+        fs::write(root(&temp).join("photos/monthly-report.txt"), "").unwrap();
+
+        let (status, body) = get_json(app, "/search?path=photos&query=report&fuzzy=true").await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(names(&body), ["report.txt", "monthly-report.txt"]);
+        assert_eq!(body["truncated"], true);
+    }
+
+    #[tokio::test]
+    async fn searches_recursively() {
+        let (_temp, app) = fixture();
+
+        let (status, body) = get_json(app.clone(), "/search?query=README").await;
+
+        assert_eq!(status, StatusCode::OK);
+        let mut found = names(&body);
+        found.sort();
+        assert_eq!(found, ["deep-readme.md", "readme.txt"]);
+
+        let (status, body) = get_json(app, "/search?query=readme&max_depth=1&path=docs").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(names(&body), ["readme.txt"]);
+    }
+
+    #[tokio::test]
+    async fn leaves_out_hidden_and_system_folders() {
+        let (temp, app) = fixture_with(|config| {
+            config.cache_dir = Path::new(&config.root_dir).join(".cache");
+        });
+        for dir in [".cache", ".trash", ".config"] {
+            fs::create_dir(root(&temp).join(dir)).unwrap();
+            fs::write(root(&temp).join(dir).join("readme.txt"), dir).unwrap();
+        }
+
+        let (_, body) = get_json(app.clone(), "/files").await;
+        assert_eq!(
+            names(&body),
+            [".config", "docs", "photos", ".hidden", "alpha.txt"]
+        );
+
+        let (_, body) = get_json(app.clone(), "/search?query=readme&skip_hidden=true").await;
+        let mut found = names(&body);
+        found.sort();
+        assert_eq!(found, ["deep-readme.md", "readme.txt"]);
+
+        let (_, body) = get_json(app, "/search?query=readme").await;
+        assert_eq!(body["total_files"], 3);
+    }
+
+    #[tokio::test]
+    async fn lists_root_cache_folder_when_cache_dir_is_elsewhere() {
+        let (temp, app) = fixture();
+        fs::create_dir(root(&temp).join(".cache")).unwrap();
+
+        let (_, body) = get_json(app, "/files").await;
+        assert!(names(&body).contains(&".cache"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn leaves_out_cache_dir_inside_root() {
+        let (temp, app) = fixture_with(|config| {
+            config.cache_dir = Path::new(&config.root_dir).join("thumbs");
+        });
+        fs::create_dir(root(&temp).join("thumbs")).unwrap();
+        fs::write(root(&temp).join("thumbs/readme.txt"), "cached").unwrap();
+
+        let (_, body) = get_json(app.clone(), "/files").await;
+        assert!(!names(&body).contains(&"thumbs"), "{}", body);
+
+        let (_, body) = get_json(app.clone(), "/search?query=readme").await;
+        assert_eq!(body["total_files"], 2);
+
+        let (_, body) = get_json(app, "/tree").await;
+        assert_eq!(body["dir_count"], 2);
+    }
+
+    #[tokio::test]
+    async fn lists_nested_folders_named_like_system_folders() {
+        let (temp, app) = fixture();
+        fs::create_dir(root(&temp).join("docs/.cache")).unwrap();
+        fs::write(root(&temp).join("docs/.cache/notes.txt"), "notes").unwrap();
+
+        let (_, body) = get_json(app.clone(), "/files?path=docs").await;
+        assert!(names(&body).contains(&".cache"), "{}", body);
+
+        let (_, body) = get_json(app.clone(), "/search?query=notes").await;
+        assert_eq!(names(&body), ["notes.txt"]);
+
+        let (_, body) = get_json(app, "/tree?path=docs").await;
+        assert_eq!(body["dir_count"], 2);
+    }
+
+    #[tokio::test]
+    async fn rejects_search_without_query() {
+        let (_temp, app) = fixture();
+
+        let (status, body) = get_json(app, "/search?path=docs").await;
+
+        assert_error(status, &body, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn serves_file_contents() {
+        let (_temp, app) = fixture();
+
+        let request = Request::get("/file/docs/readme.txt?inline=true")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "inline; filename=\"readme.txt\""
+        );
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"read me");
+    }
+
+    #[tokio::test]
+    async fn serves_ranges_only_while_if_range_matches() {
+        let (_temp, app) = fixture();
+
+        let request = Request::get("/file/docs/readme.txt")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let etag = response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let modified = response.headers()[header::LAST_MODIFIED]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        for (if_range, expected, contents) in [
+            (None, StatusCode::PARTIAL_CONTENT, &b"read"[..]),
+            (Some(etag.as_str()), StatusCode::PARTIAL_CONTENT, b"read"),
+            (
+                Some(modified.as_str()),
+                StatusCode::PARTIAL_CONTENT,
+                b"read",
+            ),
+            (Some("\"changed\""), StatusCode::OK, b"read me"),
+            (
+                Some("Thu, 01 Jan 1970 00:00:00 GMT"),
+                StatusCode::OK,
+                b"read me",
+            ),
+        ] {
+            let mut request =
+                Request::get("/file/docs/readme.txt").header(header::RANGE, "bytes=0-3");
+            if let Some(if_range) = if_range {
+                request = request.header(header::IF_RANGE, if_range);
+            }
+            let (status, body) = send(app.clone(), request.body(Body::empty()).unwrap()).await;
+
+            assert_eq!(status, expected, "If-Range: {:?}", if_range);
+            assert_eq!(&body[..], contents, "If-Range: {:?}", if_range);
+        }
+
+        let request = Request::get("/file/docs/readme.txt")
+            .header(header::IF_NONE_MATCH, &etag)
+            .body(Body::empty())
+            .unwrap();
+        let (status, _) = send(app, request).await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+    }
+
+    #[tokio::test]
+    async fn resumes_syncfusion_download() {
+        let (_temp, app) = fixture();
+
+        let download_input = serde_json::json!({ "path": "/docs/", "names": ["readme.txt"] });
+        let form = format!(
+            "downloadInput={}",
+            serde_json::to_string(&download_input)
+                .unwrap()
+                .replace('"', "%22")
+        );
+        let request = |range: Option<&str>| {
+            let mut request = Request::post("/syncfusion/download")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+            if let Some(range) = range {
+                request = request.header(header::RANGE, range);
+            }
+            request.body(Body::from(form.clone())).unwrap()
+        };
+
+        let response = app.clone().oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ACCEPT_RANGES], "bytes");
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"readme.txt\""
+        );
+
+        let (status, body) = send(app, request(Some("bytes=5-"))).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(&body[..], b"me");
+    }
+
+    #[tokio::test]
+    async fn refuses_to_serve_outside_root() {
+        let (_temp, app) = fixture();
+
+        for uri in [
+            "/file/../secret.txt",
+            "/file/../missing.txt",
+            "/file/docs/../../secret.txt",
+            "/stream/../secret.txt",
+            "/thumbnail/../secret.txt",
+        ] {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            let (status, body) = send(app.clone(), request).await;
+            assert_eq!(status, StatusCode::FORBIDDEN, "{}", uri);
+            assert!(!String::from_utf8_lossy(&body).contains("secret"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn refuses_to_serve_symlink_to_outside_root() {
+        let (temp, app) = fixture();
+        std::os::unix::fs::symlink(temp.path().join("secret.txt"), root(&temp).join("leak"))
+            .unwrap();
+
+        let (status, body) = get_json(app, "/file/leak").await;
+
+        assert_error(status, &body, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn decodes_file_paths_but_not_separators() {
+        let (temp, app) = fixture();
+        fs::write(root(&temp).join("docs/my file #1.txt"), "spaced").unwrap();
+
+        let request = Request::get("/file/docs/my%20file%20%231.txt")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(app.clone(), request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"spaced");
+
+        for uri in [
+            "/file/docs%2Freadme.txt",
+            "/file/..%2fsecret.txt",
+            "/stream/docs%2Freadme.txt",
+        ] {
+            let (status, body) = get_json(app.clone(), uri).await;
+            assert_error(status, &body, StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn file_routes_report_missing_files_and_directories() {
+        let (_temp, app) = fixture();
+
+        for route in ["file", "stream", "thumbnail"] {
+            let uri = format!("/{}/docs/missing.txt", route);
+            let (status, body) = get_json(app.clone(), &uri).await;
+            assert_error(status, &body, StatusCode::NOT_FOUND);
+
+            let uri = format!("/{}/docs", route);
+            let (status, body) = get_json(app.clone(), &uri).await;
+            assert_error(status, &body, StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_placeholder_for_unsupported_thumbnails() {
+        let (_temp, app) = fixture();
+
+        let request = Request::get("/thumbnail/alpha.txt")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/svg+xml");
+        assert_eq!(response.headers()["x-thumbnail-placeholder"], "true");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8_lossy(&body).contains(">TXT<"));
+
+        let (status, body) = get_json(app, "/thumbnail/alpha.txt?fallback=false").await;
+        assert_error(status, &body, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn regenerates_thumbnail() {
+        let (temp, app) = fixture();
+        image::RgbImage::new(8, 8)
+            .save(root(&temp).join("photos/dot.png"))
+            .unwrap();
+
+        let request = Request::post("/thumbnails/regenerate")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{ "path": "photos/dot.png" }"#))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/jpeg");
+
+        for (request, expected) in [
+            (
+                serde_json::json!({ "path": "alpha.txt" }),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                serde_json::json!({ "path": "../secret.txt" }),
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                serde_json::json!({ "path": "photos/dot.png", "seek": -1.0 }),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+        ] {
+            let (status, body) = post_json(app.clone(), "/thumbnails/regenerate", request).await;
+            assert_error(status, &body, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn overwritten_image_gets_new_thumbnail() {
+        let (temp, app) = fixture();
+        let image_path = root(&temp).join("photos/dot.png");
+        image::RgbImage::new(8, 8).save(&image_path).unwrap();
+
+        let thumbnail_etag = |app: Router| async move {
+            let request = Request::get("/thumbnail/photos/dot.png")
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()[header::CONTENT_TYPE], "image/jpeg");
+            response.headers()[header::ETAG].clone()
+        };
+
+        let before = thumbnail_etag(app.clone()).await;
+        assert_eq!(thumbnail_etag(app.clone()).await, before);
+
+        let modified = fs::metadata(&image_path).unwrap().modified().unwrap();
+        image::RgbImage::from_pixel(64, 32, image::Rgb([200, 40, 40]))
+            .save(&image_path)
+            .unwrap();
+        filetime::set_file_mtime(
+            &image_path,
+            filetime::FileTime::from_system_time(modified + std::time::Duration::from_secs(5)),
+        )
+        .unwrap();
+
+        assert_ne!(thumbnail_etag(app).await, before);
+
+        // Only the thumbnail of the current version is kept
+        let thumbnails: Vec<_> = fs::read_dir(temp.path().join("cache"))
+            .unwrap()
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .flat_map(|dir| fs::read_dir(dir.path()).unwrap().flatten())
+            .map(|entry| entry.file_name())
+            .collect();
+        assert_eq!(thumbnails.len(), 1, "{:?}", thumbnails);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn timed_out_request_stops_ffmpeg() {
+        use std::io::Read;
+        use std::os::unix::fs::PermissionsExt;
+
+        let (temp, app) = fixture_with(|config| {
+            config.request_timeout_secs = 1;
+            config.ffmpeg_path = config
+                .cache_dir
+                .with_file_name("slow-ffmpeg")
+                .to_string_lossy()
+                .into_owned();
+            config.ffprobe_path = "/nonexistent/ffprobe".to_string();
+        });
+        // Far outlives the timeout while holding the write end of a pipe,
+        // reading the pipe ends when the process is gone
+        let ffmpeg = temp.path().join("slow-ffmpeg");
+        let pipe = temp.path().join("slow-ffmpeg.pipe");
+        nix::unistd::mkfifo(&pipe, nix::sys::stat::Mode::S_IRWXU).unwrap();
+        fs::write(&ffmpeg, "#!/bin/sh\nexec sleep 60 3>\"$0.pipe\"\n").unwrap();
+        fs::set_permissions(&ffmpeg, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(root(&temp).join("clip.mp4"), "not really a video").unwrap();
+
+        let exited = tokio::task::spawn_blocking(move || {
+            let mut output = Vec::new();
+            fs::File::open(pipe)?.read_to_end(&mut output)
+        });
+
+        let (status, body) = get_json(app, "/thumbnail/clip.mp4").await;
+        assert_error(status, &body, StatusCode::GATEWAY_TIMEOUT);
+
+        let exited = tokio::time::timeout(std::time::Duration::from_secs(30), exited).await;
+        assert!(exited.is_ok(), "ffmpeg still running after the timeout");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn failed_ffmpeg_leaves_no_thumbnail() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (temp, app) = fixture_with(|config| {
+            config.ffmpeg_path = config
+                .cache_dir
+                .with_file_name("broken-ffmpeg")
+                .to_string_lossy()
+                .into_owned();
+            config.ffprobe_path = "/nonexistent/ffprobe".to_string();
+        });
+        // Writes part of its output file, then fails
+        let ffmpeg = temp.path().join("broken-ffmpeg");
+        fs::write(
+            &ffmpeg,
+            "#!/bin/sh\nfor arg; do case $arg in *.jpg|*.partial) printf x > \"$arg\";; esac; done\nexit 1\n",
+        )
+        .unwrap();
+        fs::set_permissions(&ffmpeg, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(root(&temp).join("clip.mp4"), "not really a video").unwrap();
+
+        for _ in 0..2 {
+            let (status, body) = get_json(app.clone(), "/thumbnail/clip.mp4?fallback=false").await;
+            assert_error(status, &body, StatusCode::INTERNAL_SERVER_ERROR);
+        }
+
+        let cached: Vec<_> = walkdir::WalkDir::new(temp.path().join("cache"))
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .collect();
+        assert!(cached.is_empty(), "{:?}", cached);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn new_seek_percent_replaces_old_video_thumbnails() {
+        use crate::handlers::thumbnail_manager::{get_md5_hash, modified_millis};
+        use std::os::unix::fs::PermissionsExt;
+
+        let (temp, app) = fixture_with(|config| {
+            config.ffmpeg_path = config
+                .cache_dir
+                .with_file_name("fake-ffmpeg")
+                .to_string_lossy()
+                .into_owned();
+            config.ffprobe_path = "/nonexistent/ffprobe".to_string();
+        });
+        // Writes a stand-in frame to its output file
+        let ffmpeg = temp.path().join("fake-ffmpeg");
+        fs::write(
+            &ffmpeg,
+            "#!/bin/sh\nfor arg; do case $arg in *.partial) printf frame > \"$arg\";; esac; done\n",
+        )
+        .unwrap();
+        fs::set_permissions(&ffmpeg, fs::Permissions::from_mode(0o755)).unwrap();
+        let clip = root(&temp).join("clip.mp4");
+        fs::write(&clip, "not really a video").unwrap();
+
+        // Taken at another percentage, and from before thumbnails were keyed
+        let thumbnail_dir = temp
+            .path()
+            .join("cache")
+            .join(get_md5_hash(&clip.to_string_lossy()));
+        fs::create_dir_all(&thumbnail_dir).unwrap();
+        fs::write(thumbnail_dir.join("thumbnail_10pct_1.jpg"), "old").unwrap();
+        fs::write(thumbnail_dir.join("thumbnail.jpg"), "old").unwrap();
+
+        let request = Request::get("/thumbnail/clip.mp4?fallback=false")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(app, request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"frame");
+
+        let cached: Vec<_> = fs::read_dir(&thumbnail_dir)
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            cached,
+            [format!("thumbnail_25pct_{}.jpg", modified_millis(&clip))]
+        );
+    }
+
+    #[tokio::test]
+    async fn streams_extensionless_video_as_hls() {
+        let (temp, app) = fixture_with(|config| {
+            config.ffprobe_path = "/nonexistent/ffprobe".to_string();
+        });
+        // An MP4 signature without an extension, like some camera files
+        fs::write(
+            root(&temp).join("clip"),
+            b"\0\0\0\x18ftypmp42\0\0\0\0mp42isom",
+        )
+        .unwrap();
+        fs::write(root(&temp).join("notes"), "plain text").unwrap();
+
+        // Accepted as a video, only probing it fails without ffprobe
+        let (status, body) = get_json(app.clone(), "/hls/clip/playlist.m3u8").await;
+        assert_error(status, &body, StatusCode::INTERNAL_SERVER_ERROR);
+
+        let (status, body) = get_json(app, "/hls/notes/playlist.m3u8").await;
+        assert_error(status, &body, StatusCode::BAD_REQUEST);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn chmods_tree_with_non_searchable_directory_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (temp, app) = fixture();
+
+        let request = serde_json::json!({ "path": "docs", "mode": 0o644, "recursive": true });
+        let (status, body) = post_json(app, "/chmod", request).await;
+
+        let mode = |path: &str| {
+            fs::metadata(root(&temp).join(path))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o7777
+        };
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["changed"], 4);
+        // Each directory is made searchable again once checked, to reach
+        // what is below it without being root
+        for path in ["docs", "docs/nested", "docs/nested/deep-readme.md"] {
+            assert_eq!(mode(path), 0o644, "{}", path);
+            if !path.ends_with(".md") {
+                fs::set_permissions(root(&temp).join(path), fs::Permissions::from_mode(0o755))
+                    .unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn creates_folder() {
+        let (temp, app) = fixture();
+
+        let request = serde_json::json!({ "path": "docs", "foldername": "new" });
+        let (status, body) = post_json(app.clone(), "/createfolder", request.clone()).await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert!(root(&temp).join("docs/new").is_dir());
+
+        let (status, body) = post_json(app, "/createfolder", request).await;
+        assert_error(status, &body, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "already_exists");
+    }
+
+    #[tokio::test]
+    async fn refuses_to_create_folder_outside_root() {
+        let (temp, app) = fixture();
+
+        for (path, foldername) in [("..", "escaped"), ("docs", "../../escaped")] {
+            let request = serde_json::json!({ "path": path, "foldername": foldername });
+            let (status, body) = post_json(app.clone(), "/createfolder", request).await;
+
+            assert_error(status, &body, StatusCode::BAD_REQUEST);
+            assert!(!temp.path().join("escaped").exists());
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn creates_symlink_relative_to_its_folder() {
+        let (temp, app) = fixture();
+
+        let request = serde_json::json!({
+            "source": "docs/readme.txt",
+            "target": "photos/readme",
+            "type": "symbolic"
+        });
+        let (status, body) = post_json(app, "/link", request).await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["is_symlink"], true);
+        let link = root(&temp).join("photos/readme");
+        assert_eq!(
+            fs::read_link(&link).unwrap(),
+            Path::new("../docs/readme.txt")
+        );
+        assert_eq!(fs::read_to_string(link).unwrap(), "read me");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn creates_hard_link() {
+        use std::os::unix::fs::MetadataExt;
+
+        let (temp, app) = fixture();
+
+        let request = serde_json::json!({
+            "source": "docs/readme.txt",
+            "target": "photos/readme.txt",
+            "type": "hard"
+        });
+        let (status, body) = post_json(app, "/link", request).await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["is_symlink"], false);
+        let source = fs::metadata(root(&temp).join("docs/readme.txt")).unwrap();
+        let link = fs::symlink_metadata(root(&temp).join("photos/readme.txt")).unwrap();
+        assert_eq!(link.ino(), source.ino());
+        assert_eq!(source.nlink(), 2);
+    }
+
+    #[tokio::test]
+    async fn refuses_link_over_existing_target() {
+        let (temp, app) = fixture();
+
+        for link_type in ["symbolic", "hard"] {
+            let request = serde_json::json!({
+                "source": "alpha.txt",
+                "target": "docs/readme.txt",
+                "type": link_type
+            });
+            let (status, body) = post_json(app.clone(), "/link", request).await;
+
+            assert_error(status, &body, StatusCode::CONFLICT);
+            assert_eq!(body["error"]["code"], "already_exists");
+            assert_eq!(
+                fs::read_to_string(root(&temp).join("docs/readme.txt")).unwrap(),
+                "read me"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_link_outside_root() {
+        let (temp, app) = fixture();
+
+        for (source, target) in [
+            ("../secret.txt", "photos/secret"),
+            ("alpha.txt", "../escaped"),
+        ] {
+            let request = serde_json::json!({
+                "source": source,
+                "target": target,
+                "type": "symbolic"
+            });
+            let (status, body) = post_json(app.clone(), "/link", request).await;
+
+            assert_error(status, &body, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"]["code"], "path_outside_root");
+        }
+        assert!(fs::symlink_metadata(root(&temp).join("photos/secret")).is_err());
+        assert!(fs::symlink_metadata(temp.path().join("escaped")).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn creates_racing_folder_once() {
+        let (temp, app) = fixture();
+
+        let request = serde_json::json!({ "path": "docs", "foldername": "race/leaf" });
+        let mut requests = tokio::task::JoinSet::new();
+        for _ in 0..8 {
+            requests.spawn(post_json(app.clone(), "/createfolder", request.clone()));
+        }
+        let mut statuses = Vec::new();
+        while let Some(response) = requests.join_next().await {
+            statuses.push(response.unwrap().0);
+        }
+
+        assert_eq!(statuses.iter().filter(|s| **s == StatusCode::OK).count(), 1);
+        assert!(
+            statuses
+                .iter()
+                .all(|s| [StatusCode::OK, StatusCode::CONFLICT].contains(s))
+        );
+        assert!(root(&temp).join("docs/race/leaf").is_dir());
+    }
+
+    #[tokio::test]
+    async fn empties_folder_but_not_root() {
+        let (temp, app) = fixture();
+
+        let (status, body) = post_json(
+            app.clone(),
+            "/empty",
+            serde_json::json!({ "path": "/docs" }),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["removed"], 2);
+        assert_eq!(body["bytes"], 11);
+        assert!(root(&temp).join("docs").is_dir());
+        assert_eq!(fs::read_dir(root(&temp).join("docs")).unwrap().count(), 0);
+
+        let (status, body) = post_json(app, "/empty", serde_json::json!({ "path": "/" })).await;
+        assert_error(status, &body, StatusCode::BAD_REQUEST);
+        assert!(root(&temp).join("alpha.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn empties_folder_without_following_links() {
+        let (temp, app) = fixture();
+        fs::create_dir(root(&temp).join("photos/links")).unwrap();
+        let link = root(&temp).join("photos/links/docs");
+        std::os::unix::fs::symlink(root(&temp).join("docs"), &link).unwrap();
+        let link_size = fs::symlink_metadata(&link).unwrap().len();
+
+        let (status, body) = post_json(
+            app,
+            "/empty",
+            serde_json::json!({ "path": "/photos/links" }),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["removed"], 1);
+        assert_eq!(body["bytes"], link_size);
+        assert!(fs::symlink_metadata(&link).is_err());
+        assert!(root(&temp).join("docs/readme.txt").exists());
+        assert!(root(&temp).join("docs/nested/deep-readme.md").exists());
+    }
+
+    #[tokio::test]
+    async fn copies_folder_tree() {
+        let (temp, app) = fixture();
+
+        let request = serde_json::json!({ "source": "docs", "target": "photos" });
+        let (status, body) = post_json(app.clone(), "/copy", request.clone()).await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["file"]["name"], "docs");
+        assert_eq!(body["files_copied"], 2);
+        assert_eq!(body["bytes_copied"], 11);
+        assert_eq!(
+            fs::read_to_string(root(&temp).join("photos/docs/nested/deep-readme.md")).unwrap(),
+            "deep"
+        );
+
+        let (status, body) = post_json(app.clone(), "/copy", request).await;
+        assert_error(status, &body, StatusCode::CONFLICT);
+
+        let request = serde_json::json!({ "source": "docs", "target": "photos", "rename": true });
+        let (status, body) = post_json(app, "/copy", request).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["file"]["name"], "docs (1)");
+    }
+
+    #[tokio::test]
+    async fn refuses_to_copy_folder_into_itself() {
+        let (temp, app) = fixture();
+
+        let request = serde_json::json!({ "source": "docs", "target": "docs/nested" });
+        let (status, body) = post_json(app, "/copy", request).await;
+
+        assert_error(status, &body, StatusCode::BAD_REQUEST);
+        assert!(!root(&temp).join("docs/nested/docs").exists());
+    }
+
+    #[tokio::test]
+    async fn streams_copy_progress() {
+        let (temp, app) = fixture();
+
+        let request = Request::post("/copy")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                serde_json::json!({ "source": "docs", "target": "", "rename": true, "progress": true })
+                    .to_string(),
+            ))
+            .unwrap();
+        let (status, body) = send(app, request).await;
+        let body = String::from_utf8(body).unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        let events: Vec<&str> = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("event: "))
+            .collect();
+        assert_eq!(events, ["progress", "progress", "progress", "complete"]);
+        assert!(
+            body.contains(r#""files_copied":2,"files_total":2,"bytes_copied":11,"bytes_total":11"#)
+        );
+        assert!(root(&temp).join("docs (1)/readme.txt").is_file());
+    }
+
+    #[tokio::test]
+    async fn reads_lines_within_bounds() {
+        let (temp, app) = fixture();
+        // Longer than max_text_file_size, the long line crosses the limit
+        let contents = format!("one\n{}\nthree\n", "x".repeat(2000));
+        fs::write(root(&temp).join("docs/long.txt"), contents).unwrap();
+
+        let (status, body) = get_json(
+            app.clone(),
+            "/file/lines?path=docs/readme.txt&start=18446744073709551615",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["lines"], serde_json::json!([]));
+
+        let (status, body) = get_json(app.clone(), "/file/lines?path=docs/long.txt").await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["lines"], serde_json::json!(["one"]));
+        assert_eq!(body["has_more"], true);
+
+        let (status, body) = get_json(app, "/file/lines?path=docs/long.txt&start=3").await;
+        assert_error(status, &body, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn tails_file_within_size_limit() {
+        let (temp, app) = fixture();
+        // Longer than max_text_file_size, mostly one line
+        let contents = format!("first\n{}\nsecond\nthird\n", "x".repeat(5000));
+        fs::write(root(&temp).join("docs/log.txt"), contents).unwrap();
+
+        for (lines, expected) in [(1, "third"), (2, "second\nthird"), (10, "second\nthird")] {
+            let uri = format!("/file/tail?path=docs/log.txt&lines={}", lines);
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            let (status, body) = send(app.clone(), request).await;
+
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(String::from_utf8(body).unwrap(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn uploads_file() {
+        let (temp, app) = fixture();
+
+        let (status, body) = send(app, multipart_upload("photos", "new.txt", "uploaded")).await;
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["filename"], "new.txt");
+        assert_eq!(body["uploaded_by"], "tester");
+        assert_eq!(body["skipped"], false);
+        assert_eq!(
+            fs::read_to_string(root(&temp).join("photos/new.txt")).unwrap(),
+            "uploaded"
+        );
+    }
+
+    #[tokio::test]
+    async fn uploads_file_larger_than_default_body_limit() {
+        let (temp, app) = fixture();
+        let contents = "x".repeat(3 * 1024 * 1024);
+
+        let (status, body) = send(app, multipart_upload("photos", "large.txt", &contents)).await;
+
+        assert_eq!(status, StatusCode::OK, "{}", String::from_utf8_lossy(&body));
+        assert_eq!(
+            fs::metadata(root(&temp).join("photos/large.txt"))
+                .unwrap()
+                .len(),
+            contents.len() as u64
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_upload_dedup() {
+        use sha2::{Digest, Sha512};
+
+        let (temp, app) = fixture();
+
+        let boundary = "filepi-test-boundary";
+        let readme_hash = format!("{:x}", Sha512::digest(b"read me"));
+        let body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"location\"\r\n\r\ndocs\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"user\"\r\n\r\ntester\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"readme.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\nread me\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"sha512\"\r\n\r\n{readme_hash}\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"new.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\nfresh\r\n\
+             --{b}--\r\n",
+            b = boundary
+        );
+        let request = Request::post("/uploadfile")
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(Body::from(body))
+            .unwrap();
+
+        let (status, body) = send(app.clone(), request).await;
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["results"][0]["skipped"], true);
+        assert_eq!(body["results"][1]["skipped"], false);
+        let expected = serde_json::json!({
+            "files_written": 1,
+            "files_skipped": 1,
+            "bytes_written": 5,
+            "bytes_skipped": 7,
+        });
+        assert_eq!(body["dedup"], expected);
+        assert!(root(&temp).join("docs/new.txt").is_file());
+
+        let (status, body) = get_json(app, "/metrics").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["uploads"], expected);
+    }
+
+    #[tokio::test]
+    async fn refuses_upload_with_duplicate_names() {
+        let (temp, app) = fixture();
+
+        let boundary = "filepi-test-boundary";
+        let body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"location\"\r\n\r\nphotos\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"user\"\r\n\r\ntester\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"on_conflict\"\r\n\r\noverwrite\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"new.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\nfirst\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"new.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\nsecond\r\n\
+             --{b}--\r\n",
+            b = boundary
+        );
+        let request = Request::post("/uploadfile")
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(Body::from(body))
+            .unwrap();
+
+        let (status, body) = send(app, request).await;
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_error(status, &body, StatusCode::BAD_REQUEST);
+        assert!(!root(&temp).join("photos/new.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn upload_conflict_keeps_existing_file() {
+        let (temp, app) = fixture();
+
+        let (status, body) = send(app, multipart_upload("docs", "readme.txt", "changed")).await;
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+        assert_eq!(body["error"]["code"], "already_exists");
+        assert_eq!(
+            fs::read_to_string(root(&temp).join("docs/readme.txt")).unwrap(),
+            "read me"
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_to_upload_outside_root() {
+        let (temp, app) = fixture();
+
+        let (status, body) = send(app, multipart_upload("../escaped", "new.txt", "x")).await;
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_error(status, &body, StatusCode::BAD_REQUEST);
+        assert!(!temp.path().join("escaped").exists());
+    }
+
+    fn syncfusion_upload(action: &str, file_name: &str, contents: &str) -> Request<Body> {
+        let boundary = "filepi-test-boundary";
+        let body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"path\"\r\n\r\n/docs/\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"uploadFiles\"; filename=\"{file_name}\"\r\n\
+             Content-Type: text/plain\r\n\r\n{contents}\r\n\
+             --{b}--\r\n",
+            b = boundary
+        );
+        Request::post(format!("/syncfusion/upload?action={}", action))
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn syncfusion_upload_refuses_names_with_path_components() {
+        let (temp, app) = fixture();
+
+        for file_name in ["../escaped.txt", "sub/new.txt", "..\\new.txt", ".."] {
+            let (status, body) = send(app.clone(), syncfusion_upload("save", file_name, "x")).await;
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_error(status, &body, StatusCode::BAD_REQUEST);
+        }
+
+        assert!(!root(&temp).join("escaped.txt").exists());
+        assert!(!root(&temp).join("docs/sub").exists());
+        assert_eq!(fs::read_dir(root(&temp).join("docs")).unwrap().count(), 2);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn syncfusion_upload_does_not_replace_through_links() {
+        let (temp, app) = fixture();
+        std::os::unix::fs::symlink(
+            temp.path().join("secret.txt"),
+            root(&temp).join("docs/secret.txt"),
+        )
+        .unwrap();
+
+        let (status, body) = send(app, syncfusion_upload("replace", "secret.txt", "x")).await;
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_error(status, &body, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            fs::read_to_string(temp.path().join("secret.txt")).unwrap(),
+            "secret"
+        );
+    }
+}
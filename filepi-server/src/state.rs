@@ -0,0 +1,74 @@
+use axum::extract::FromRef;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::Config;
+use crate::handlers::favorites::FavoriteStore;
+use crate::handlers::listing_cache::ListingCache;
+use crate::handlers::metrics::UploadStats;
+use crate::handlers::tag_store::TagStore;
+
+/// Shared state of the API router. Handlers extract the parts they need,
+/// e.g. `State<Arc<Config>>`, through the `FromRef` impls below.
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Arc<Config>,
+    pub listing_cache: Arc<ListingCache>,
+    pub tag_store: Arc<TagStore>,
+    pub favorites: Arc<FavoriteStore>,
+    pub upload_stats: Arc<UploadStats>,
+    /// Cancelled to start a graceful shutdown
+    pub shutdown: CancellationToken,
+}
+
+impl AppState {
+    pub fn new(config: Arc<Config>, shutdown: CancellationToken) -> Self {
+        let listing_cache = Arc::new(ListingCache::new(&config));
+        let tag_store = Arc::new(TagStore::load(&config));
+        let favorites = Arc::new(FavoriteStore::load(&config));
+        AppState {
+            config,
+            listing_cache,
+            tag_store,
+            favorites,
+            upload_stats: Arc::default(),
+            shutdown,
+        }
+    }
+}
+
+impl FromRef<AppState> for Arc<Config> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<ListingCache> {
+    fn from_ref(state: &AppState) -> Self {
+        state.listing_cache.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<TagStore> {
+    fn from_ref(state: &AppState) -> Self {
+        state.tag_store.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<FavoriteStore> {
+    fn from_ref(state: &AppState) -> Self {
+        state.favorites.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<UploadStats> {
+    fn from_ref(state: &AppState) -> Self {
+        state.upload_stats.clone()
+    }
+}
+
+impl FromRef<AppState> for CancellationToken {
+    fn from_ref(state: &AppState) -> Self {
+        state.shutdown.clone()
+    }
+}
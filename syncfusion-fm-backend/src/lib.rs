@@ -1,15 +1,31 @@
 pub mod models;
+pub mod path_error;
+use filetime::FileTime;
 pub use models::*;
+pub use path_error::PathError;
 use std::fs;
-use std::path::PathBuf;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
 
+/// Handles `request`, leaving the default [`SYSTEM_DIRS`] of `root_dir` out
+/// of listings.
 pub fn process_file_manager_request(
     request: &FileManagerDirectoryContent,
-    root_dir: &PathBuf,
+    root_dir: &Path,
+) -> FileManagerResponse {
+    process_file_manager_request_excluding(request, root_dir, &system_dirs(root_dir))
+}
+
+/// Handles `request`, leaving `system_dirs` out of listings, see
+/// [`is_listed`].
+pub fn process_file_manager_request_excluding(
+    request: &FileManagerDirectoryContent,
+    root_dir: &Path,
+    system_dirs: &[PathBuf],
 ) -> FileManagerResponse {
     let action = request.action.as_deref().unwrap_or("");
     match action {
-        "read" => handle_read(request, root_dir),
+        "read" => handle_read(request, root_dir, system_dirs),
         "create" => handle_create(request, root_dir),
         "delete" => handle_delete(request, root_dir),
         "rename" => handle_rename(request, root_dir),
@@ -21,7 +37,11 @@ pub fn process_file_manager_request(
     }
 }
 
-fn handle_read(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> FileManagerResponse {
+fn handle_read(
+    request: &FileManagerDirectoryContent,
+    root_dir: &Path,
+    system_dirs: &[PathBuf],
+) -> FileManagerResponse {
     let path_str = request.path.as_deref().unwrap_or("");
     let relative_path = if path_str == "/" {
         ""
@@ -32,8 +52,8 @@ fn handle_read(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> Fil
     let full_path = root_dir.join(relative_path);
 
     // Security check
-    if !is_safe_path(&full_path, root_dir) {
-        return create_error_response("400", "Invalid path");
+    if let Err(e) = is_safe_path(&full_path, root_dir) {
+        return path_error_response(&e);
     }
 
     if !full_path.exists() {
@@ -50,63 +70,70 @@ fn handle_read(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> Fil
     };
 
     let show_hidden = request.show_hidden_items;
+    let show_extension = request.show_file_extension;
     let mut files = Vec::new();
 
-    for entry in entries {
-        if let Ok(entry) = entry {
-            let file_name = entry.file_name().to_string_lossy().to_string();
-            if !show_hidden && file_name.starts_with('.') {
-                continue;
-            }
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if !is_listed(&full_path.join(&file_name), show_hidden, system_dirs) {
+            continue;
+        }
 
-            if let Ok(metadata) = entry.metadata() {
-                let is_dir = metadata.is_dir();
-                let file_type = if is_dir {
-                    "Directory".to_string()
-                } else {
-                    get_file_extension(&file_name)
-                };
+        if let Ok(metadata) = entry.metadata() {
+            let is_dir = metadata.is_dir();
+            let file_type = if is_dir {
+                "Directory".to_string()
+            } else {
+                get_file_extension(&file_name)
+            };
 
-                files.push(FileManagerDirectoryContent {
-                    name: Some(file_name),
-                    size: Some(metadata.len() as i64),
-                    is_file: !is_dir,
-                    date_modified: metadata
-                        .modified()
-                        .ok()
-                        .map(Into::into)
-                        .or_else(|| Some(chrono::Utc::now())),
-                    date_created: metadata
-                        .created()
-                        .ok()
-                        .map(Into::into)
-                        .or_else(|| Some(chrono::Utc::now())),
-                    has_child: is_dir,
-                    filter_path: if relative_path.is_empty() {
-                        Some("/".to_string())
-                    } else {
-                        Some(format!("/{}/", relative_path))
-                    },
-                    file_type: Some(if is_dir { "".to_string() } else { file_type }),
-                    permission: Some(get_default_permission()),
-                    path: None,
-                    action: None,
-                    new_name: None,
-                    names: None,
-                    previous_name: None,
-                    id: None,
-                    filter_id: None,
-                    parent_id: None,
-                    target_path: None,
-                    rename_files: None,
-                    case_sensitive: false,
-                    search_string: None,
-                    show_hidden_items: false,
-                    show_file_extension: false,
-                    data: None,
-                    target_data: None,
-                });
-            }
+            // Only the displayed name loses its extension, file_type keeps it
+            let display_name = if is_dir || show_extension {
+                file_name
+            } else {
+                strip_file_extension(&file_name)
+            };
+
+            files.push(FileManagerDirectoryContent {
+                name: Some(display_name),
+                size: Some(metadata.len() as i64),
+                is_file: !is_dir,
+                date_modified: metadata
+                    .modified()
+                    .ok()
+                    .map(Into::into)
+                    .or_else(|| Some(chrono::Utc::now())),
+                date_created: metadata
+                    .created()
+                    .ok()
+                    .map(Into::into)
+                    .or_else(|| Some(chrono::Utc::now())),
+                has_child: is_dir,
+                filter_path: if relative_path.is_empty() {
+                    Some("/".to_string())
+                } else {
+                    Some(format!("/{}/", relative_path))
+                },
+                file_type: Some(if is_dir { "".to_string() } else { file_type }),
+                permission: Some(get_default_permission()),
+                path: None,
+                action: None,
+                new_name: None,
+                names: None,
+                previous_name: None,
+                id: None,
+                filter_id: None,
+                parent_id: None,
+                target_path: None,
+                rename_files: None,
+                case_sensitive: false,
+                search_string: None,
+                show_hidden_items: false,
+                show_file_extension: false,
+                data: None,
+                target_data: None,
+                preserve_timestamps: None,
+            });
         }
     }
 
@@ -117,7 +144,11 @@ fn handle_read(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> Fil
             .unwrap_or("")
             .to_string()
     } else {
-        relative_path.split('/').last().unwrap_or("").to_string()
+        relative_path
+            .split('/')
+            .next_back()
+            .unwrap_or("")
+            .to_string()
     };
 
     let cwd = if let Ok(metadata) = full_path.metadata() {
@@ -159,6 +190,7 @@ fn handle_read(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> Fil
             show_file_extension: false,
             data: None,
             target_data: None,
+            preserve_timestamps: None,
         })
     } else {
         None
@@ -172,7 +204,7 @@ fn handle_read(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> Fil
     }
 }
 
-fn handle_create(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> FileManagerResponse {
+fn handle_create(request: &FileManagerDirectoryContent, root_dir: &Path) -> FileManagerResponse {
     let path_str = request.path.as_deref().unwrap_or("");
     let relative_path = if path_str == "/" {
         ""
@@ -185,10 +217,26 @@ fn handle_create(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> F
         _ => return create_error_response("400", "Folder name is required"),
     };
 
+    // Nested names like "New Folder/Sub" are not supported, the client has to
+    // create each level on its own
+    if name.contains('/') || name.contains('\\') {
+        return create_error_response(
+            "400",
+            &format!(
+                "Invalid folder name '{}': names cannot contain path separators",
+                name
+            ),
+        );
+    }
+
+    if name == "." || name == ".." {
+        return create_error_response("400", &format!("Invalid folder name '{}'", name));
+    }
+
     let full_path = root_dir.join(relative_path).join(name);
 
-    if !is_safe_path(&full_path, root_dir) {
-        return create_error_response("400", "Invalid path");
+    if let Err(e) = is_safe_path(&full_path, root_dir) {
+        return path_error_response(&e);
     }
 
     if full_path.exists() {
@@ -199,6 +247,7 @@ fn handle_create(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> F
                 code: Some("400".to_string()),
                 message: Some("Folder already exists".to_string()),
                 file_exists: Some(vec![name.clone()]),
+                failed_items: None,
             }),
             details: None,
         };
@@ -252,6 +301,7 @@ fn handle_create(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> F
         show_file_extension: false,
         data: None,
         target_data: None,
+        preserve_timestamps: None,
     };
 
     FileManagerResponse {
@@ -262,7 +312,7 @@ fn handle_create(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> F
     }
 }
 
-fn handle_delete(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> FileManagerResponse {
+fn handle_delete(request: &FileManagerDirectoryContent, root_dir: &Path) -> FileManagerResponse {
     let path_str = request.path.as_deref().unwrap_or("");
     let relative_path = if path_str == "/" {
         ""
@@ -275,17 +325,27 @@ fn handle_delete(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> F
         _ => return create_error_response("400", "File names are required"),
     };
 
+    // Every name is attempted, one that is already gone or can't be removed
+    // doesn't stop the rest
     let mut deleted_files = Vec::new();
+    let mut failed = Vec::new();
 
     for name in names {
         let full_path = root_dir.join(relative_path).join(name);
 
-        if !is_safe_path(&full_path, root_dir) {
-            return create_error_response("400", "Invalid path");
+        if let Err(e) = is_safe_path(&full_path, root_dir) {
+            failed.push(failed_item(name, e.code(), e.client_message()));
+            continue;
         }
 
         if !full_path.exists() {
-            return create_error_response("404", "File not found");
+            failed.push(failed_item(name, "404", "File not found"));
+            continue;
+        }
+
+        if is_root_dir(&full_path, root_dir) {
+            failed.push(failed_item(name, "400", ROOT_DIR_MESSAGE));
+            continue;
         }
 
         let is_dir = full_path.is_dir();
@@ -297,7 +357,12 @@ fn handle_delete(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> F
         };
 
         if let Err(e) = result {
-            return create_error_response("500", &format!("Failed to delete {}: {}", name, e));
+            failed.push(failed_item(
+                name,
+                "500",
+                &format!("Failed to delete: {}", e),
+            ));
+            continue;
         }
 
         deleted_files.push(FileManagerDirectoryContent {
@@ -330,18 +395,53 @@ fn handle_delete(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> F
             show_file_extension: false,
             data: None,
             target_data: None,
+            preserve_timestamps: None,
         });
     }
 
+    let error = if failed.is_empty() {
+        None
+    } else {
+        let summary = failed
+            .iter()
+            .map(|item| format!("{} ({})", item.name, item.message))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Some(ErrorDetails {
+            // A single kind of failure keeps its code
+            code: Some(if failed.iter().all(|item| item.code == failed[0].code) {
+                failed[0].code.clone()
+            } else {
+                "500".to_string()
+            }),
+            message: Some(format!(
+                "Failed to delete {} of {} items: {}",
+                failed.len(),
+                names.len(),
+                summary
+            )),
+            file_exists: None,
+            failed_items: Some(failed),
+        })
+    };
+
     FileManagerResponse {
         cwd: None,
         files: Some(deleted_files),
-        error: None,
+        error,
         details: None,
     }
 }
 
-fn handle_rename(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> FileManagerResponse {
+fn failed_item(name: &str, code: &str, message: &str) -> FailedItem {
+    FailedItem {
+        name: name.to_string(),
+        code: code.to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn handle_rename(request: &FileManagerDirectoryContent, root_dir: &Path) -> FileManagerResponse {
     let path_str = request.path.as_deref().unwrap_or("");
     let relative_path = if path_str == "/" {
         ""
@@ -362,14 +462,20 @@ fn handle_rename(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> F
     let old_path = root_dir.join(relative_path).join(name);
     let new_path = root_dir.join(relative_path).join(new_name);
 
-    if !is_safe_path(&old_path, root_dir) || !is_safe_path(&new_path, root_dir) {
-        return create_error_response("400", "Invalid path");
+    if let Err(e) =
+        is_safe_path(&old_path, root_dir).and_then(|_| is_safe_path(&new_path, root_dir))
+    {
+        return path_error_response(&e);
     }
 
     if !old_path.exists() {
         return create_error_response("404", "File not found");
     }
 
+    if is_root_dir(&old_path, root_dir) {
+        return create_error_response("400", ROOT_DIR_MESSAGE);
+    }
+
     if new_path.exists() {
         return FileManagerResponse {
             cwd: None,
@@ -378,6 +484,7 @@ fn handle_rename(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> F
                 code: Some("400".to_string()),
                 message: Some("File already exists".to_string()),
                 file_exists: Some(vec![new_name.clone()]),
+                failed_items: None,
             }),
             details: None,
         };
@@ -437,6 +544,7 @@ fn handle_rename(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> F
         show_file_extension: false,
         data: None,
         target_data: None,
+        preserve_timestamps: None,
     };
 
     FileManagerResponse {
@@ -447,10 +555,7 @@ fn handle_rename(request: &FileManagerDirectoryContent, root_dir: &PathBuf) -> F
     }
 }
 
-fn handle_search(
-    _request: &FileManagerDirectoryContent,
-    _root_dir: &PathBuf,
-) -> FileManagerResponse {
+fn handle_search(_request: &FileManagerDirectoryContent, _root_dir: &Path) -> FileManagerResponse {
     // Placeholder
     FileManagerResponse {
         cwd: None,
@@ -460,30 +565,359 @@ fn handle_search(
     }
 }
 
-fn handle_copy(_request: &FileManagerDirectoryContent, _root_dir: &PathBuf) -> FileManagerResponse {
-    // Placeholder
-    FileManagerResponse {
-        cwd: None,
-        files: Some(vec![]),
-        error: None,
-        details: None,
+fn handle_copy(request: &FileManagerDirectoryContent, root_dir: &Path) -> FileManagerResponse {
+    transfer_items(request, root_dir, Transfer::Copy)
+}
+
+fn handle_move(request: &FileManagerDirectoryContent, root_dir: &Path) -> FileManagerResponse {
+    transfer_items(request, root_dir, Transfer::Move)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Transfer {
+    Copy,
+    Move,
+}
+
+impl Transfer {
+    fn verb(self) -> &'static str {
+        match self {
+            Transfer::Copy => "copy",
+            Transfer::Move => "move",
+        }
     }
 }
 
-fn handle_move(_request: &FileManagerDirectoryContent, _root_dir: &PathBuf) -> FileManagerResponse {
-    // Placeholder
+// Copies or moves `names` from `path` into `target_path`. Items that already
+// exist in the target are reported in `file_exists` unless they are listed in
+// `rename_files`, in which case they get a numbered name like Windows does.
+fn transfer_items(
+    request: &FileManagerDirectoryContent,
+    root_dir: &Path,
+    transfer: Transfer,
+) -> FileManagerResponse {
+    let path_str = request.path.as_deref().unwrap_or("");
+    let relative_path = if path_str == "/" {
+        ""
+    } else {
+        path_str.trim_start_matches('/')
+    };
+
+    let target_str = match request.target_path.as_deref() {
+        Some(target) if !target.is_empty() => target,
+        _ => return create_error_response("400", "Target path is required"),
+    };
+    let target_relative = if target_str == "/" {
+        ""
+    } else {
+        target_str.trim_start_matches('/')
+    };
+
+    let names = match &request.names {
+        Some(n) if !n.is_empty() => n,
+        _ => return create_error_response("400", "File names are required"),
+    };
+
+    let source_dir = root_dir.join(relative_path);
+    let target_dir = root_dir.join(target_relative);
+
+    if let Err(e) =
+        is_safe_path(&source_dir, root_dir).and_then(|_| is_safe_path(&target_dir, root_dir))
+    {
+        return path_error_response(&e);
+    }
+
+    if !target_dir.is_dir() {
+        return create_error_response("404", "Target folder not found");
+    }
+
+    let rename_files = request.rename_files.as_deref().unwrap_or(&[]);
+    let preserve = request.preserve_timestamps.unwrap_or(true);
+    let filter_path = if target_relative.trim_end_matches('/').is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{}/", target_relative.trim_end_matches('/'))
+    };
+
+    let mut existing = Vec::new();
+    let mut transferred = Vec::new();
+
+    for name in names {
+        let source = source_dir.join(name);
+
+        if let Err(e) = is_safe_path(&source, root_dir) {
+            return path_error_response(&e);
+        }
+
+        if source.symlink_metadata().is_err() {
+            return create_error_response("404", "File not found");
+        }
+
+        if transfer == Transfer::Move && is_root_dir(&source, root_dir) {
+            return create_error_response("400", ROOT_DIR_MESSAGE);
+        }
+
+        let rename = rename_files.contains(name);
+        if let Err(message) = check_transfer_target(&source, &target_dir, rename, transfer) {
+            return create_error_response("400", &message);
+        }
+
+        let mut destination = target_dir.join(name);
+        if destination.symlink_metadata().is_ok() {
+            if !rename {
+                existing.push(name.clone());
+                continue;
+            }
+            destination = next_free_path(&target_dir, name);
+        }
+
+        let result = match transfer {
+            Transfer::Copy => {
+                copy_recursively(&source, &destination, preserve, &mut false, &mut |_| Ok(()))
+            }
+            Transfer::Move => move_item(&source, &destination),
+        };
+
+        if let Err(e) = result {
+            return create_error_response(
+                "500",
+                &format!("Failed to {} {}: {}", transfer.verb(), name, e),
+            );
+        }
+
+        match file_entry(&destination, &filter_path) {
+            Ok(mut entry) => {
+                entry.previous_name = Some(name.clone());
+                transferred.push(entry);
+            }
+            Err(e) => {
+                return create_error_response(
+                    "500",
+                    &format!("Failed to read {} metadata: {}", name, e),
+                );
+            }
+        }
+    }
+
+    // The client asks the user about these and retries with rename_files
+    let error = if existing.is_empty() {
+        None
+    } else {
+        Some(ErrorDetails {
+            code: Some("400".to_string()),
+            message: Some("File Already Exists".to_string()),
+            file_exists: Some(existing),
+            failed_items: None,
+        })
+    };
+
     FileManagerResponse {
         cwd: None,
-        files: Some(vec![]),
-        error: None,
+        files: Some(transferred),
+        error,
         details: None,
     }
 }
 
-fn handle_details(
-    _request: &FileManagerDirectoryContent,
-    _root_dir: &PathBuf,
-) -> FileManagerResponse {
+// Refuses transfers that would destroy or endlessly nest the source: a folder
+// into itself or one of its descendants, or an item onto itself. Copying into
+// the same folder is fine when the copy gets a new name.
+fn check_transfer_target(
+    source: &Path,
+    target_dir: &Path,
+    rename: bool,
+    transfer: Transfer,
+) -> Result<(), String> {
+    let canonical_source = source.canonicalize().map_err(|e| {
+        let name = source.file_name().unwrap_or_default().to_string_lossy();
+        format!("Failed to resolve {}: {}", name, e)
+    })?;
+    let canonical_target = target_dir
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve target folder: {}", e))?;
+
+    // A symlink is transferred as the link itself, so compare the link's own
+    // location rather than where it points
+    let source_location = match source.symlink_metadata() {
+        Ok(metadata) if metadata.file_type().is_symlink() => source
+            .parent()
+            .and_then(|parent| parent.canonicalize().ok())
+            .zip(source.file_name())
+            .map(|(parent, name)| parent.join(name))
+            .unwrap_or(canonical_source),
+        _ => canonical_source,
+    };
+
+    if source_location.is_dir()
+        && !source.is_symlink()
+        && canonical_target.starts_with(&source_location)
+    {
+        return Err(format!(
+            "Cannot {} a folder into itself or one of its subfolders",
+            transfer.verb()
+        ));
+    }
+
+    let same_folder = source_location.parent() == Some(canonical_target.as_path());
+    if same_folder && (transfer == Transfer::Move || !rename) {
+        return Err(format!("Cannot {} an item onto itself", transfer.verb()));
+    }
+
+    Ok(())
+}
+
+/// `name (1).ext`, `name (2).ext`, ... in `dir`, whichever doesn't exist yet
+pub fn next_free_path(dir: &Path, name: &str) -> PathBuf {
+    let path = Path::new(name);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| name.to_string());
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| candidate.symlink_metadata().is_err())
+        .expect("ran out of numbered names")
+}
+
+/// Copies a file, link or folder tree to `destination` the way the `copy`
+/// action does, for handlers outside this crate that report progress.
+///
+/// `on_copied` is called with the size of every file once it's written,
+/// links count as 0 bytes. An error it returns stops the copy. `destination`
+/// is created exclusively, so one that appeared meanwhile fails the copy with
+/// `AlreadyExists` and is left alone. Otherwise a failed copy is removed again.
+pub fn copy_item(
+    source: &Path,
+    destination: &Path,
+    preserve: bool,
+    on_copied: &mut dyn FnMut(u64) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let mut created = false;
+    copy_recursively(source, destination, preserve, &mut created, on_copied).inspect_err(|_| {
+        // The copy error is the one worth reporting
+        if created {
+            let _ = remove_item(destination);
+        }
+    })
+}
+
+// Copies a file, link or whole folder tree. Links are copied as links so a
+// link to outside the root doesn't drag its target in. With `preserve` the
+// copies keep the source's access and modification times, and folders their
+// permissions (files always keep theirs). Nothing that already exists is
+// replaced, `created` is set once `destination` itself was made by this call.
+fn copy_recursively(
+    source: &Path,
+    destination: &Path,
+    preserve: bool,
+    created: &mut bool,
+    on_copied: &mut dyn FnMut(u64) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let metadata = source.symlink_metadata()?;
+    let file_type = metadata.file_type();
+
+    if file_type.is_symlink() {
+        copy_symlink(source, destination)?;
+        *created = true;
+        on_copied(0)?;
+    } else if file_type.is_dir() {
+        fs::create_dir(destination)?;
+        *created = true;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursively(
+                &entry.path(),
+                &destination.join(entry.file_name()),
+                preserve,
+                &mut false,
+                on_copied,
+            )?;
+        }
+        if preserve {
+            fs::set_permissions(destination, metadata.permissions())?;
+        }
+    } else {
+        let mut reader = fs::File::open(source)?;
+        let mut writer = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(destination)?;
+        *created = true;
+        let bytes = std::io::copy(&mut reader, &mut writer)?;
+        writer.set_permissions(metadata.permissions())?;
+        on_copied(bytes)?;
+    }
+
+    // Last, as creating the children above touches a folder's mtime
+    if preserve {
+        let atime = FileTime::from_last_access_time(&metadata);
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        if file_type.is_symlink() {
+            filetime::set_symlink_file_times(destination, atime, mtime)?;
+        } else {
+            filetime::set_file_times(destination, atime, mtime)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(source: &Path, destination: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, destination)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(source: &Path, destination: &Path) -> std::io::Result<()> {
+    fs::copy(source, destination).map(|_| ())
+}
+
+// Renames when possible and falls back to copy and delete across filesystems.
+// A failed copy is cleaned up so only the untouched source remains. When the
+// copy succeeded but the source can't be removed, the error says so: the
+// complete copy is in the target and (some of) the original is still in place.
+fn move_item(source: &Path, destination: &Path) -> std::io::Result<()> {
+    match fs::rename(source, destination) {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+            let mut created = false;
+            if let Err(e) =
+                copy_recursively(source, destination, true, &mut created, &mut |_| Ok(()))
+            {
+                // The copy error is the one worth reporting
+                if created {
+                    let _ = remove_item(destination);
+                }
+                return Err(e);
+            }
+            remove_item(source).map_err(|e| {
+                std::io::Error::new(
+                    e.kind(),
+                    format!(
+                        "copied to the target folder, but removing the original failed: {}",
+                        e
+                    ),
+                )
+            })
+        }
+        result => result,
+    }
+}
+
+fn remove_item(path: &Path) -> std::io::Result<()> {
+    match path.symlink_metadata() {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn handle_details(_request: &FileManagerDirectoryContent, _root_dir: &Path) -> FileManagerResponse {
     // Placeholder
     FileManagerResponse {
         cwd: None,
@@ -503,40 +937,217 @@ fn create_error_response(code: &str, message: &str) -> FileManagerResponse {
             code: Some(code.to_string()),
             message: Some(message.to_string()),
             file_exists: None,
+            failed_items: None,
         }),
         details: None,
     }
 }
 
-pub fn validate_path(root_dir: &PathBuf, relative_path: &str) -> Result<PathBuf, String> {
+pub fn validate_path(root_dir: &Path, relative_path: &str) -> Result<PathBuf, PathError> {
+    check_relative_components(Path::new(relative_path), relative_path)?;
     let full_path = root_dir.join(relative_path);
-    if !is_safe_path(&full_path, root_dir) {
-        return Err("Invalid path".to_string());
-    }
+    is_safe_path(&full_path, root_dir)?;
     Ok(full_path)
 }
 
-fn is_safe_path(path: &PathBuf, root: &PathBuf) -> bool {
-    match path.canonicalize() {
-        Ok(canonical_path) => match root.canonicalize() {
-            Ok(canonical_root) => canonical_path.starts_with(canonical_root),
-            Err(_) => false,
-        },
-        Err(_) => {
-            // If path doesn't exist (e.g. creating new file), check parent
-            if let Some(parent) = path.parent() {
-                match parent.canonicalize() {
-                    Ok(canonical_parent) => match root.canonicalize() {
-                        Ok(canonical_root) => canonical_parent.starts_with(canonical_root),
-                        Err(_) => false,
-                    },
-                    Err(_) => false,
+/// Folders directly in the root that the server keeps its own data in, such
+/// as thumbnails. A folder with the same name further down is an ordinary
+/// folder.
+pub const SYSTEM_DIRS: [&str; 2] = [CACHE_DIR, TRASH_DIR];
+
+/// Default folder for thumbnails and other generated files
+pub const CACHE_DIR: &str = ".cache";
+
+/// Folder deleted items are kept in
+pub const TRASH_DIR: &str = ".trash";
+
+/// The [`SYSTEM_DIRS`] of `root_dir`, as [`is_listed`] takes them.
+pub fn system_dirs(root_dir: &Path) -> Vec<PathBuf> {
+    SYSTEM_DIRS.iter().map(|dir| root_dir.join(dir)).collect()
+}
+
+/// Whether the entry at `path` belongs in a listing or search. Hidden (dot)
+/// entries only with `show_hidden`, the `system_dirs` never, whatever the
+/// hidden items setting says. Paths are compared as given, so `path` has to
+/// be built from the same root as `system_dirs`. Walks should skip
+/// everything below an entry that isn't listed.
+pub fn is_listed(path: &Path, show_hidden: bool, system_dirs: &[PathBuf]) -> bool {
+    let hidden = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    !system_dirs.iter().any(|dir| dir == path) && (show_hidden || !hidden)
+}
+
+/// Describes the file or folder at `path` the way a `read` lists it, for
+/// handlers outside this crate that report what they created.
+pub fn file_entry(path: &Path, filter_path: &str) -> std::io::Result<FileManagerDirectoryContent> {
+    let metadata = path.metadata()?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let is_dir = metadata.is_dir();
+
+    Ok(FileManagerDirectoryContent {
+        size: Some(metadata.len() as i64),
+        is_file: !is_dir,
+        date_modified: metadata
+            .modified()
+            .ok()
+            .map(Into::into)
+            .or_else(|| Some(chrono::Utc::now())),
+        date_created: metadata
+            .created()
+            .ok()
+            .map(Into::into)
+            .or_else(|| Some(chrono::Utc::now())),
+        has_child: is_dir,
+        filter_path: Some(filter_path.to_string()),
+        file_type: Some(if is_dir {
+            "".to_string()
+        } else {
+            get_file_extension(&name)
+        }),
+        name: Some(name),
+        permission: Some(get_default_permission()),
+        path: None,
+        action: None,
+        new_name: None,
+        names: None,
+        previous_name: None,
+        id: None,
+        filter_id: None,
+        parent_id: None,
+        target_path: None,
+        rename_files: None,
+        case_sensitive: false,
+        search_string: None,
+        show_hidden_items: false,
+        show_file_extension: false,
+        data: None,
+        target_data: None,
+        preserve_timestamps: None,
+    })
+}
+
+/// Like [`validate_path`], but also creates the directory and its missing
+/// parents, returning its canonical path.
+///
+/// The created directory is checked again once it exists, so a symlink
+/// swapped in between the validation and the create can't move it outside
+/// `root_dir`.
+pub fn create_dir_within_root(root_dir: &Path, relative_path: &str) -> Result<PathBuf, PathError> {
+    let full_path = validate_path(root_dir, relative_path)?;
+    std::fs::create_dir_all(&full_path).map_err(|e| path_io_error(relative_path.to_string(), e))?;
+
+    let canonical_path = full_path
+        .canonicalize()
+        .map_err(|e| path_io_error(relative_path.to_string(), e))?;
+    let canonical_root = root_dir
+        .canonicalize()
+        .map_err(|e| path_io_error(relative_path.to_string(), e))?;
+    if !canonical_path.starts_with(&canonical_root) {
+        return Err(PathError::OutsideRoot(relative_path.to_string()));
+    }
+
+    Ok(canonical_path)
+}
+
+/// Checks that `path` stays inside `root`.
+///
+/// Note that this is a check, not a lock: the filesystem can still change
+/// between this call and the operation that follows it. Rejecting `..` and
+/// absolute components up front means a later symlink swap is the only way
+/// out, and that is caught for everything that already exists here.
+fn is_safe_path(path: &Path, root: &Path) -> Result<(), PathError> {
+    let attempted = path
+        .strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string();
+
+    // An absolute input replaces the root when joined, so it no longer has
+    // the root as a prefix
+    let relative = path
+        .strip_prefix(root)
+        .map_err(|_| PathError::OutsideRoot(attempted.clone()))?;
+    check_relative_components(relative, &attempted)?;
+
+    let canonical_root = root.canonicalize().map_err(|source| PathError::Io {
+        path: attempted.clone(),
+        source,
+    })?;
+
+    // If path doesn't exist (e.g. creating new file or nested folders), check
+    // the closest ancestor that does. The missing components were checked
+    // above so they cannot climb back out of it.
+    let mut existing = path;
+    let canonical_path = loop {
+        match existing.canonicalize() {
+            Ok(canonical_path) => break canonical_path,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                // A dangling symlink would let the following create follow it
+                // wherever it points
+                if existing.symlink_metadata().is_ok() {
+                    return Err(PathError::OutsideRoot(attempted));
                 }
-            } else {
-                false
+                existing = match existing.parent() {
+                    Some(parent) => parent,
+                    None => return Err(PathError::NotFound(attempted)),
+                };
+            }
+            Err(e) => return Err(path_io_error(attempted, e)),
+        }
+    };
+
+    if !canonical_path.starts_with(&canonical_root) {
+        return Err(PathError::OutsideRoot(attempted));
+    }
+
+    Ok(())
+}
+
+const ROOT_DIR_MESSAGE: &str = "The root folder can't be deleted, renamed or moved";
+
+// Whether `path` names the root directory itself, e.g. through an empty or "."
+// name. The last component is not resolved, so a link pointing at the root
+// is still just a link.
+fn is_root_dir(path: &Path, root: &Path) -> bool {
+    let Ok(canonical_root) = root.canonicalize() else {
+        return false;
+    };
+    let resolved = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent.canonicalize().map(|parent| parent.join(name)),
+        _ => path.canonicalize(),
+    };
+    resolved.is_ok_and(|path| path == canonical_root)
+}
+
+fn check_relative_components(relative: &Path, attempted: &str) -> Result<(), PathError> {
+    for component in relative.components() {
+        match component {
+            Component::Normal(_) | Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(PathError::OutsideRoot(attempted.to_string()));
             }
         }
     }
+    Ok(())
+}
+
+fn path_io_error(attempted: String, source: std::io::Error) -> PathError {
+    match source.kind() {
+        ErrorKind::NotFound => PathError::NotFound(attempted),
+        ErrorKind::NotADirectory => PathError::NotADirectory(attempted),
+        _ => PathError::Io {
+            path: attempted,
+            source,
+        },
+    }
+}
+
+fn path_error_response(error: &PathError) -> FileManagerResponse {
+    create_error_response(error.code(), error.client_message())
 }
 
 fn get_file_extension(filename: &str) -> String {
@@ -547,6 +1158,14 @@ fn get_file_extension(filename: &str) -> String {
         .unwrap_or_else(|| "file".to_string())
 }
 
+fn strip_file_extension(filename: &str) -> String {
+    std::path::Path::new(filename)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(filename)
+        .to_string()
+}
+
 fn get_default_permission() -> AccessPermission {
     AccessPermission {
         read: true,
@@ -558,3 +1177,417 @@ fn get_default_permission() -> AccessPermission {
         message: String::new(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn fixture() -> (TempDir, PathBuf) {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path().join("root");
+        fs::create_dir_all(root.join("docs/nested")).unwrap();
+        fs::write(root.join("docs/readme.txt"), "hello").unwrap();
+        fs::create_dir_all(tmp.path().join("outside")).unwrap();
+        fs::write(tmp.path().join("outside/secret.txt"), "secret").unwrap();
+        (tmp, root)
+    }
+
+    #[test]
+    fn accepts_existing_paths() {
+        let (_tmp, root) = fixture();
+        assert!(validate_path(&root, "").is_ok());
+        assert!(validate_path(&root, "docs/readme.txt").is_ok());
+        assert!(validate_path(&root, "./docs/nested").is_ok());
+    }
+
+    #[test]
+    fn accepts_nested_creates() {
+        let (_tmp, root) = fixture();
+        let path = validate_path(&root, "docs/new/deeper/folder").unwrap();
+        assert_eq!(path, root.join("docs/new/deeper/folder"));
+        assert!(validate_path(&root, "brand-new.txt").is_ok());
+    }
+
+    #[test]
+    fn rejects_parent_dir_escape() {
+        let (_tmp, root) = fixture();
+        for attempt in [
+            "../outside",
+            "../outside/secret.txt",
+            "docs/../../outside",
+            "..",
+        ] {
+            assert!(
+                matches!(
+                    validate_path(&root, attempt),
+                    Err(PathError::OutsideRoot(_))
+                ),
+                "{} should be rejected",
+                attempt
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_parent_dir_even_when_it_stays_inside() {
+        let (_tmp, root) = fixture();
+        assert!(matches!(
+            validate_path(&root, "docs/../docs/readme.txt"),
+            Err(PathError::OutsideRoot(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_parent_dir_in_joined_leaf() {
+        let (_tmp, root) = fixture();
+        let path = root.join("docs").join("../../outside/new.txt");
+        assert!(matches!(
+            is_safe_path(&path, &root),
+            Err(PathError::OutsideRoot(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        let (tmp, root) = fixture();
+        let outside = tmp.path().join("outside/secret.txt");
+        assert!(matches!(
+            validate_path(&root, outside.to_str().unwrap()),
+            Err(PathError::OutsideRoot(_))
+        ));
+        assert!(matches!(
+            is_safe_path(&root.join(&outside), &root),
+            Err(PathError::OutsideRoot(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlink_to_outside() {
+        let (tmp, root) = fixture();
+        std::os::unix::fs::symlink(tmp.path().join("outside"), root.join("escape")).unwrap();
+        assert!(matches!(
+            validate_path(&root, "escape/secret.txt"),
+            Err(PathError::OutsideRoot(_))
+        ));
+        assert!(matches!(
+            validate_path(&root, "escape/new.txt"),
+            Err(PathError::OutsideRoot(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_dangling_symlink() {
+        let (tmp, root) = fixture();
+        std::os::unix::fs::symlink(
+            tmp.path().join("outside/missing.txt"),
+            root.join("dangling"),
+        )
+        .unwrap();
+        assert!(matches!(
+            validate_path(&root, "dangling"),
+            Err(PathError::OutsideRoot(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn accepts_symlink_within_root() {
+        let (_tmp, root) = fixture();
+        std::os::unix::fs::symlink(root.join("docs"), root.join("docs-link")).unwrap();
+        assert!(validate_path(&root, "docs-link/readme.txt").is_ok());
+    }
+
+    fn transfer_request(
+        action: &str,
+        path: &str,
+        names: &[&str],
+        target_path: &str,
+    ) -> FileManagerDirectoryContent {
+        FileManagerDirectoryContent {
+            path: Some(path.to_string()),
+            action: Some(action.to_string()),
+            new_name: None,
+            names: Some(names.iter().map(|n| n.to_string()).collect()),
+            name: None,
+            size: None,
+            previous_name: None,
+            date_modified: None,
+            date_created: None,
+            has_child: false,
+            is_file: false,
+            file_type: None,
+            id: None,
+            filter_path: None,
+            filter_id: None,
+            parent_id: None,
+            target_path: Some(target_path.to_string()),
+            rename_files: None,
+            case_sensitive: false,
+            search_string: None,
+            show_hidden_items: false,
+            show_file_extension: false,
+            data: None,
+            target_data: None,
+            preserve_timestamps: None,
+            permission: None,
+        }
+    }
+
+    fn error_code(response: &FileManagerResponse) -> Option<&str> {
+        response.error.as_ref().and_then(|e| e.code.as_deref())
+    }
+
+    #[test]
+    fn copies_folder_tree() {
+        let (_tmp, root) = fixture();
+        fs::create_dir_all(root.join("backup")).unwrap();
+        let response = process_file_manager_request(
+            &transfer_request("copy", "/", &["docs"], "/backup/"),
+            &root,
+        );
+        assert!(response.error.is_none());
+        assert_eq!(
+            fs::read_to_string(root.join("backup/docs/readme.txt")).unwrap(),
+            "hello"
+        );
+        assert!(root.join("docs/readme.txt").exists());
+    }
+
+    #[test]
+    fn copy_leaves_destination_created_meanwhile() {
+        let (tmp, root) = fixture();
+        let target = tmp.path().join("target");
+        fs::create_dir(&target).unwrap();
+        // Someone else's data under the name the copy was about to use
+        fs::create_dir(target.join("docs")).unwrap();
+        fs::write(target.join("docs/theirs.txt"), "theirs").unwrap();
+        fs::write(target.join("readme.txt"), "theirs").unwrap();
+
+        for (source, destination) in [("docs", "docs"), ("docs/readme.txt", "readme.txt")] {
+            let result = copy_item(
+                &root.join(source),
+                &target.join(destination),
+                false,
+                &mut |_| Ok(()),
+            );
+            assert_eq!(result.unwrap_err().kind(), ErrorKind::AlreadyExists);
+        }
+
+        assert_eq!(
+            fs::read_to_string(target.join("docs/theirs.txt")).unwrap(),
+            "theirs"
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("readme.txt")).unwrap(),
+            "theirs"
+        );
+    }
+
+    #[test]
+    fn rejects_copy_into_own_descendant() {
+        let (_tmp, root) = fixture();
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        let response = process_file_manager_request(
+            &transfer_request("copy", "/a/", &["b"], "/a/b/c/"),
+            &root,
+        );
+        assert_eq!(error_code(&response), Some("400"));
+        assert_eq!(fs::read_dir(root.join("a/b/c")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn rejects_move_into_itself() {
+        let (_tmp, root) = fixture();
+        let response = process_file_manager_request(
+            &transfer_request("move", "/", &["docs"], "/docs/"),
+            &root,
+        );
+        assert_eq!(error_code(&response), Some("400"));
+        assert!(root.join("docs/readme.txt").exists());
+    }
+
+    #[test]
+    fn rejects_transfer_onto_itself() {
+        let (_tmp, root) = fixture();
+        for action in ["copy", "move"] {
+            let response = process_file_manager_request(
+                &transfer_request(action, "/docs/", &["readme.txt"], "/docs/"),
+                &root,
+            );
+            assert_eq!(error_code(&response), Some("400"), "{}", action);
+            assert_eq!(
+                fs::read_to_string(root.join("docs/readme.txt")).unwrap(),
+                "hello"
+            );
+        }
+    }
+
+    #[test]
+    fn copies_into_same_folder_under_new_name() {
+        let (_tmp, root) = fixture();
+        let mut request = transfer_request("copy", "/docs/", &["readme.txt"], "/docs/");
+        request.rename_files = Some(vec!["readme.txt".to_string()]);
+        let response = process_file_manager_request(&request, &root);
+        assert!(response.error.is_none());
+        assert!(root.join("docs/readme (1).txt").exists());
+    }
+
+    #[test]
+    fn copies_keep_source_timestamps() {
+        let (_tmp, root) = fixture();
+        let mtime = FileTime::from_unix_time(1_600_000_000, 0);
+        filetime::set_file_mtime(root.join("docs/readme.txt"), mtime).unwrap();
+        filetime::set_file_mtime(root.join("docs"), mtime).unwrap();
+        fs::create_dir_all(root.join("backup")).unwrap();
+
+        let response = process_file_manager_request(
+            &transfer_request("copy", "/", &["docs"], "/backup/"),
+            &root,
+        );
+        assert!(response.error.is_none());
+        for copied in ["backup/docs", "backup/docs/readme.txt"] {
+            let metadata = fs::metadata(root.join(copied)).unwrap();
+            assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+        }
+    }
+
+    #[test]
+    fn creates_upload_directory_within_root() {
+        let (_tmp, root) = fixture();
+        let created = create_dir_within_root(&root, "docs/uploads/2024").unwrap();
+        assert!(created.is_dir());
+        assert!(created.starts_with(root.canonicalize().unwrap()));
+    }
+
+    #[test]
+    fn rejects_upload_directory_outside_root() {
+        let (tmp, root) = fixture();
+        assert!(matches!(
+            create_dir_within_root(&root, "../outside"),
+            Err(PathError::OutsideRoot(_))
+        ));
+        assert!(matches!(
+            create_dir_within_root(&root, "../outside/new"),
+            Err(PathError::OutsideRoot(_))
+        ));
+        assert!(!tmp.path().join("outside/new").exists());
+    }
+
+    #[test]
+    fn deletes_remaining_items_when_one_fails() {
+        let (_tmp, root) = fixture();
+        fs::write(root.join("docs/other.txt"), "other").unwrap();
+        let request = transfer_request(
+            "delete",
+            "/docs/",
+            &["readme.txt", "missing.txt", "other.txt"],
+            "",
+        );
+
+        let response = process_file_manager_request(&request, &root);
+        let deleted: Vec<_> = response
+            .files
+            .iter()
+            .flatten()
+            .filter_map(|file| file.name.as_deref())
+            .collect();
+        assert_eq!(deleted, ["readme.txt", "other.txt"]);
+        assert!(!root.join("docs/readme.txt").exists());
+        assert!(!root.join("docs/other.txt").exists());
+
+        let error = response.error.unwrap();
+        assert_eq!(error.code.as_deref(), Some("404"));
+        let failed = error.failed_items.unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].name, "missing.txt");
+    }
+
+    #[test]
+    fn refuses_to_delete_or_move_the_root() {
+        let (_tmp, root) = fixture();
+        for (action, names) in [("delete", [""]), ("delete", ["."]), ("move", ["."])] {
+            let request = transfer_request(action, "/", &names, "/docs/");
+            let response = process_file_manager_request(&request, &root);
+            assert_eq!(error_code(&response), Some("400"), "{} {:?}", action, names);
+        }
+        assert!(root.join("docs/readme.txt").exists());
+    }
+
+    #[test]
+    fn refuses_to_rename_the_root() {
+        let (_tmp, root) = fixture();
+        let mut request = transfer_request("rename", "/", &[], "");
+        request.name = Some(".".to_string());
+        request.new_name = Some("renamed".to_string());
+        let response = process_file_manager_request(&request, &root);
+        assert_eq!(error_code(&response), Some("400"));
+        assert!(root.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn deletes_link_to_the_root() {
+        let (_tmp, root) = fixture();
+        std::os::unix::fs::symlink(&root, root.join("root-link")).unwrap();
+        let request = transfer_request("delete", "/", &["root-link"], "");
+        let response = process_file_manager_request(&request, &root);
+        assert!(response.error.is_none());
+        assert!(root.join("docs/readme.txt").exists());
+    }
+
+    #[test]
+    fn reports_file_used_as_directory() {
+        let (_tmp, root) = fixture();
+        assert!(matches!(
+            validate_path(&root, "docs/readme.txt/child"),
+            Err(PathError::NotADirectory(_))
+        ));
+    }
+
+    #[test]
+    fn read_hides_system_folders_even_with_hidden_items() {
+        let (_tmp, root) = fixture();
+        for dir in [".cache", ".trash", ".config"] {
+            fs::create_dir(root.join(dir)).unwrap();
+        }
+
+        let listed = |show_hidden_items: bool| {
+            let mut request = transfer_request("read", "/", &[], "");
+            request.show_hidden_items = show_hidden_items;
+            let response = process_file_manager_request(&request, &root);
+            let mut names: Vec<String> = response
+                .files
+                .unwrap()
+                .into_iter()
+                .filter_map(|file| file.name)
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(listed(false), ["docs"]);
+        assert_eq!(listed(true), [".config", "docs"]);
+    }
+
+    #[test]
+    fn read_lists_nested_folders_named_like_system_folders() {
+        let (_tmp, root) = fixture();
+        fs::create_dir(root.join("docs/.cache")).unwrap();
+
+        let mut request = transfer_request("read", "/docs/", &[], "");
+        request.show_hidden_items = true;
+        let response = process_file_manager_request(&request, &root);
+        let names: Vec<String> = response
+            .files
+            .unwrap()
+            .into_iter()
+            .filter_map(|file| file.name)
+            .collect();
+
+        assert!(names.contains(&".cache".to_string()), "{:?}", names);
+    }
+}
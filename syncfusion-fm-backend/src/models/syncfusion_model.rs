@@ -62,6 +62,9 @@ pub struct FileManagerDirectoryContent {
     pub data: Option<Vec<FileManagerDirectoryContent>>,
     pub target_data: Option<Box<FileManagerDirectoryContent>>,
     pub permission: Option<AccessPermission>,
+    /// Not part of the Syncfusion protocol: copies keep the source timestamps
+    /// and permissions unless this is `false`
+    pub preserve_timestamps: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -83,6 +86,18 @@ pub struct ErrorDetails {
     pub code: Option<String>,
     pub message: Option<String>,
     pub file_exists: Option<Vec<String>>,
+    /// Not part of the Syncfusion protocol: the items a batch operation
+    /// couldn't handle while it went on with the others
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_items: Option<Vec<FailedItem>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedItem {
+    pub name: String,
+    pub code: String,
+    pub message: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -0,0 +1,50 @@
+use thiserror::Error;
+
+/// Reasons a client supplied path can be rejected. Every variant carries the
+/// attempted path (relative to the root when possible) so it can be logged.
+#[derive(Debug, Error)]
+pub enum PathError {
+    #[error("path '{0}' resolves outside the root directory")]
+    OutsideRoot(String),
+    #[error("path '{0}' not found")]
+    NotFound(String),
+    #[error("path '{0}' is not a directory")]
+    NotADirectory(String),
+    #[error("failed to resolve path '{path}': {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl PathError {
+    /// The attempted path, for logging.
+    pub fn path(&self) -> &str {
+        match self {
+            PathError::OutsideRoot(path)
+            | PathError::NotFound(path)
+            | PathError::NotADirectory(path) => path,
+            PathError::Io { path, .. } => path,
+        }
+    }
+
+    /// Status code the Syncfusion error response should carry.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PathError::OutsideRoot(_) | PathError::NotADirectory(_) => "400",
+            PathError::NotFound(_) => "404",
+            PathError::Io { .. } => "500",
+        }
+    }
+
+    /// Message that is safe to send back to the client.
+    pub fn client_message(&self) -> &'static str {
+        match self {
+            PathError::OutsideRoot(_) => "Invalid path",
+            PathError::NotFound(_) => "Path not found",
+            PathError::NotADirectory(_) => "Path is not a directory",
+            PathError::Io { .. } => "Failed to resolve path",
+        }
+    }
+}